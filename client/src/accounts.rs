@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use crate::action_log::ActionLog;
+use crate::clock::Clock;
+use crate::event_loop;
+use crate::llm::{ImageBackend, LlmBackend};
+use crate::mentions::MentionCursor;
+use crate::settings::AgentSettings;
+use crate::status::{AccountStatus, AgentStatus};
+use crate::twitter::{
+    auth::UserAuth, builder::TwitterClient, info::UserInfo, rate_limit::RateLimiter,
+};
+use crate::watchdog::{Watchdog, WatchdogReport};
+
+/// An account whose owner completed a login.
+pub struct AuthorizedAccount {
+    pub user: UserInfo,
+    pub auth: UserAuth,
+}
+
+/// What one account's agent runs with. Each account gets its own, so agents
+/// never share a watchdog baseline.
+pub struct AgentConfig {
+    pub llm: Arc<dyn LlmBackend>,
+    pub images: Option<Arc<dyn ImageBackend>>,
+    pub action_log: Arc<ActionLog>,
+    pub watchdog: Arc<Watchdog>,
+    pub status: Arc<AgentStatus>,
+    pub settings: Arc<AgentSettings>,
+    pub clock: Arc<dyn Clock>,
+    pub mention_cursor: Arc<MentionCursor>,
+}
+
+/// Served at `/accounts`.
+#[derive(Serialize, Debug, Clone)]
+pub struct AccountSummary {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    /// False once the agent has stopped, e.g. because access was revoked.
+    pub running: bool,
+}
+
+struct RunningAgent {
+    user: UserInfo,
+    watchdog: Arc<Watchdog>,
+    status: Arc<AgentStatus>,
+    rate_limits: Arc<RateLimiter>,
+    /// The client's OAuth 2.0 refresh lock, taken before stopping the agent.
+    refreshing: Option<Arc<tokio::sync::Mutex<()>>>,
+    /// `None` once the agent is being stopped.
+    task: Option<JoinHandle<()>>,
+}
+
+impl RunningAgent {
+    /// Leaves the agent listed, but for the caller to stop.
+    fn take_task(&mut self) -> Option<StoppingAgent> {
+        Some(StoppingAgent {
+            refreshing: self.refreshing.clone(),
+            task: self.task.take()?,
+        })
+    }
+
+    fn summary(&self) -> AccountSummary {
+        AccountSummary {
+            id: self.user.id.clone(),
+            username: self.user.username.clone(),
+            name: self.user.name.clone(),
+            running: self.task.as_ref().is_some_and(|task| !task.is_finished()),
+        }
+    }
+}
+
+/// The agents acting as each logged-in account, keyed by Twitter user ID.
+#[derive(Default)]
+pub struct Agents {
+    running: Mutex<BTreeMap<String, RunningAgent>>,
+    /// Set by [`Agents::stop_all`]; no agent starts after it.
+    stopped: AtomicBool,
+}
+
+impl Agents {
+    /// Starts an agent acting as `user` through `twitter_client`. A later
+    /// login to the same account replaces its agent, since only the newest
+    /// credentials are guaranteed to still work; the old one is stopped first,
+    /// like [`Agents::stop_all`] stops it. Does nothing once the agents have
+    /// been stopped.
+    pub async fn spawn(
+        &self,
+        user: UserInfo,
+        twitter_client: TwitterClient<'static>,
+        config: AgentConfig,
+    ) {
+        // Loops in case a concurrent login to the account started an agent
+        // while the previous one was stopping
+        loop {
+            let previous = {
+                let mut running = self.running.lock().unwrap();
+                if self.stopped.load(Ordering::SeqCst) {
+                    log::warn!(
+                        "Not starting an agent for @{}; agents are stopped",
+                        user.username
+                    );
+                    return;
+                }
+                match running.get_mut(&user.id).and_then(RunningAgent::take_task) {
+                    Some(previous) => previous,
+                    None => {
+                        let agent = start(user, twitter_client, config);
+                        running.insert(agent.user.id.clone(), agent);
+                        return;
+                    }
+                }
+            };
+            previous.stop().await;
+        }
+    }
+
+    /// Whether [`Agents::stop_all`] has been called.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    pub fn accounts(&self) -> Vec<AccountSummary> {
+        self.running
+            .lock()
+            .unwrap()
+            .values()
+            .map(RunningAgent::summary)
+            .collect()
+    }
+
+    pub fn statuses(&self) -> Vec<AccountStatus> {
+        self.running
+            .lock()
+            .unwrap()
+            .values()
+            .map(|agent| AccountStatus {
+                account: agent.summary(),
+                activity: agent.status.activity(),
+                rate_limits: agent.rate_limits.budgets().into_iter().collect(),
+            })
+            .collect()
+    }
+
+    /// Each account's watchdog report, keyed by user ID.
+    pub fn watchdog_reports(&self) -> BTreeMap<String, WatchdogReport> {
+        self.running
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, agent)| (id.clone(), agent.watchdog.report()))
+            .collect()
+    }
+
+    /// Stops every agent, including its token refreshes, and returns once
+    /// none is left running. A refresh already under way finishes first, so
+    /// its rotated token is saved. No agent starts afterwards; the accounts
+    /// stay listed.
+    pub async fn stop_all(&self) {
+        let stopping: Vec<_> = {
+            let mut running = self.running.lock().unwrap();
+            self.stopped.store(true, Ordering::SeqCst);
+            running
+                .values_mut()
+                .filter_map(RunningAgent::take_task)
+                .collect()
+        };
+        for agent in stopping {
+            agent.stop().await;
+        }
+    }
+}
+
+/// Spawns the task running `user`'s agent.
+fn start(
+    user: UserInfo,
+    twitter_client: TwitterClient<'static>,
+    config: AgentConfig,
+) -> RunningAgent {
+    let user_id = user.id.clone();
+    let watchdog = config.watchdog.clone();
+    let status = config.status.clone();
+    let rate_limits = twitter_client.rate_limiter();
+    let refreshing = twitter_client.oauth2_refresh_lock();
+    let task = tokio::spawn(async move {
+        let result = event_loop::event_loop(twitter_client, config).await;
+        if let Err(e) = result {
+            log::error!("Agent for account {} stopped: {}", user_id, e);
+        }
+    });
+
+    log::info!("Started agent for @{}", user.username);
+    RunningAgent {
+        user,
+        watchdog,
+        status,
+        rate_limits,
+        refreshing,
+        task: Some(task),
+    }
+}
+
+struct StoppingAgent {
+    refreshing: Option<Arc<tokio::sync::Mutex<()>>>,
+    task: JoinHandle<()>,
+}
+
+impl StoppingAgent {
+    /// Aborts the task once any token refresh under way has finished, so its
+    /// rotated token is saved.
+    async fn stop(self) {
+        // Held until the task is gone, so no refresh can start meanwhile
+        let _refreshing = match &self.refreshing {
+            Some(refreshing) => Some(refreshing.lock().await),
+            None => None,
+        };
+        self.task.abort();
+        let _ = self.task.await;
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::attestation::EnclaveKey;
+use crate::llm::Message;
+use crate::twitter::rate_limit::TWEETS_ENDPOINT;
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Something the agent did.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// The conversation an agent run starts from.
+    Prompt {
+        messages: Vec<Message>,
+        /// The mention the run answers; absent for scheduled runs.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mention_id: Option<String>,
+    },
+    /// Everything the model returned, including any tool calls.
+    ModelResponse { message: Message },
+    /// A tool call the agent executed and what it fed back to the model.
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+        output: String,
+    },
+    /// An ID Twitter returned for something the agent did, keyed by the
+    /// rate-limit endpoint name, e.g. `POST /2/tweets`.
+    TwitterResponse { endpoint: String, id: String },
+}
+
+/// The signed part of an entry, in field order.
+#[derive(Serialize)]
+struct EntryBody<'e> {
+    seq: u64,
+    timestamp: u64,
+    prev_hash: &'e str,
+    public_key: &'e str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<&'e str>,
+    action: &'e Action,
+}
+
+/// One link of the chain. `hash` is the hex SHA-256 of the compact JSON of
+/// `{seq, timestamp, prev_hash, public_key, account, action}` in that order,
+/// leaving out `account` where it is absent, and
+/// `signature` is the hex ed25519 signature over the raw hash bytes by
+/// `public_key`, the attested enclave key of the run that wrote the entry.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub prev_hash: String,
+    pub public_key: String,
+    /// Twitter user ID of the account the entry was recorded for. Absent
+    /// from entries written before accounts were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub action: Action,
+    pub hash: String,
+    pub signature: String,
+}
+
+impl LogEntry {
+    fn digest(&self) -> eyre::Result<[u8; 32]> {
+        let body = EntryBody {
+            seq: self.seq,
+            timestamp: self.timestamp,
+            prev_hash: &self.prev_hash,
+            public_key: &self.public_key,
+            account: self.account.as_deref(),
+            action: &self.action,
+        };
+        Ok(Sha256::digest(serde_json::to_vec(&body)?).into())
+    }
+
+    /// Checks the hash and signature of this entry alone.
+    pub fn verify(&self) -> eyre::Result<()> {
+        let digest = self.digest()?;
+        if hex::encode(digest) != self.hash {
+            eyre::bail!("Entry {} hash mismatch", self.seq);
+        }
+        let public_key: [u8; 32] = hex::decode(&self.public_key)?
+            .try_into()
+            .map_err(|_| eyre::eyre!("Entry {} has a malformed public key", self.seq))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)?
+            .try_into()
+            .map_err(|_| eyre::eyre!("Entry {} has a malformed signature", self.seq))?;
+        VerifyingKey::from_bytes(&public_key)?
+            .verify(&digest, &Signature::from_bytes(&signature))
+            .map_err(|_| eyre::eyre!("Entry {} signature is invalid", self.seq))
+    }
+}
+
+/// Checks every entry and that each one links to the one before it.
+pub fn verify_chain(entries: &[LogEntry]) -> eyre::Result<()> {
+    let mut prev_hash = GENESIS_HASH;
+    for (seq, entry) in entries.iter().enumerate() {
+        if entry.seq != seq as u64 {
+            eyre::bail!("Entry {} found at position {}", entry.seq, seq);
+        }
+        if entry.prev_hash != prev_hash {
+            eyre::bail!("Entry {} does not link to the entry before it", entry.seq);
+        }
+        entry.verify()?;
+        prev_hash = &entry.hash;
+    }
+    Ok(())
+}
+
+/// Append-only, hash-chained record of everything the agent does, signed
+/// by the enclave key so followers can audit every post.
+pub struct ActionLog {
+    key: Arc<EnclaveKey>,
+    entries: Mutex<Vec<LogEntry>>,
+    /// Entries are also appended here as JSON lines, if set.
+    path: Option<PathBuf>,
+}
+
+impl ActionLog {
+    /// An in-memory log, lost on restart.
+    pub fn new(key: Arc<EnclaveKey>) -> Self {
+        Self {
+            key,
+            entries: Mutex::new(Vec::new()),
+            path: None,
+        }
+    }
+
+    /// Continues the chain stored at `path`, refusing to start if it has been
+    /// tampered with. Earlier entries stay signed by earlier runs' keys.
+    pub async fn open(key: Arc<EnclaveKey>, path: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let path = path.into();
+        let entries = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents
+                .lines()
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<LogEntry>, _>>()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        verify_chain(&entries)?;
+        Ok(Self {
+            key,
+            entries: Mutex::new(entries),
+            path: Some(path),
+        })
+    }
+
+    pub fn public_key(&self) -> String {
+        self.key.public_key_hex()
+    }
+
+    /// Appends `action`, done by the agent acting as user ID `account`.
+    pub async fn record(&self, account: &str, action: Action) -> eyre::Result<LogEntry> {
+        let mut entries = self.entries.lock().await;
+        let mut entry = LogEntry {
+            seq: entries.len() as u64,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            prev_hash: entries
+                .last()
+                .map_or(GENESIS_HASH.to_string(), |last| last.hash.clone()),
+            public_key: self.key.public_key_hex(),
+            account: Some(account.to_string()),
+            action,
+            hash: String::new(),
+            signature: String::new(),
+        };
+        let digest = entry.digest()?;
+        entry.hash = hex::encode(digest);
+        entry.signature = hex::encode(self.key.sign(&digest).to_bytes());
+
+        if let Some(path) = &self.path {
+            let mut line = serde_json::to_vec(&entry)?;
+            line.push(b'\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(&line).await?;
+            file.sync_data().await?;
+        }
+        entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// Up to `limit` entries with `seq >= from`.
+    pub async fn entries(&self, from: u64, limit: usize) -> Vec<LogEntry> {
+        let entries = self.entries.lock().await;
+        entries
+            .iter()
+            .skip(from.min(entries.len() as u64) as usize)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// When `account`'s latest scheduled run started.
+    pub async fn last_run_at(&self, account: &str) -> Option<u64> {
+        let entries = self.entries.lock().await;
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.account.as_deref() == Some(account))
+            .find(|entry| {
+                matches!(
+                    entry.action,
+                    Action::Prompt {
+                        mention_id: None,
+                        ..
+                    }
+                )
+            })
+            .map(|entry| entry.timestamp)
+    }
+
+    /// When `account` posted tweets, from `since` on.
+    pub async fn posted_at(&self, account: &str, since: u64) -> Vec<u64> {
+        let entries = self.entries.lock().await;
+        entries
+            .iter()
+            .filter(|entry| entry.timestamp >= since)
+            .filter(|entry| entry.account.as_deref() == Some(account))
+            .filter(|entry| {
+                matches!(&entry.action, Action::TwitterResponse { endpoint, .. } if endpoint == TWEETS_ENDPOINT)
+            })
+            .map(|entry| entry.timestamp)
+            .collect()
+    }
+
+    pub async fn len(&self) -> u64 {
+        self.entries.lock().await.len() as u64
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
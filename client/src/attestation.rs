@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey};
+use rand_core::OsRng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Domain separator hashed into the report data.
+const REPORT_DATA_DOMAIN: &[u8] = b"twitter-encumbrance/attestation/v1";
+
+/// A signing key generated inside the enclave at startup and never exported.
+/// Its public half is bound into the attestation quote, so anything it signs
+/// is known to come from the attested code.
+pub struct EnclaveKey {
+    signing_key: SigningKey,
+}
+
+impl EnclaveKey {
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().as_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        self.signing_key.sign(message)
+    }
+}
+
+/// What the quote vouches for.
+#[derive(Serialize, Debug, Clone)]
+pub struct AttestationClaims {
+    /// SHA-256 of the running executable.
+    pub binary_sha256: String,
+    /// SHA-256 of the agent configuration, once one is loaded.
+    pub config_sha256: Option<String>,
+    /// Hex-encoded ed25519 public key of the `EnclaveKey`.
+    pub public_key: String,
+}
+
+impl AttestationClaims {
+    /// The 64 bytes placed in the quote's report data: the first 32 are
+    /// `SHA-256(domain || binary_sha256 || "\n" || config_sha256 || "\n" ||
+    /// public_key)` over the hex strings, with an empty string for a missing
+    /// config hash; the last 32 are zero.
+    pub fn report_data(&self) -> [u8; 64] {
+        let mut hasher = Sha256::new();
+        hasher.update(REPORT_DATA_DOMAIN);
+        hasher.update(self.binary_sha256.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(self.config_sha256.as_deref().unwrap_or("").as_bytes());
+        hasher.update(b"\n");
+        hasher.update(self.public_key.as_bytes());
+
+        let mut report_data = [0u8; 64];
+        report_data[..32].copy_from_slice(&hasher.finalize());
+        report_data
+    }
+}
+
+/// Hashes the executable this process was started from.
+pub fn binary_sha256() -> eyre::Result<String> {
+    let binary = std::fs::read(std::env::current_exe()?)?;
+    Ok(hex::encode(Sha256::digest(&binary)))
+}
+
+#[async_trait::async_trait]
+pub trait AttestationProvider: Send + Sync {
+    /// Identifies the quote format, e.g. so verifiers can reject `mock`.
+    fn name(&self) -> &'static str;
+
+    async fn quote(&self, report_data: &[u8; 64]) -> eyre::Result<Vec<u8>>;
+}
+
+/// Produces quotes that prove nothing, for running outside an enclave.
+pub struct MockAttestationProvider;
+
+impl MockAttestationProvider {
+    pub const QUOTE_PREFIX: &'static [u8] = b"MOCK-QUOTE";
+}
+
+#[async_trait::async_trait]
+impl AttestationProvider for MockAttestationProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn quote(&self, report_data: &[u8; 64]) -> eyre::Result<Vec<u8>> {
+        Ok([Self::QUOTE_PREFIX, report_data.as_slice()].concat())
+    }
+}
+
+/// SGX DCAP quotes through Gramine's `/dev/attestation` pseudo-files.
+pub struct GramineAttestationProvider {
+    attestation_dir: PathBuf,
+}
+
+impl Default for GramineAttestationProvider {
+    fn default() -> Self {
+        Self {
+            attestation_dir: PathBuf::from("/dev/attestation"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttestationProvider for GramineAttestationProvider {
+    fn name(&self) -> &'static str {
+        "gramine-sgx"
+    }
+
+    async fn quote(&self, report_data: &[u8; 64]) -> eyre::Result<Vec<u8>> {
+        tokio::fs::write(self.attestation_dir.join("user_report_data"), report_data).await?;
+        Ok(tokio::fs::read(self.attestation_dir.join("quote")).await?)
+    }
+}
+
+/// Served at `/attestation`. Verifiers check the quote, recompute
+/// `report_data` from `claims`, and compare.
+#[derive(Serialize, Debug, Clone)]
+pub struct AttestationReport {
+    pub provider: String,
+    /// Base64-encoded quote.
+    pub quote: String,
+    /// Hex-encoded report data embedded in the quote.
+    pub report_data: String,
+    pub claims: AttestationClaims,
+}
+
+impl AttestationReport {
+    pub async fn generate(
+        provider: &dyn AttestationProvider,
+        claims: AttestationClaims,
+    ) -> eyre::Result<Self> {
+        let report_data = claims.report_data();
+        let quote = provider.quote(&report_data).await?;
+        Ok(Self {
+            provider: provider.name().to_string(),
+            quote: STANDARD.encode(quote),
+            report_data: hex::encode(report_data),
+            claims,
+        })
+    }
+}
+
+/// Picks a provider from `ATTESTATION_PROVIDER`: `mock` (default) or
+/// `gramine`.
+pub fn from_env() -> eyre::Result<Box<dyn AttestationProvider>> {
+    let provider = std::env::var("ATTESTATION_PROVIDER").unwrap_or_else(|_| "mock".to_string());
+    match provider.as_str() {
+        "mock" => Ok(Box::new(MockAttestationProvider)),
+        "gramine" => Ok(Box::new(GramineAttestationProvider::default())),
+        other => eyre::bail!("Unknown ATTESTATION_PROVIDER: {}", other),
+    }
+}
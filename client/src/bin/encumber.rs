@@ -0,0 +1,62 @@
+//! Rotates the recovery email and Twitter passwords to values only the
+//! enclave knows. Run once, inside the enclave, before starting the agent.
+//!
+//! `encumber resolve` settles rotations a failed run left pending, after
+//! which `encumber` can be run again.
+
+use std::{collections::HashMap, sync::Arc};
+
+use client::{
+    encumbrance::{
+        self,
+        webdriver::{ProtonMailPasswordRotator, TwitterPasswordRotator, WebDriver},
+        CredentialRotator, Secret, Vault,
+    },
+    sealing::KeyFileProvider,
+};
+
+fn var(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| panic!("{} not set", name))
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    dotenv::dotenv().ok();
+
+    let webdriver_url = var("WEBDRIVER_URL");
+    let keys = Arc::new(KeyFileProvider::new(var("SEALING_KEY_PATH")));
+    let vault = Vault::new(var("ENCUMBRANCE_VAULT_PATH"), keys);
+
+    let initial = HashMap::from([
+        (
+            "protonmail".to_string(),
+            Secret::new(var("PROTONMAIL_PASSWORD")),
+        ),
+        ("twitter".to_string(), Secret::new(var("TWITTER_PASSWORD"))),
+    ]);
+    // The recovery email first, so the owner can't use it to reset Twitter
+    let rotators: Vec<Box<dyn CredentialRotator>> = vec![
+        Box::new(ProtonMailPasswordRotator::new(
+            WebDriver::new(webdriver_url.clone()),
+            var("PROTONMAIL_USERNAME"),
+        )),
+        Box::new(TwitterPasswordRotator::new(
+            WebDriver::new(webdriver_url),
+            var("TWITTER_USERNAME"),
+        )),
+    ];
+
+    match std::env::args().nth(1).as_deref() {
+        None => encumbrance::encumber(&rotators, &initial, &vault)
+            .await
+            .expect("Failed to encumber account"),
+        Some("resolve") => encumbrance::resolve(&rotators, &initial, &vault)
+            .await
+            .expect("Failed to resolve pending rotations"),
+        Some(other) => panic!("Unknown command {}; expected none or resolve", other),
+    }
+    for (service, record) in vault.load().await.expect("Failed to load vault").iter() {
+        println!("{}: {:?}", service, record.state);
+    }
+}
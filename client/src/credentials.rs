@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use zeroize::Zeroizing;
+
+use crate::sealing::SealingKeyProvider;
+use crate::twitter::{
+    auth::{OAuth2Token, UserAuth},
+    oauth2::OAuth2TokenSink,
+};
+
+/// Label the credential sealing key is derived under.
+const CREDENTIALS_KEY_LABEL: &str = "twitter-encumbrance/credentials/v1";
+
+/// Everything needed to act as the encumbered account without logging in
+/// again.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredCredentials {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    /// Written as `token_pair` by versions that only spoke OAuth 1.0a.
+    #[serde(alias = "token_pair")]
+    pub auth: UserAuth,
+}
+
+#[async_trait::async_trait]
+pub trait CredentialStore: Send + Sync {
+    /// `None` if nothing has been stored yet.
+    async fn load(&self) -> eyre::Result<Option<StoredCredentials>>;
+
+    async fn save(&self, credentials: &StoredCredentials) -> eyre::Result<()>;
+}
+
+/// Keeps credentials in a single file, sealed under a key derived from
+/// `keys`, so a copy of the file is useless outside the enclave.
+pub struct SealedFileStore {
+    path: PathBuf,
+    keys: Arc<dyn SealingKeyProvider>,
+}
+
+impl SealedFileStore {
+    pub fn new(path: impl Into<PathBuf>, keys: Arc<dyn SealingKeyProvider>) -> Self {
+        Self {
+            path: path.into(),
+            keys,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialStore for SealedFileStore {
+    async fn load(&self) -> eyre::Result<Option<StoredCredentials>> {
+        let sealed = match tokio::fs::read(&self.path).await {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let plaintext = self.keys.derive_key(CREDENTIALS_KEY_LABEL)?.open(&sealed)?;
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
+
+    async fn save(&self, credentials: &StoredCredentials) -> eyre::Result<()> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(credentials)?);
+        let sealed = self
+            .keys
+            .derive_key(CREDENTIALS_KEY_LABEL)?
+            .seal(&plaintext)?;
+        write_atomic(&self.path, &sealed).await
+    }
+}
+
+/// One `SealedFileStore` per account, named `<user id>.sealed`, in `dir`.
+pub struct AccountStore {
+    dir: PathBuf,
+    keys: Arc<dyn SealingKeyProvider>,
+}
+
+impl AccountStore {
+    pub fn new(dir: impl Into<PathBuf>, keys: Arc<dyn SealingKeyProvider>) -> Self {
+        Self {
+            dir: dir.into(),
+            keys,
+        }
+    }
+
+    pub fn account(&self, user_id: &str) -> eyre::Result<SealedFileStore> {
+        // IDs come back from the API, but never let one escape `dir`
+        if user_id.is_empty() || !user_id.bytes().all(|b| b.is_ascii_digit()) {
+            eyre::bail!("Not a Twitter user ID: {:?}", user_id);
+        }
+        Ok(SealedFileStore::new(
+            self.dir.join(format!("{}.sealed", user_id)),
+            self.keys.clone(),
+        ))
+    }
+
+    /// Every stored account's credentials, keyed by user ID.
+    pub async fn load_all(&self) -> eyre::Result<BTreeMap<String, StoredCredentials>> {
+        let mut accounts = BTreeMap::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(accounts),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(user_id) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".sealed"))
+            else {
+                continue;
+            };
+            if let Some(credentials) = self.account(user_id)?.load().await? {
+                accounts.insert(user_id.to_string(), credentials);
+            }
+        }
+        Ok(accounts)
+    }
+}
+
+/// Writes `contents` to a temporary sibling and renames it over `path`, so a
+/// crash never leaves a half-written file behind.
+pub(crate) async fn write_atomic(path: &Path, contents: &[u8]) -> eyre::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Saves each rotated OAuth 2.0 token back to a `CredentialStore`.
+pub struct RefreshedTokenSaver {
+    store: Arc<dyn CredentialStore>,
+    consumer_key: String,
+    consumer_secret: String,
+}
+
+impl RefreshedTokenSaver {
+    pub fn new(
+        store: Arc<dyn CredentialStore>,
+        consumer_key: String,
+        consumer_secret: String,
+    ) -> Self {
+        Self {
+            store,
+            consumer_key,
+            consumer_secret,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuth2TokenSink for RefreshedTokenSaver {
+    async fn save(&self, token: &OAuth2Token) -> eyre::Result<()> {
+        let credentials = StoredCredentials {
+            consumer_key: self.consumer_key.clone(),
+            consumer_secret: self.consumer_secret.clone(),
+            auth: UserAuth::OAuth2(token.clone()),
+        };
+        self.store.save(&credentials).await
+    }
+}
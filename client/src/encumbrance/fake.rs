@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+
+use super::{CredentialRotator, Secret};
+
+/// An in-memory account, for tests. Clones share the account, so a test can
+/// keep one to inspect after handing another to `encumber`.
+#[derive(Clone)]
+pub struct FakeRotator {
+    service: &'static str,
+    password: Arc<Mutex<Secret>>,
+    fail: bool,
+    lose_confirmation: bool,
+}
+
+impl FakeRotator {
+    pub fn new(service: &'static str, password: Secret) -> Self {
+        Self {
+            service,
+            password: Arc::new(Mutex::new(password)),
+            fail: false,
+            lose_confirmation: false,
+        }
+    }
+
+    /// Makes every rotation fail after checking the current password, without
+    /// changing it.
+    pub fn failing(mut self) -> Self {
+        self.fail = true;
+        self
+    }
+
+    /// Makes every rotation change the password but still fail, like a page
+    /// that never showed its confirmation.
+    pub fn losing_confirmation(mut self) -> Self {
+        self.lose_confirmation = true;
+        self
+    }
+
+    pub fn password(&self) -> Secret {
+        self.password.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRotator for FakeRotator {
+    fn service(&self) -> &'static str {
+        self.service
+    }
+
+    async fn rotate(&self, current: &Secret, new: &Secret) -> eyre::Result<()> {
+        let mut password = self.password.lock().unwrap();
+        if *password != *current {
+            eyre::bail!("Current password is incorrect");
+        }
+        if self.fail {
+            eyre::bail!("Password change failed");
+        }
+        *password = new.clone();
+        if self.lose_confirmation {
+            eyre::bail!("No confirmation shown");
+        }
+        Ok(())
+    }
+
+    async fn check(&self, password: &Secret) -> eyre::Result<bool> {
+        Ok(*self.password.lock().unwrap() == *password)
+    }
+}
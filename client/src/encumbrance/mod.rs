@@ -0,0 +1,186 @@
+//! Locks the account owner out: rotates the passwords of the Twitter account
+//! and its recovery email to values generated and kept inside the enclave.
+//!
+//! New passwords only ever exist in memory and, sealed, in the `Vault`. They
+//! are wrapped in `Secret`, which never prints its contents, so they cannot
+//! end up in logs.
+
+use std::{collections::HashMap, fmt};
+
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+pub mod fake;
+pub mod vault;
+pub mod webdriver;
+
+pub use fake::FakeRotator;
+pub use vault::{RotationState, Vault};
+
+/// Characters generated passwords are drawn from.
+const PASSWORD_CHARSET: &[u8] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!@#$%^&*()_+-=";
+pub const PASSWORD_LENGTH: usize = 24;
+
+/// A password or other credential. Its `Debug` output is redacted and it has
+/// no `Display`, so it can't be logged by accident.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// A uniformly random password from the OS RNG.
+pub fn generate_password() -> Secret {
+    let mut password = String::with_capacity(PASSWORD_LENGTH);
+    while password.len() < PASSWORD_LENGTH {
+        let mut byte = [0u8; 1];
+        OsRng.fill_bytes(&mut byte);
+        // Rejection sampling keeps the distribution uniform
+        let limit = 256 - 256 % PASSWORD_CHARSET.len();
+        if (byte[0] as usize) < limit {
+            password.push(PASSWORD_CHARSET[byte[0] as usize % PASSWORD_CHARSET.len()] as char);
+        }
+    }
+    Secret::new(password)
+}
+
+/// Changes the password of one account.
+#[async_trait::async_trait]
+pub trait CredentialRotator: Send + Sync {
+    /// Identifies the account in the vault, e.g. `twitter`.
+    fn service(&self) -> &'static str;
+
+    /// Errors must not include either password.
+    async fn rotate(&self, current: &Secret, new: &Secret) -> eyre::Result<()>;
+
+    /// Whether `password` logs in. Errors when the site gives no clear
+    /// answer.
+    async fn check(&self, password: &Secret) -> eyre::Result<bool>;
+}
+
+/// Runs `rotators` in order, so the recovery email should come before the
+/// account it can recover.
+///
+/// The new password is sealed into `vault` as `Pending` before each change is
+/// attempted and marked `Rotated` after, so a crash or an ambiguous failure
+/// never loses the only copy. The current password comes from the vault if it
+/// already holds a rotated one, else from `initial`.
+pub async fn encumber(
+    rotators: &[Box<dyn CredentialRotator>],
+    initial: &HashMap<String, Secret>,
+    vault: &Vault,
+) -> eyre::Result<()> {
+    for rotator in rotators {
+        let service = rotator.service();
+        let mut records = vault.load().await?;
+        let (current, sealed) = match records.get(service) {
+            Some(record) if record.state == RotationState::Rotated => {
+                (record.password.clone(), true)
+            }
+            Some(_) => eyre::bail!(
+                "{} has a pending rotation; run `encumber resolve` before rotating again",
+                service
+            ),
+            None => (initial_password(initial, service)?, false),
+        };
+
+        let new = generate_password();
+        records.insert_pending(service, new.clone(), sealed.then(|| current.clone()));
+        vault.save(&records).await?;
+
+        log::info!("Rotating {} password", service);
+        rotator
+            .rotate(&current, &new)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to rotate {} password: {}", service, e))?;
+
+        records.insert(service, new, RotationState::Rotated);
+        vault.save(&records).await?;
+        log::info!("Rotated {} password", service);
+    }
+    Ok(())
+}
+
+fn initial_password(initial: &HashMap<String, Secret>, service: &str) -> eyre::Result<Secret> {
+    initial
+        .get(service)
+        .cloned()
+        .ok_or_else(|| eyre::eyre!("No current password for {}", service))
+}
+
+/// Settles the `Pending` records a failed or interrupted `encumber` left
+/// behind, by logging in: the new password is kept as `Rotated` if it works,
+/// otherwise the one it was meant to replace is restored. A record neither
+/// password works for stays pending and fails the call.
+pub async fn resolve(
+    rotators: &[Box<dyn CredentialRotator>],
+    initial: &HashMap<String, Secret>,
+    vault: &Vault,
+) -> eyre::Result<()> {
+    for rotator in rotators {
+        let service = rotator.service();
+        let mut records = vault.load().await?;
+        let Some(record) = records
+            .get(service)
+            .filter(|record| record.state == RotationState::Pending)
+            .cloned()
+        else {
+            continue;
+        };
+
+        if rotator.check(&record.password).await? {
+            records.insert(service, record.password, RotationState::Rotated);
+            log::info!("Pending {} password is in use; marked rotated", service);
+        } else {
+            let previous = match &record.previous {
+                Some(previous) => previous.clone(),
+                None => initial_password(initial, service)?,
+            };
+            if !rotator.check(&previous).await? {
+                eyre::bail!(
+                    "Neither the pending nor the previous {} password logs in",
+                    service
+                );
+            }
+            match record.previous {
+                Some(previous) => records.insert(service, previous, RotationState::Rotated),
+                None => {
+                    records.remove(service);
+                }
+            }
+            log::info!(
+                "{} password was never changed; restored the previous one",
+                service
+            );
+        }
+        vault.save(&records).await?;
+    }
+    Ok(())
+}
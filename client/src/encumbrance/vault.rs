@@ -0,0 +1,100 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::Secret;
+use crate::credentials::write_atomic;
+use crate::sealing::SealingKeyProvider;
+
+/// Label the vault sealing key is derived under.
+const VAULT_KEY_LABEL: &str = "twitter-encumbrance/encumbrance/v1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationState {
+    /// Generated and about to be set; the change may or may not have applied.
+    Pending,
+    /// The account now uses this password.
+    Rotated,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VaultRecord {
+    pub password: Secret,
+    pub state: RotationState,
+    /// While `Pending`, the sealed password being replaced, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Secret>,
+}
+
+/// The passwords generated by `encumber`, keyed by service.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VaultRecords(BTreeMap<String, VaultRecord>);
+
+impl VaultRecords {
+    pub fn get(&self, service: &str) -> Option<&VaultRecord> {
+        self.0.get(service)
+    }
+
+    pub fn insert(&mut self, service: &str, password: Secret, state: RotationState) {
+        let record = VaultRecord {
+            password,
+            state,
+            previous: None,
+        };
+        self.0.insert(service.to_string(), record);
+    }
+
+    /// Records `password` as `Pending`, keeping the sealed one it replaces so
+    /// `resolve` can fall back to it.
+    pub fn insert_pending(&mut self, service: &str, password: Secret, previous: Option<Secret>) {
+        let record = VaultRecord {
+            password,
+            state: RotationState::Pending,
+            previous,
+        };
+        self.0.insert(service.to_string(), record);
+    }
+
+    pub fn remove(&mut self, service: &str) -> Option<VaultRecord> {
+        self.0.remove(service)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &VaultRecord)> {
+        self.0.iter()
+    }
+}
+
+/// Sealed file holding the rotated passwords.
+pub struct Vault {
+    path: PathBuf,
+    keys: Arc<dyn SealingKeyProvider>,
+}
+
+impl Vault {
+    pub fn new(path: impl Into<PathBuf>, keys: Arc<dyn SealingKeyProvider>) -> Self {
+        Self {
+            path: path.into(),
+            keys,
+        }
+    }
+
+    pub async fn load(&self) -> eyre::Result<VaultRecords> {
+        let sealed = match tokio::fs::read(&self.path).await {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(VaultRecords::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let plaintext = self.keys.derive_key(VAULT_KEY_LABEL)?.open(&sealed)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    pub async fn save(&self, records: &VaultRecords) -> eyre::Result<()> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(records)?);
+        let sealed = self.keys.derive_key(VAULT_KEY_LABEL)?.seal(&plaintext)?;
+        write_atomic(&self.path, &sealed).await
+    }
+}
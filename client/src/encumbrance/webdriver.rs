@@ -0,0 +1,404 @@
+//! Password rotation through the account settings pages, driven over the W3C
+//! WebDriver protocol (e.g. a `chromedriver` running inside the enclave).
+
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+
+use super::{CredentialRotator, Secret};
+
+/// Key under which WebDriver returns element references.
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735fa7f1d7";
+/// The Enter key, as WebDriver encodes it in typed text.
+const ENTER: &str = "\u{E007}";
+/// How long to wait for an element to appear.
+const FIND_TIMEOUT: Duration = Duration::from_secs(20);
+const FIND_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long to wait for a page to confirm or reject a submitted form.
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(30);
+
+// What the sites show after a submission. They follow the pages' current
+// markup, so check them first when a rotation times out.
+const TWITTER_LOGGED_IN: Locator = Locator::Css(r#"[data-testid="SideNav_NewTweet_Button"]"#);
+const TWITTER_LOGIN_REJECTED: Locator = Locator::XPath("//*[contains(text(), 'Wrong password')]");
+const TWITTER_PASSWORD_CHANGED: Locator =
+    Locator::XPath("//*[contains(text(), 'Your password has been changed')]");
+const TWITTER_PASSWORD_REJECTED: Locator = Locator::XPath(
+    "//*[contains(text(), 'password you entered was incorrect') or contains(text(), 'Passwords do not match') or contains(text(), 'Something went wrong')]",
+);
+const PROTON_LOGGED_IN: Locator = Locator::Css(r#"[data-testid="heading:userdropdown"]"#);
+const PROTON_REJECTED: Locator = Locator::Css(".notification--error");
+const PROTON_PASSWORD_CHANGED: Locator = Locator::XPath("//*[contains(text(), 'Password saved')]");
+
+#[derive(Clone, Copy)]
+pub enum Locator<'l> {
+    Css(&'l str),
+    XPath(&'l str),
+}
+
+impl Locator<'_> {
+    fn to_json(self) -> Value {
+        match self {
+            Locator::Css(selector) => json!({ "using": "css selector", "value": selector }),
+            Locator::XPath(path) => json!({ "using": "xpath", "value": path }),
+        }
+    }
+}
+
+/// How a page answered a submitted form.
+pub enum Outcome {
+    Confirmed,
+    /// With the text of the error shown.
+    Rejected(String),
+}
+
+/// A minimal WebDriver client: just enough to fill in and submit forms.
+pub struct WebDriver {
+    client: Client,
+    base_url: String,
+}
+
+impl WebDriver {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Sends one command and returns its `value`. Error messages come from the
+    /// driver and never echo typed text.
+    async fn command(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> eyre::Result<Value> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response: Value = request.send().await?.json().await?;
+        let value = response["value"].clone();
+        if let Some(error) = value["error"].as_str() {
+            eyre::bail!(
+                "WebDriver error {}: {}",
+                error,
+                value["message"].as_str().unwrap_or_default()
+            );
+        }
+        Ok(value)
+    }
+
+    pub async fn session(&self) -> eyre::Result<Session<'_>> {
+        let capabilities = json!({
+            "capabilities": { "alwaysMatch": { "browserName": "chrome" } }
+        });
+        let value = self
+            .command(Method::POST, "/session", Some(capabilities))
+            .await?;
+        let id = value["sessionId"]
+            .as_str()
+            .ok_or_else(|| eyre::eyre!("WebDriver returned no session ID"))?
+            .to_string();
+        Ok(Session { driver: self, id })
+    }
+}
+
+/// A browser session. Call `close` when done; it is not closed on drop.
+pub struct Session<'d> {
+    driver: &'d WebDriver,
+    id: String,
+}
+
+impl Session<'_> {
+    async fn command(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> eyre::Result<Value> {
+        let path = format!("/session/{}{}", self.id, path);
+        self.driver.command(method, &path, body).await
+    }
+
+    pub async fn goto(&self, url: &str) -> eyre::Result<()> {
+        self.command(Method::POST, "/url", Some(json!({ "url": url })))
+            .await?;
+        Ok(())
+    }
+
+    /// The element if it is on the page right now.
+    async fn find_now(&self, locator: Locator<'_>) -> eyre::Result<String> {
+        let value = self
+            .command(Method::POST, "/element", Some(locator.to_json()))
+            .await?;
+        value[ELEMENT_KEY]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| eyre::eyre!("WebDriver returned no element"))
+    }
+
+    /// Waits up to `FIND_TIMEOUT` for the element to appear.
+    async fn find(&self, locator: Locator<'_>) -> eyre::Result<String> {
+        let started = Instant::now();
+        loop {
+            match self.find_now(locator).await {
+                Ok(element) => return Ok(element),
+                Err(e) if started.elapsed() >= FIND_TIMEOUT => return Err(e),
+                Err(_) => tokio::time::sleep(FIND_POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn text(&self, element: &str) -> eyre::Result<String> {
+        let value = self
+            .command(Method::GET, &format!("/element/{}/text", element), None)
+            .await?;
+        Ok(value.as_str().unwrap_or_default().trim().to_string())
+    }
+
+    /// Waits up to `OUTCOME_TIMEOUT` for `confirmed` or `rejected` to appear.
+    /// Neither appearing is an error, since the submission may or may not
+    /// have gone through.
+    pub async fn outcome(
+        &self,
+        confirmed: Locator<'_>,
+        rejected: Locator<'_>,
+    ) -> eyre::Result<Outcome> {
+        let started = Instant::now();
+        loop {
+            if let Ok(element) = self.find_now(rejected).await {
+                return Ok(Outcome::Rejected(self.text(&element).await?));
+            }
+            if self.find_now(confirmed).await.is_ok() {
+                return Ok(Outcome::Confirmed);
+            }
+            if started.elapsed() >= OUTCOME_TIMEOUT {
+                eyre::bail!("The page neither confirmed nor rejected the submission");
+            }
+            tokio::time::sleep(FIND_POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn send_keys(&self, locator: Locator<'_>, text: &str) -> eyre::Result<()> {
+        let element = self.find(locator).await?;
+        self.command(
+            Method::POST,
+            &format!("/element/{}/value", element),
+            Some(json!({ "text": text })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn click(&self, locator: Locator<'_>) -> eyre::Result<()> {
+        let element = self.find(locator).await?;
+        self.command(
+            Method::POST,
+            &format!("/element/{}/click", element),
+            Some(json!({})),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn close(self) -> eyre::Result<()> {
+        self.command(Method::DELETE, "", None).await?;
+        Ok(())
+    }
+}
+
+/// Closes `session` whatever the outcome of the steps run in it.
+async fn finish<T>(session: Session<'_>, result: eyre::Result<T>) -> eyre::Result<T> {
+    if let Err(e) = session.close().await {
+        log::warn!("Failed to close WebDriver session: {}", e);
+    }
+    result
+}
+
+/// Changes the Twitter password through `x.com/settings/password`.
+pub struct TwitterPasswordRotator {
+    driver: WebDriver,
+    username: String,
+}
+
+impl TwitterPasswordRotator {
+    pub fn new(driver: WebDriver, username: String) -> Self {
+        Self { driver, username }
+    }
+
+    /// Whether `password` logs in.
+    async fn log_in(&self, session: &Session<'_>, password: &Secret) -> eyre::Result<bool> {
+        session.goto("https://twitter.com/i/flow/login").await?;
+        session
+            .send_keys(
+                Locator::Css(r#"input[autocomplete="username"]"#),
+                &format!("{}{}", self.username, ENTER),
+            )
+            .await?;
+        session
+            .send_keys(
+                Locator::Css(r#"input[name="password"]"#),
+                &format!("{}{}", password.expose(), ENTER),
+            )
+            .await?;
+        let outcome = session
+            .outcome(TWITTER_LOGGED_IN, TWITTER_LOGIN_REJECTED)
+            .await?;
+        Ok(matches!(outcome, Outcome::Confirmed))
+    }
+
+    async fn change_password(
+        &self,
+        session: &Session<'_>,
+        current: &Secret,
+        new: &Secret,
+    ) -> eyre::Result<()> {
+        if !self.log_in(session, current).await? {
+            eyre::bail!("The current password was rejected");
+        }
+
+        session.goto("https://x.com/settings/password").await?;
+        session
+            .send_keys(
+                Locator::Css(r#"input[name="current_password"]"#),
+                current.expose(),
+            )
+            .await?;
+        session
+            .send_keys(Locator::Css(r#"input[name="new_password"]"#), new.expose())
+            .await?;
+        session
+            .send_keys(
+                Locator::Css(r#"input[name="password_confirmation"]"#),
+                new.expose(),
+            )
+            .await?;
+        session
+            .click(Locator::XPath(
+                "/html/body/div[1]/div/div/div[2]/main/div/div/div/section[2]/div[2]/div[3]/button",
+            ))
+            .await?;
+        match session
+            .outcome(TWITTER_PASSWORD_CHANGED, TWITTER_PASSWORD_REJECTED)
+            .await?
+        {
+            Outcome::Confirmed => Ok(()),
+            Outcome::Rejected(reason) => eyre::bail!("Password change rejected: {}", reason),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRotator for TwitterPasswordRotator {
+    fn service(&self) -> &'static str {
+        "twitter"
+    }
+
+    async fn rotate(&self, current: &Secret, new: &Secret) -> eyre::Result<()> {
+        let session = self.driver.session().await?;
+        let result = self.change_password(&session, current, new).await;
+        finish(session, result).await
+    }
+
+    async fn check(&self, password: &Secret) -> eyre::Result<bool> {
+        let session = self.driver.session().await?;
+        let result = self.log_in(&session, password).await;
+        finish(session, result).await
+    }
+}
+
+/// Changes the Proton Mail account password, for the recovery email.
+pub struct ProtonMailPasswordRotator {
+    driver: WebDriver,
+    username: String,
+}
+
+impl ProtonMailPasswordRotator {
+    pub fn new(driver: WebDriver, username: String) -> Self {
+        Self { driver, username }
+    }
+
+    /// Whether `password` logs in.
+    async fn log_in(&self, session: &Session<'_>, password: &Secret) -> eyre::Result<bool> {
+        session.goto("https://account.proton.me/").await?;
+        session
+            .send_keys(Locator::Css("#username"), &self.username)
+            .await?;
+        session
+            .send_keys(
+                Locator::Css("#password"),
+                &format!("{}{}", password.expose(), ENTER),
+            )
+            .await?;
+        let outcome = session.outcome(PROTON_LOGGED_IN, PROTON_REJECTED).await?;
+        Ok(matches!(outcome, Outcome::Confirmed))
+    }
+
+    async fn change_password(
+        &self,
+        session: &Session<'_>,
+        current: &Secret,
+        new: &Secret,
+    ) -> eyre::Result<()> {
+        if !self.log_in(session, current).await? {
+            eyre::bail!("The current password was rejected");
+        }
+
+        session
+            .goto("https://account.proton.me/u/0/mail/account-password")
+            .await?;
+        session
+            .click(Locator::XPath(
+                "//button[contains(text(), 'Change password')]",
+            ))
+            .await?;
+        // Proton asks for the current password again before the change
+        session
+            .send_keys(
+                Locator::Css("#password"),
+                &format!("{}{}", current.expose(), ENTER),
+            )
+            .await?;
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        session
+            .send_keys(Locator::Css("#newPassword"), new.expose())
+            .await?;
+        session
+            .send_keys(
+                Locator::Css("#confirmPassword"),
+                &format!("{}{}", new.expose(), ENTER),
+            )
+            .await?;
+        match session
+            .outcome(PROTON_PASSWORD_CHANGED, PROTON_REJECTED)
+            .await?
+        {
+            Outcome::Confirmed => Ok(()),
+            Outcome::Rejected(reason) => eyre::bail!("Password change rejected: {}", reason),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRotator for ProtonMailPasswordRotator {
+    fn service(&self) -> &'static str {
+        "protonmail"
+    }
+
+    async fn rotate(&self, current: &Secret, new: &Secret) -> eyre::Result<()> {
+        let session = self.driver.session().await?;
+        let result = self.change_password(&session, current, new).await;
+        finish(session, result).await
+    }
+
+    async fn check(&self, password: &Secret) -> eyre::Result<bool> {
+        let session = self.driver.session().await?;
+        let result = self.log_in(&session, password).await;
+        finish(session, result).await
+    }
+}
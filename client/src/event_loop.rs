@@ -1,223 +1,410 @@
-use std::env;
-
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-
-use crate::twitter::{builder::TwitterClient, tweet::Tweet};
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Message {
-    role: String,
-    content: Option<String>,
-    name: Option<String>,
-    function_call: Option<FunctionCall>,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct FunctionCall {
-    name: String,
-    arguments: String,
-}
-
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<Message>,
-    functions: Option<Vec<FunctionDefinition>>,
-    function_call: Option<Value>, // Can be "auto" or a specific function name
-    max_tokens: Option<u32>,
-    temperature: Option<f32>,
-}
-
-#[derive(Serialize, Debug, Clone)]
-struct FunctionDefinition {
-    name: String,
-    description: Option<String>,
-    parameters: Value,
-}
-
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
-}
+use std::{sync::Arc, time::Duration};
+
+use futures::future::join_all;
+
+use crate::accounts::AgentConfig;
+use crate::action_log::{Action, ActionLog};
+use crate::llm::{CompletionRequest, LlmBackend, Message, ToolCall};
+use crate::mentions::{mention_prompt, MentionCursor};
+use crate::metrics::metrics;
+use crate::schedule::Scheduler;
+use crate::settings::AgentSettings;
+use crate::status::AgentStatus;
+use crate::tools::{ToolContext, ToolRegistry};
+use crate::twitter::{
+    builder::TwitterClient,
+    conversation::Conversation,
+    error::TwitterError,
+    mentions::Mention,
+    rate_limit::{self, RateLimitBudget, TWEETS_ENDPOINT},
+};
 
 struct Agent<'a> {
-    api_key: String,
-    client: Client,
-    model: String,
-    functions: Vec<FunctionDefinition>,
-    system_prompt: Option<String>,
+    llm: Arc<dyn LlmBackend>,
+    tools: ToolRegistry,
+    settings: Arc<AgentSettings>,
     twitter_client: TwitterClient<'a>,
+    user_id: String,
+    action_log: Arc<ActionLog>,
+    status: Arc<AgentStatus>,
+    scheduler: Scheduler,
 }
 
 impl<'a> Agent<'a> {
-    fn new(functions: Vec<FunctionDefinition>, system_prompt: Option<String>, twitter_client: TwitterClient<'a>) -> Self {
-        let api_key = env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        llm: Arc<dyn LlmBackend>,
+        tools: ToolRegistry,
+        settings: Arc<AgentSettings>,
+        twitter_client: TwitterClient<'a>,
+        user_id: String,
+        action_log: Arc<ActionLog>,
+        status: Arc<AgentStatus>,
+        scheduler: Scheduler,
+    ) -> Self {
         Agent {
-            api_key,
-            client: Client::new(),
-            model: "gpt-4o".to_string(),
-            functions,
-            system_prompt,
+            llm,
+            tools,
+            settings,
             twitter_client,
+            user_id,
+            action_log,
+            status,
+            scheduler,
         }
     }
 
-    async fn call_openai_api(
-        &self,
-        messages: &Vec<Message>,
-    ) -> eyre::Result<ChatCompletionResponse> {
-        let request_body = ChatCompletionRequest {
-            model: self.model.clone(),
-            messages: messages.clone(),
-            functions: Some(self.functions.clone()),
-            function_call: Some(serde_json::json!("auto")),
-            max_tokens: Some(500),
-            temperature: Some(0.5),
+    async fn complete(&self, messages: &[Message]) -> eyre::Result<Message> {
+        let request = CompletionRequest {
+            messages: messages.to_vec(),
+            tools: self.tools.definitions(),
+            max_tokens: Some(self.settings.model.max_tokens),
+            temperature: Some(self.settings.model.temperature),
         };
+        self.llm.complete(&request).await
+    }
+
+    async fn handle_tool_call(&self, tool_call: &ToolCall) -> eyre::Result<String> {
+        let function_call = &tool_call.function;
+        log::info!("Assistant is calling tool {}", function_call.name);
+        log::debug!("With arguments: {}", function_call.arguments);
+
+        let ctx = ToolContext::new(
+            &self.twitter_client,
+            &self.user_id,
+            &self.action_log,
+            &self.scheduler,
+        );
+        self.tools.invoke(&ctx, tool_call).await
+    }
+
+    /// Runs every tool call from one assistant turn and returns the `tool`
+    /// messages answering them, in the order they were requested. Runs of
+    /// consecutive calls that are safe to reorder execute concurrently; the
+    /// rest execute one at a time. A failed call fails the turn, but only
+    /// once every call in its batch is logged, since its siblings may
+    /// already have acted.
+    async fn handle_tool_calls(&self, tool_calls: &[ToolCall]) -> eyre::Result<Vec<Message>> {
+        let mut responses = Vec::with_capacity(tool_calls.len());
+        let runs_concurrently = |call: &ToolCall| self.tools.runs_concurrently(call);
+        for batch in tool_calls.chunk_by(|a, b| runs_concurrently(a) && runs_concurrently(b)) {
+            let outputs = join_all(batch.iter().map(|call| self.handle_tool_call(call))).await;
+            let mut failure = None;
+            for (call, output) in batch.iter().zip(outputs) {
+                let output = match output {
+                    Ok(output) => output,
+                    Err(e) => {
+                        let output = format!("Failed: {}", e);
+                        failure.get_or_insert(e);
+                        output
+                    }
+                };
+                self.action_log
+                    .record(
+                        &self.user_id,
+                        Action::ToolCall {
+                            id: call.id.clone(),
+                            name: call.function.name.clone(),
+                            arguments: call.function.arguments.clone(),
+                            output: output.clone(),
+                        },
+                    )
+                    .await?;
+                self.status.record_action(&call.function.name);
+                responses.push(Message::tool(call, output));
+            }
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+        Ok(responses)
+    }
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request_body)
-            .send()
+    /// Runs the persona on `prompt`: the scheduled prompt, or the one for
+    /// the mention `mention_id`.
+    pub async fn run(&self, prompt: &str, mention_id: Option<&str>) -> eyre::Result<String> {
+        let mut messages = vec![
+            Message::system(self.settings.persona.system_prompt.clone()),
+            Message::user(prompt),
+        ];
+        self.action_log
+            .record(
+                &self.user_id,
+                Action::Prompt {
+                    messages: messages.clone(),
+                    mention_id: mention_id.map(str::to_string),
+                },
+            )
             .await?;
 
-        if !response.status().is_success() {
-            eprintln!("Request failed with status: {}", response.status());
-            let error_text = response.text().await?;
-            eprintln!("Error body: {}", error_text);
-            eyre::bail!("API request failed");
+        for _ in 0..self.settings.limits.max_turns {
+            let message = self.complete(&messages).await?;
+            self.action_log
+                .record(
+                    &self.user_id,
+                    Action::ModelResponse {
+                        message: message.clone(),
+                    },
+                )
+                .await?;
+
+            if let Some(tool_calls) = message.tool_calls.clone().filter(|c| !c.is_empty()) {
+                let responses = self.handle_tool_calls(&tool_calls).await?;
+                messages.push(message);
+                messages.extend(responses);
+            } else if let Some(content) = &message.content {
+                return Ok(content.clone());
+            } else {
+                eyre::bail!("Failed to get a response from the assistant.")
+            }
         }
 
-        let completion_response: ChatCompletionResponse = response.json().await?;
-        Ok(completion_response)
+        eyre::bail!(
+            "Assistant still calling tools after {} turns.",
+            self.settings.limits.max_turns
+        )
     }
 
-    async fn handle_function_call(&self, function_call: &FunctionCall) -> eyre::Result<String> {
-        match function_call.name.as_str() {
-            "tweet_joke" => {
-                let args: Value = serde_json::from_str(&function_call.arguments)?;
-                let joke = args["joke"]
-                    .as_str()
-                    .ok_or_else(|| eyre::eyre!("Missing 'joke' field in arguments"))?;
-                let result = tweet_joke(&self.twitter_client, joke).await;
-                match result {
-                    Ok(_) => Ok("Tweeted successfully".to_string()), // Return a success message
-                    Err(e) => Err(e), // Propagate the error
-                }
+    /// The thread `mention` replies to, if it replies to one and it could be
+    /// fetched. The mention is still answered without it.
+    async fn thread_above(&self, mention: &Mention) -> Option<Conversation> {
+        let depth = self.settings.mentions.thread_depth;
+        let replied_to = mention.in_reply_to.as_ref().filter(|_| depth > 0)?;
+        match self
+            .twitter_client
+            .get_conversation(&replied_to.id, depth)
+            .await
+        {
+            Ok(thread) => Some(thread),
+            Err(e) => {
+                log::warn!("Failed to fetch the thread above {}: {}", mention.id, e);
+                None
             }
-            _ => eyre::bail!("Unknown function: {}", function_call.name),
         }
     }
 
-    pub async fn run(&self, user_input: &str) -> eyre::Result<String> {
-        let mut messages = Vec::new();
-
-        // Add system prompt if provided
-        if let Some(system_prompt) = &self.system_prompt {
-            messages.push(Message {
-                role: "system".to_string(),
-                content: Some(system_prompt.clone()),
-                name: None,
-                function_call: None,
-            });
+    /// Runs the agent on `mention`. False if it should be retried later,
+    /// e.g. once quiet hours are over or the daily posts allow a reply.
+    async fn answer(&self, mention: &Mention) -> eyre::Result<bool> {
+        let own = mention
+            .author
+            .as_ref()
+            .is_some_and(|author| author.id == self.user_id);
+        if own {
+            return Ok(true);
         }
+        if self.scheduler.is_quiet() || self.scheduler.posts_left() == 0 {
+            log::debug!(
+                "Holding mention {} until the schedule allows a reply",
+                mention.id
+            );
+            return Ok(false);
+        }
+        let thread = self.thread_above(mention).await;
+        let prompt = mention_prompt(
+            mention,
+            thread.as_ref(),
+            &self.settings.mentions.instructions,
+        );
+        let logged_before = self.action_log.len().await;
+        match self.run(&prompt, Some(&mention.id)).await {
+            Ok(response) => {
+                metrics().record_agent_run("ok");
+                log::info!("Assistant: {}", response);
+                Ok(true)
+            }
+            Err(e) => match e.downcast_ref::<TwitterError>() {
+                Some(TwitterError::RateLimited { reset_at, .. }) => {
+                    metrics().record_agent_run("rate_limited");
+                    let backoff = rate_limit::backoff(*reset_at);
+                    log::warn!(
+                        "Rate limited answering mentions, backing off for {:?}",
+                        backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    // Answering again would repeat whatever was posted
+                    Ok(self.posted_since(logged_before).await)
+                }
+                Some(twitter_error) if twitter_error.is_fatal() => {
+                    metrics().record_agent_run("fatal");
+                    Err(e)
+                }
+                _ => {
+                    metrics().record_agent_run("error");
+                    log::error!("Failed to answer mention {}: {}", mention.id, e);
+                    Ok(true)
+                }
+            },
+        }
+    }
 
-        // Add user message
-        messages.push(Message {
-            role: "user".to_string(),
-            content: Some(user_input.to_string()),
-            name: None,
-            function_call: None,
-        });
+    /// Whether this account has posted since the action log held `len`
+    /// entries.
+    async fn posted_since(&self, len: u64) -> bool {
+        self.action_log
+            .entries(len, usize::MAX)
+            .await
+            .iter()
+            .filter(|entry| entry.account.as_deref() == Some(self.user_id.as_str()))
+            .any(|entry| {
+                matches!(&entry.action, Action::TwitterResponse { endpoint, .. } if endpoint == TWEETS_ENDPOINT)
+            })
+    }
 
+    /// Polls for mentions and answers each new one, oldest first. Without a
+    /// cursor, mentions from before the first poll are skipped rather than
+    /// answered late.
+    async fn answer_mentions(&self, cursor: &MentionCursor) -> eyre::Result<()> {
+        let interval = Duration::from_secs(self.settings.mentions.poll_interval_secs);
+        let mut skip_backlog = cursor.since_id().is_none();
         loop {
-            let completion_response = self.call_openai_api(&messages).await?;
-
-            let choice = &completion_response.choices[0];
-            let message = &choice.message;
-
-            if let Some(function_call) = &message.function_call {
-                println!("Assistant is calling function: {}", function_call.name);
-                println!("With arguments: {}", function_call.arguments);
-
-                let function_response = self.handle_function_call(function_call).await?;
-
-                messages.push(Message {
-                    role: "assistant".to_string(),
-                    content: None,
-                    name: None,
-                    function_call: Some(function_call.clone()),
-                });
-
-                messages.push(Message {
-                    role: "function".to_string(),
-                    content: Some(function_response),
-                    name: Some(function_call.name.clone()),
-                    function_call: None,
-                });
-            } else if let Some(content) = &message.content {
-                return Ok(content.clone());
-            } else {
-                break;
+            let since_id = cursor.since_id();
+            match self
+                .twitter_client
+                .get_mentions(&self.user_id, since_id.as_deref())
+                .await
+            {
+                Ok(mentions) if skip_backlog => {
+                    skip_backlog = false;
+                    if let Some(newest) = mentions.last() {
+                        log::info!("Skipping {} earlier mentions", mentions.len());
+                        cursor.advance(&newest.id).await?;
+                    }
+                }
+                Ok(mentions) => {
+                    for mention in mentions {
+                        if !self.answer(&mention).await? {
+                            break;
+                        }
+                        cursor.advance(&mention.id).await?;
+                    }
+                }
+                Err(TwitterError::RateLimited { reset_at, .. }) => {
+                    let backoff = rate_limit::backoff(reset_at);
+                    log::warn!(
+                        "Rate limited polling mentions, backing off for {:?}",
+                        backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    continue;
+                }
+                Err(e) if e.is_fatal() => {
+                    log::error!("Lost access to the account: {}", e);
+                    return Err(e.into());
+                }
+                Err(e) => log::error!("Failed to poll mentions: {}", e),
             }
+            tokio::time::sleep(interval).await;
         }
-
-        eyre::bail!("Failed to get a response from the assistant.")
     }
 }
 
-async fn tweet_joke<'a>(client: &TwitterClient<'a>, joke: &str) -> eyre::Result<()> {
-    let tweet = Tweet::new(joke.to_string());
-    match client.raw_tweet(tweet).await {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            eprintln!("Failed to tweet joke: {}", e); // Log the error
-            Err(eyre::eyre!("Failed to tweet joke")) // Return a custom error message
-        }
+/// Spreads the remaining tweet `budget` evenly over the current rate-limit
+/// window, never running more often than `min_interval`.
+pub fn next_run_delay(budget: Option<RateLimitBudget>, min_interval: Duration) -> Duration {
+    match budget {
+        Some(budget) if budget.remaining == 0 => budget.until_reset().max(min_interval),
+        Some(budget) => (budget.until_reset() / budget.remaining).max(min_interval),
+        None => min_interval,
     }
 }
 
-pub async fn event_loop<'a>(twitter_client: TwitterClient<'a>) -> eyre::Result<()> {
-    // Define the function(s) that the assistant can call
-    let functions = vec![
-        FunctionDefinition {
-            name: "tweet_joke".to_string(),
-            description: Some("Tweets a joke.".to_string()),
-            parameters: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "joke": { "type": "string", "description": "The joke to be tweeted." }
-                },
-            }),
-        },
-    ];
-
-    let tweet_system_prompt =
-        Some("You are great at coming up with surprising links and unhinged and deranged analogies between things and see the underlying similarity between seemingly different ideas. 
-            And you are very snarky. 
-            Your words should bring out people's sentiments and can go viral. 
-            You need to make a tweet that is an unhinged joke about crypto.
-            You think Ethereum L1 roadmap politics discussion is like supporting your local football team, you don't want it but everybody talks about it so you force yourself to read the ethresearch posts just to get invested in the characters.
-            Don't use hashtags.".to_string());
-
-    let agent = Agent::new(functions, tweet_system_prompt, twitter_client);
-
-    let user_message = "make a tweet";
+pub async fn event_loop(
+    twitter_client: TwitterClient<'_>,
+    config: AgentConfig,
+) -> eyre::Result<()> {
+    let AgentConfig {
+        llm,
+        images,
+        action_log,
+        watchdog,
+        status,
+        settings,
+        clock,
+        mention_cursor,
+    } = config;
+    let tools = ToolRegistry::all(images).retain(|name| settings.enables(name));
+
+    // Like and retweet act on behalf of this account
+    let user_id = twitter_client.get_user_info().await?.id;
+
+    let min_interval = Duration::from_secs(settings.schedule.min_interval_secs);
+    // Resume this account's schedule where the log left it; two days of
+    // posts cover today and yesterday in any timezone
+    let last_run = action_log.last_run_at(&user_id).await;
+    let posts = action_log
+        .posted_at(&user_id, clock.now().saturating_sub(2 * 24 * 60 * 60))
+        .await;
+    let scheduler =
+        Scheduler::new(&settings.schedule, clock.clone())?.with_history(last_run, posts);
+    let agent = Agent::new(
+        llm,
+        tools,
+        settings,
+        twitter_client,
+        user_id,
+        action_log,
+        status,
+        scheduler,
+    );
+
+    let agent_loop = async {
+        let mut earliest = clock.now();
+        loop {
+            let at = agent.scheduler.next_run(earliest);
+            let delay = Duration::from_secs(at.saturating_sub(clock.now()));
+            if !delay.is_zero() {
+                log::info!("Next agent run in {:?}", delay);
+                agent.status.schedule_next_run(Some(delay));
+                tokio::time::sleep(delay).await;
+            }
 
-    loop {
-        let response = agent.run(user_message).await?;
-        println!("Assistant: {}", response);
+            agent.status.schedule_next_run(None);
+            match agent.run(&agent.settings.persona.prompt, None).await {
+                Ok(response) => {
+                    metrics().record_agent_run("ok");
+                    log::info!("Assistant: {}", response)
+                }
+                Err(e) => match e.downcast_ref::<TwitterError>() {
+                    Some(TwitterError::RateLimited { reset_at, .. }) => {
+                        metrics().record_agent_run("rate_limited");
+                        let backoff = rate_limit::backoff(*reset_at);
+                        log::warn!("Rate limited by Twitter, backing off for {:?}", backoff);
+                        agent.status.schedule_next_run(Some(backoff));
+                        tokio::time::sleep(backoff).await;
+                        earliest = clock.now();
+                        continue;
+                    }
+                    Some(twitter_error) if twitter_error.is_fatal() => {
+                        metrics().record_agent_run("fatal");
+                        log::error!("Lost access to the account: {}", twitter_error);
+                        return Err(e);
+                    }
+                    _ => {
+                        metrics().record_agent_run("error");
+                        log::error!("Agent run failed: {}", e)
+                    }
+                },
+            }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+            earliest = clock.now()
+                + next_run_delay(
+                    agent.twitter_client.rate_limit_budget(TWEETS_ENDPOINT),
+                    min_interval,
+                )
+                .as_secs();
+        }
+    };
+
+    // Shares the client, and so its rate-limit budgets and OAuth 2.0 token,
+    // with the agent
+    tokio::select! {
+        result = agent_loop => result,
+        result = agent.answer_mentions(&mention_cursor), if agent.settings.mentions.enabled => result,
+        result = watchdog.run(&agent.twitter_client, &agent.user_id) => result,
+        result = agent.twitter_client.keep_oauth2_token_fresh() => {
+            log::error!("Lost access to the account: {:?}", result);
+            result.map_err(Into::into)
+        }
     }
-    Ok(())
 }
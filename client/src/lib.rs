@@ -0,0 +1,19 @@
+pub mod accounts;
+pub mod action_log;
+pub mod attestation;
+pub mod clock;
+pub mod credentials;
+pub mod encumbrance;
+pub mod event_loop;
+pub mod llm;
+pub mod mentions;
+pub mod metrics;
+pub mod release;
+pub mod schedule;
+pub mod sealing;
+pub mod server;
+pub mod settings;
+pub mod status;
+pub mod tools;
+pub mod twitter;
+pub mod watchdog;
@@ -0,0 +1,222 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{check_status, CompletionRequest, LlmBackend, Message, TokenUsage, ToolCall};
+use crate::metrics::metrics;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+pub const DEFAULT_MODEL: &str = "claude-3-5-sonnet-latest";
+const API_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`; used when the request has none.
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    tools: Vec<Tool>,
+    temperature: Option<f32>,
+}
+
+#[derive(Serialize, Debug)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: Vec<Value>,
+}
+
+#[derive(Serialize, Debug)]
+struct Tool {
+    name: String,
+    description: Option<String>,
+    input_schema: Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Usage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// The Anthropic Messages API.
+pub struct AnthropicBackend {
+    api_key: String,
+    base_url: String,
+    model: String,
+    client: Client,
+}
+
+impl AnthropicBackend {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+/// Splits out the system prompt and rewrites tool calls and results as
+/// `tool_use` / `tool_result` blocks, merging consecutive same-role turns as
+/// the API requires strictly alternating roles. This also folds the results
+/// of parallel tool calls into a single user turn.
+fn to_anthropic(messages: &[Message]) -> (Option<String>, Vec<AnthropicMessage>) {
+    let mut system: Vec<String> = Vec::new();
+    let mut out: Vec<AnthropicMessage> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message.role.as_str() {
+            "system" => {
+                system.extend(message.content.clone());
+                continue;
+            }
+            "assistant" => {
+                let mut blocks: Vec<Value> = message
+                    .content
+                    .iter()
+                    .map(|text| json!({ "type": "text", "text": text }))
+                    .collect();
+                for call in message.tool_calls.iter().flatten() {
+                    let input: Value = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            "tool" => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                    "content": message.content.clone().unwrap_or_default(),
+                })],
+            ),
+            _ => (
+                "user",
+                message
+                    .content
+                    .iter()
+                    .map(|text| json!({ "type": "text", "text": text }))
+                    .collect(),
+            ),
+        };
+        match out.last_mut() {
+            Some(last) if last.role == role => last.content.extend(blocks),
+            _ => out.push(AnthropicMessage {
+                role,
+                content: blocks,
+            }),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    (system, out)
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for AnthropicBackend {
+    async fn complete(&self, request: &CompletionRequest) -> eyre::Result<Message> {
+        metrics()
+            .observe_llm_call("anthropic", &self.model, self.request(request))
+            .await
+    }
+}
+
+impl AnthropicBackend {
+    async fn request(
+        &self,
+        request: &CompletionRequest,
+    ) -> eyre::Result<(Message, Option<TokenUsage>)> {
+        let (system, messages) = to_anthropic(&request.messages);
+        let request_body = MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system,
+            messages,
+            tools: request
+                .tools
+                .iter()
+                .map(|function| Tool {
+                    name: function.name.clone(),
+                    description: function.description.clone(),
+                    input_schema: function.parameters.clone(),
+                })
+                .collect(),
+            temperature: request.temperature,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&request_body)
+            .send()
+            .await?;
+
+        let response: MessagesResponse = check_status(response).await?.json().await?;
+        let mut text = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                ContentBlock::Text { text: t } => text.push(t),
+                ContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ToolCall::function(id, name, input.to_string()));
+                }
+                ContentBlock::Other => {}
+            }
+        }
+
+        let message = Message {
+            role: "assistant".to_string(),
+            content: (!text.is_empty()).then(|| text.join("\n")),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            ..Default::default()
+        };
+        let usage = response.usage.map(|usage| TokenUsage {
+            input: usage.input_tokens,
+            output: usage.output_tokens,
+        });
+        Ok((message, usage))
+    }
+}
@@ -0,0 +1,99 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{check_status, openai::DEFAULT_BASE_URL};
+
+pub const DEFAULT_IMAGE_MODEL: &str = "dall-e-3";
+
+/// Turns a prompt into image bytes ready for `TwitterClient::upload_media`.
+#[async_trait::async_trait]
+pub trait ImageBackend: Send + Sync {
+    async fn generate(&self, prompt: &str) -> eyre::Result<Vec<u8>>;
+}
+
+#[derive(Serialize, Debug)]
+struct ImageGenerationRequest<'p> {
+    model: String,
+    prompt: &'p str,
+    n: u32,
+    response_format: &'static str,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImageGenerationResponse {
+    data: Vec<ImageData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImageData {
+    b64_json: String,
+}
+
+/// The OpenAI images API, or any server implementing it.
+pub struct OpenAiImageBackend {
+    api_key: Option<String>,
+    base_url: String,
+    model: String,
+    client: Client,
+}
+
+impl OpenAiImageBackend {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_IMAGE_MODEL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageBackend for OpenAiImageBackend {
+    async fn generate(&self, prompt: &str) -> eyre::Result<Vec<u8>> {
+        let request_body = ImageGenerationRequest {
+            model: self.model.clone(),
+            prompt,
+            n: 1,
+            response_format: "b64_json",
+        };
+
+        let mut builder = self
+            .client
+            .post(format!("{}/images/generations", self.base_url))
+            .json(&request_body);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = builder.send().await?;
+
+        let response: ImageGenerationResponse = check_status(response).await?.json().await?;
+        let image = response
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| eyre::eyre!("Image response has no data"))?;
+        Ok(STANDARD.decode(image.b64_json)?)
+    }
+}
+
+/// Always returns the same bytes, for tests.
+pub struct StaticImageBackend(pub Vec<u8>);
+
+#[async_trait::async_trait]
+impl ImageBackend for StaticImageBackend {
+    async fn generate(&self, _prompt: &str) -> eyre::Result<Vec<u8>> {
+        Ok(self.0.clone())
+    }
+}
@@ -0,0 +1,197 @@
+use std::{env, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub mod anthropic;
+pub mod image;
+pub mod openai;
+pub mod scripted;
+
+pub use anthropic::AnthropicBackend;
+pub use image::{ImageBackend, OpenAiImageBackend, StaticImageBackend};
+pub use openai::OpenAiBackend;
+pub use scripted::ScriptedBackend;
+
+/// A chat message in the OpenAI shape, which every backend translates to and
+/// from its own wire format.
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Message {
+    pub role: String,
+    pub content: Option<String>,
+    pub name: Option<String>,
+    /// Set on assistant messages that call one or more tools.
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Set on `tool` messages, naming the call they answer.
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: Some(content.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: Some(content.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: Some(content.into()),
+            ..Default::default()
+        }
+    }
+
+    /// The result of `tool_call`, fed back to the model.
+    pub fn tool(tool_call: &ToolCall, content: impl Into<String>) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: Some(content.into()),
+            tool_call_id: Some(tool_call.id.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    /// Always `"function"`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+impl ToolCall {
+    pub fn function(id: impl Into<String>, name: impl Into<String>, arguments: String) -> Self {
+        Self {
+            id: id.into(),
+            kind: "function".to_string(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Value,
+}
+
+/// Everything a backend needs for one completion, minus the model, which is
+/// part of the backend's own configuration.
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub messages: Vec<Message>,
+    pub tools: Vec<FunctionDefinition>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+/// Tokens a completion used, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+}
+
+/// Passes successful responses through; anything else becomes an error
+/// carrying the status and the provider's explanation from the body.
+async fn check_status(response: reqwest::Response) -> eyre::Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let url = response.url().clone();
+    let body = response.text().await.unwrap_or_default();
+    log::warn!("Request to {} failed with status {}", url, status);
+    eyre::bail!("API request failed with status {}: {}", status, body)
+}
+
+#[async_trait::async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Returns the assistant's next message, which carries content, tool
+    /// calls, or both.
+    async fn complete(&self, request: &CompletionRequest) -> eyre::Result<Message>;
+}
+
+/// Picks a backend from the environment:
+///
+/// - `LLM_PROVIDER`: `openai` (default, any OpenAI-compatible server) or `anthropic`
+/// - `OPENAI_API_KEY` / `OPENAI_BASE_URL` for `openai`; the key is optional
+///   so local llama.cpp or vLLM servers work
+/// - `ANTHROPIC_API_KEY` / `ANTHROPIC_BASE_URL` for `anthropic`
+///
+/// `model` overrides the provider's default model.
+pub fn from_env(model: Option<String>) -> eyre::Result<Arc<dyn LlmBackend>> {
+    let provider = env::var("LLM_PROVIDER").unwrap_or_else(|_| "openai".to_string());
+    match provider.as_str() {
+        "openai" => {
+            let mut backend = OpenAiBackend::new(env::var("OPENAI_API_KEY").ok());
+            if let Ok(base_url) = env::var("OPENAI_BASE_URL") {
+                backend = backend.with_base_url(base_url);
+            }
+            if let Some(model) = model {
+                backend = backend.with_model(model);
+            }
+            Ok(Arc::new(backend))
+        }
+        "anthropic" => {
+            let api_key = env::var("ANTHROPIC_API_KEY")
+                .map_err(|_| eyre::eyre!("ANTHROPIC_API_KEY not set"))?;
+            let mut backend = AnthropicBackend::new(api_key);
+            if let Ok(base_url) = env::var("ANTHROPIC_BASE_URL") {
+                backend = backend.with_base_url(base_url);
+            }
+            if let Some(model) = model {
+                backend = backend.with_model(model);
+            }
+            Ok(Arc::new(backend))
+        }
+        other => eyre::bail!("Unknown LLM_PROVIDER: {}", other),
+    }
+}
+
+/// Picks an image backend from the environment, if any:
+///
+/// - `IMAGE_PROVIDER`: `openai`, or unset to disable image generation
+/// - `IMAGE_MODEL`: overrides the default image model
+/// - `OPENAI_API_KEY` / `OPENAI_BASE_URL` as for `from_env`
+pub fn image_from_env() -> eyre::Result<Option<Arc<dyn ImageBackend>>> {
+    let Ok(provider) = env::var("IMAGE_PROVIDER") else {
+        return Ok(None);
+    };
+    match provider.as_str() {
+        "openai" => {
+            let mut backend = OpenAiImageBackend::new(env::var("OPENAI_API_KEY").ok());
+            if let Ok(base_url) = env::var("OPENAI_BASE_URL") {
+                backend = backend.with_base_url(base_url);
+            }
+            if let Ok(model) = env::var("IMAGE_MODEL") {
+                backend = backend.with_model(model);
+            }
+            Ok(Some(Arc::new(backend)))
+        }
+        other => eyre::bail!("Unknown IMAGE_PROVIDER: {}", other),
+    }
+}
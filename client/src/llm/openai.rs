@@ -0,0 +1,133 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{check_status, CompletionRequest, FunctionDefinition, LlmBackend, Message, TokenUsage};
+use crate::metrics::metrics;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_MODEL: &str = "gpt-4o";
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<Message>,
+    tools: Option<Vec<ToolDefinition>>,
+    tool_choice: Option<Value>, // Can be "auto", "none" or a specific tool
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+}
+
+#[derive(Serialize, Debug)]
+struct ToolDefinition {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: FunctionDefinition,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionResponse {
+    choices: Vec<Choice>,
+    /// Missing from some OpenAI-compatible servers.
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: Message,
+}
+
+/// Any server speaking the OpenAI chat completions API: OpenAI itself, or a
+/// local llama.cpp / vLLM server via `with_base_url`.
+pub struct OpenAiBackend {
+    api_key: Option<String>,
+    base_url: String,
+    model: String,
+    client: Client,
+}
+
+impl OpenAiBackend {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for OpenAiBackend {
+    async fn complete(&self, request: &CompletionRequest) -> eyre::Result<Message> {
+        metrics()
+            .observe_llm_call("openai", &self.model, self.request(request))
+            .await
+    }
+}
+
+impl OpenAiBackend {
+    async fn request(
+        &self,
+        request: &CompletionRequest,
+    ) -> eyre::Result<(Message, Option<TokenUsage>)> {
+        let tools: Vec<ToolDefinition> = request
+            .tools
+            .iter()
+            .map(|function| ToolDefinition {
+                kind: "function",
+                function: function.clone(),
+            })
+            .collect();
+        // The API rejects `tool_choice` without any tools
+        let tool_choice = (!tools.is_empty()).then(|| serde_json::json!("auto"));
+        let request_body = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: request.messages.clone(),
+            tools: (!tools.is_empty()).then_some(tools),
+            tool_choice,
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+        };
+
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&request_body);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = builder.send().await?;
+
+        let completion_response: ChatCompletionResponse =
+            check_status(response).await?.json().await?;
+        let usage = completion_response.usage.map(|usage| TokenUsage {
+            input: usage.prompt_tokens,
+            output: usage.completion_tokens,
+        });
+        let message = completion_response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| eyre::eyre!("Completion response has no choices"))?;
+        Ok((message, usage))
+    }
+}
@@ -0,0 +1,57 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use super::{CompletionRequest, LlmBackend, Message, ToolCall};
+
+/// Replays a fixed sequence of assistant messages, for tests.
+#[derive(Default)]
+pub struct ScriptedBackend {
+    responses: Mutex<VecDeque<Message>>,
+    requests: Mutex<Vec<CompletionRequest>>,
+}
+
+impl ScriptedBackend {
+    pub fn new(responses: Vec<Message>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into()),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// An assistant message calling each `(name, arguments)` pair in one turn,
+    /// with ids `call_0`, `call_1`, ...
+    pub fn tool_calls(calls: Vec<(&str, serde_json::Value)>) -> Message {
+        let tool_calls = calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, arguments))| {
+                ToolCall::function(format!("call_{}", i), name, arguments.to_string())
+            })
+            .collect();
+        Message {
+            role: "assistant".to_string(),
+            tool_calls: Some(tool_calls),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&self, response: Message) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    /// Every request received so far, in order.
+    pub fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for ScriptedBackend {
+    async fn complete(&self, request: &CompletionRequest) -> eyre::Result<Message> {
+        self.requests.lock().unwrap().push(request.clone());
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| eyre::eyre!("Scripted backend has no responses left"))
+    }
+}
@@ -1,131 +1,328 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
-use axum::{
-    extract::{Query, State},
-    response::Redirect,
+use client::{
+    accounts::{AgentConfig, Agents},
+    action_log::ActionLog,
+    attestation::{self, AttestationClaims, AttestationReport, EnclaveKey},
+    clock::{Clock, SystemClock},
+    credentials::{AccountStore, CredentialStore, RefreshedTokenSaver, StoredCredentials},
+    encumbrance::{RotationState, Vault},
+    llm,
+    mentions::MentionCursor,
+    release::{Release, ReleasePayload, ReleasePolicy},
+    sealing::KeyFileProvider,
+    server::{self, SharedState},
+    settings::LoadedSettings,
+    status::AgentStatus,
+    twitter::{
+        auth::{OAuth2Config, UserAuth},
+        builder::{TwitterBuilder, TwitterClient},
+    },
+    watchdog::Watchdog,
 };
-use serde::Deserialize;
-use tokio::sync::{oneshot, Mutex};
-use tower_http::cors::CorsLayer;
-use twitter::{auth::TwitterTokenPair, builder::TwitterBuilder};
-
-mod event_loop;
-mod twitter;
-
-#[derive(Clone)]
-pub struct SharedState {
-    tee_url: String,
-    twitter_builder: TwitterBuilder,
-    twitter_token_pair: Arc<Mutex<Option<TwitterTokenPair>>>,
-    shutdown_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-}
 
-#[derive(Deserialize)]
-pub struct CallbackQuery {
-    oauth_token: String,
-    oauth_verifier: String,
-}
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    dotenv::dotenv().ok();
 
-pub async fn login(State(shared_state): State<SharedState>) -> Redirect {
-    let callback_url = format!("{}/callback", shared_state.tee_url.clone(),);
+    // Without CREDENTIALS_DIR nothing is persisted and every start needs a login
+    let store = std::env::var("CREDENTIALS_DIR").ok().map(|dir| {
+        let key_path = std::env::var("SEALING_KEY_PATH").expect("SEALING_KEY_PATH not set");
+        std::fs::create_dir_all(&dir).expect("Failed to create CREDENTIALS_DIR");
+        Arc::new(AccountStore::new(
+            dir,
+            Arc::new(KeyFileProvider::new(key_path)),
+        ))
+    });
+    let stored = match &store {
+        Some(store) => store
+            .load_all()
+            .await
+            .expect("Failed to load stored credentials"),
+        None => BTreeMap::new(),
+    };
 
-    let oauth_tokens = shared_state
-        .twitter_builder
-        .request_oauth_token(callback_url)
-        .await
-        .expect("Failed to request oauth token");
+    // Every account logs in through the same app
+    let (consumer_key, consumer_secret) = match stored.values().next() {
+        Some(stored) => (stored.consumer_key.clone(), stored.consumer_secret.clone()),
+        None => (
+            std::env::var("TWITTER_CONSUMER_KEY").expect("TWITTER_CONSUMER_KEY not set"),
+            std::env::var("TWITTER_CONSUMER_SECRET").expect("TWITTER_CONSUMER_SECRET not set"),
+        ),
+    };
 
-    let mut db = shared_state.twitter_token_pair.lock().await;
-    *db = Some(oauth_tokens.clone());
+    let mut twitter_builder = TwitterBuilder::new(consumer_key, consumer_secret);
+    // Optional overrides, e.g. to run against a local mock server
+    if let Ok(url) = std::env::var("TWITTER_API_BASE_URL") {
+        twitter_builder = twitter_builder.with_api_base_url(url);
+    }
+    if let Ok(url) = std::env::var("TWITTER_UPLOAD_BASE_URL") {
+        twitter_builder = twitter_builder.with_upload_base_url(url);
+    }
+    if let Ok(url) = std::env::var("TWITTER_OAUTH_BASE_URL") {
+        twitter_builder = twitter_builder.with_oauth_base_url(url);
+    }
+    if let Ok(url) = std::env::var("TWITTER_AUTHORIZE_BASE_URL") {
+        twitter_builder = twitter_builder.with_authorize_base_url(url);
+    }
+    // Logins use OAuth 2.0 with PKCE when a client ID is configured
+    if let Ok(client_id) = std::env::var("TWITTER_OAUTH2_CLIENT_ID") {
+        let mut oauth2 = OAuth2Config::new(client_id);
+        if let Ok(client_secret) = std::env::var("TWITTER_OAUTH2_CLIENT_SECRET") {
+            oauth2 = oauth2.with_client_secret(client_secret);
+        }
+        if let Ok(scopes) = std::env::var("TWITTER_OAUTH2_SCOPES") {
+            oauth2 = oauth2.with_scopes(scopes.split_whitespace().map(str::to_string).collect());
+        }
+        twitter_builder = twitter_builder.with_oauth2(oauth2);
+    }
 
-    let url = format!(
-        "https://api.twitter.com/oauth/authenticate?oauth_token={}",
-        oauth_tokens.token
+    let settings = Arc::new(
+        LoadedSettings::from_env()
+            .await
+            .expect("Invalid agent config"),
     );
+    log::info!("Loaded agent config with sha256 {}", settings.sha256);
 
-    Redirect::temporary(&url)
-}
-
-pub async fn callback(
-    State(shared_state): State<SharedState>,
-    Query(query): Query<CallbackQuery>,
-) -> String {
-    let oauth_token = query.oauth_token;
-    let oauth_verifier = query.oauth_verifier;
-
-    let twitter_token_pair = shared_state
-        .twitter_token_pair
-        .lock()
-        .await
+    // A model named in the config wins over LLM_MODEL, so the attested hash
+    // covers it
+    let model = settings
+        .settings
+        .model
+        .name
         .clone()
-        .unwrap();
-
-    assert_eq!(oauth_token, twitter_token_pair.token);
+        .or_else(|| std::env::var("LLM_MODEL").ok());
+    let llm = llm::from_env(model).expect("Failed to configure LLM backend");
+    let images = llm::image_from_env().expect("Failed to configure image backend");
+    let lists_media = settings
+        .settings
+        .tools
+        .iter()
+        .flatten()
+        .any(|tool| tool == "tweet_with_media");
+    assert!(
+        images.is_some() || !lists_media,
+        "tweet_with_media requires IMAGE_PROVIDER"
+    );
 
-    let token_pair = shared_state
-        .twitter_builder
-        .authorize_token(
-            twitter_token_pair.token.clone(),
-            twitter_token_pair.secret.clone(),
-            oauth_verifier,
-        )
+    let enclave_key = Arc::new(EnclaveKey::generate());
+    let claims = AttestationClaims {
+        binary_sha256: attestation::binary_sha256().expect("Failed to hash binary"),
+        config_sha256: Some(settings.sha256.clone()),
+        public_key: enclave_key.public_key_hex(),
+    };
+    let attestation_provider =
+        attestation::from_env().expect("Failed to configure attestation provider");
+    let attestation = AttestationReport::generate(attestation_provider.as_ref(), claims)
         .await
-        .unwrap();
+        .expect("Failed to generate attestation");
+    log::info!(
+        "Attested enclave key {} with {} quote",
+        attestation.claims.public_key,
+        attestation.provider
+    );
 
-    let mut db = shared_state.twitter_token_pair.lock().await;
-    *db = Some(token_pair.clone());
+    let action_log = Arc::new(match std::env::var("ACTION_LOG_PATH") {
+        Ok(path) => ActionLog::open(enclave_key.clone(), path)
+            .await
+            .expect("Failed to open action log"),
+        Err(_) => ActionLog::new(enclave_key.clone()),
+    });
 
-    let twitter_client = shared_state.twitter_builder.with_auth(token_pair);
-    let x_info = twitter_client
-        .get_user_info()
-        .await
-        .expect("Failed to get user info");
+    let agents = Arc::new(Agents::default());
+
+    // Without a release policy the accounts are never handed back
+    let release = match ReleasePolicy::from_env().expect("Invalid release policy") {
+        Some(policy) => {
+            // Released credentials must be the latest, rotated ones
+            assert!(store.is_some(), "RELEASE_AT requires CREDENTIALS_DIR");
+            let path = std::env::var("RELEASE_PATH").expect("RELEASE_PATH not set");
+            let key_path = std::env::var("SEALING_KEY_PATH").expect("SEALING_KEY_PATH not set");
+            let release = Release::open(
+                policy,
+                Arc::new(SystemClock),
+                path,
+                Arc::new(KeyFileProvider::new(key_path)),
+            )
+            .await
+            .expect("Failed to open release decision");
+            Some(Arc::new(release))
+        }
+        None => None,
+    };
 
-    if let Some(sender) = shared_state.shutdown_sender.lock().await.take() {
-        let _ = sender.send(());
+    // The server outlives the logins so observers can keep auditing the agents
+    let tee_url = std::env::var("TEE_URL").expect("TEE_URL not set");
+    let (shared_state, mut login_receiver) = SharedState::new(
+        tee_url,
+        twitter_builder.clone(),
+        attestation,
+        action_log.clone(),
+        agents.clone(),
+    );
+    let shared_state = match &release {
+        Some(release) => shared_state.with_release(release.clone()),
+        None => shared_state,
     }
+    .with_settings(settings.clone());
+    let app = server::router(shared_state);
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
 
-    let msg = format!("Succesfully logged into {}", x_info.name);
-    msg
-}
+    if release
+        .as_ref()
+        .is_some_and(|release| release.is_released())
+    {
+        log::info!("Accounts already released; not acting as them.");
+        // Turns away any login that gets past the server's own check
+        drop(login_receiver);
+        return std::future::pending().await;
+    }
 
-#[tokio::main]
-async fn main() {
-    env_logger::init();
-    dotenv::dotenv().ok();
+    // Each account gets its own watchdog and status
+    let agent_settings = Arc::new(settings.settings.clone());
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let mentions_dir = std::env::var("MENTIONS_DIR").ok();
+    if let Some(dir) = &mentions_dir {
+        std::fs::create_dir_all(dir).expect("Failed to create MENTIONS_DIR");
+    }
+    let mention_cursor = |user_id: String| {
+        let mentions_dir = mentions_dir.clone();
+        async move {
+            eyre::Ok(Arc::new(match mentions_dir {
+                Some(dir) => MentionCursor::open(dir, &user_id).await?,
+                None => MentionCursor::new(),
+            }))
+        }
+    };
+    // With a persisted action log, each agent resumes its account's schedule
+    // from it, catching up on runs missed while down
+    let agent_config = |mention_cursor| AgentConfig {
+        llm: llm.clone(),
+        images: images.clone(),
+        action_log: action_log.clone(),
+        watchdog: Arc::new(Watchdog::default()),
+        status: Arc::new(AgentStatus::default()),
+        settings: agent_settings.clone(),
+        clock: clock.clone(),
+        mention_cursor,
+    };
+    // OAuth 2.0 refresh tokens are single use, so every client saves each
+    // rotation to its account's file before anything can spend it
+    let client_for = |user_id: &str, auth: UserAuth| -> eyre::Result<TwitterClient<'static>> {
+        let twitter_client = twitter_builder.with_user_auth(auth);
+        let Some(store) = &store else {
+            return Ok(twitter_client);
+        };
+        Ok(
+            twitter_client.with_token_sink(Arc::new(RefreshedTokenSaver::new(
+                Arc::new(store.account(user_id)?),
+                twitter_builder.consumer_key.clone(),
+                twitter_builder.consumer_secret.clone(),
+            ))),
+        )
+    };
 
-    let tee_url = std::env::var("TEE_URL").expect("TEE_URL not set");
-    let consumer_key = std::env::var("TWITTER_CONSUMER_KEY").expect("TWITTER_CONSUMER_KEY not set");
-    let consumer_secret =
-        std::env::var("TWITTER_CONSUMER_SECRET").expect("TWITTER_CONSUMER_SECRET not set");
+    for (user_id, credentials) in stored {
+        let resumed = async {
+            let twitter_client = client_for(&user_id, credentials.auth)?;
+            let user = twitter_client.get_user_info().await?;
+            let cursor = mention_cursor(user_id.clone()).await?;
+            eyre::Ok((user, twitter_client, cursor))
+        };
+        match resumed.await {
+            Ok((user, twitter_client, cursor)) => {
+                log::info!("Resuming @{} with stored credentials.", user.username);
+                agents
+                    .spawn(user, twitter_client, agent_config(cursor))
+                    .await;
+            }
+            Err(e) => log::error!("Not resuming account {}: {}", user_id, e),
+        }
+    }
 
-    let twitter_builder = TwitterBuilder::new(consumer_key, consumer_secret);
+    let accept_logins = async {
+        while let Some(account) = login_receiver.recv().await {
+            log::info!("Received credentials for @{}.", account.user.username);
+            let started = async {
+                if let Some(store) = &store {
+                    let credentials = StoredCredentials {
+                        consumer_key: twitter_builder.consumer_key.clone(),
+                        consumer_secret: twitter_builder.consumer_secret.clone(),
+                        auth: account.auth.clone(),
+                    };
+                    store.account(&account.user.id)?.save(&credentials).await?;
+                }
+                let twitter_client = client_for(&account.user.id, account.auth)?;
+                let cursor = mention_cursor(account.user.id.clone()).await?;
+                agents
+                    .spawn(account.user, twitter_client, agent_config(cursor))
+                    .await;
+                eyre::Ok(())
+            };
+            if let Err(e) = started.await {
+                log::error!("Failed to start agent: {}", e);
+            }
+        }
+    };
 
-    let (shutdown_sender, shutdown_receiver) = oneshot::channel();
-    let shared_state = SharedState {
-        tee_url,
-        twitter_builder: twitter_builder.clone(),
-        twitter_token_pair: Arc::new(Mutex::new(None)),
-        shutdown_sender: Arc::new(Mutex::new(Some(shutdown_sender))),
+    let Some(release) = release else {
+        return accept_logins.await;
     };
 
-    let app = axum::Router::new()
-        .route("/login", axum::routing::get(login))
-        .route("/callback", axum::routing::get(callback))
-        .layer(CorsLayer::permissive())
-        .with_state(shared_state.clone());
+    // Read back at release time, since OAuth 2.0 tokens rotate
+    let payload = || async {
+        // Agents may still rotate tokens; read the store only once they can't
+        agents.stop_all().await;
+        let mut payload = ReleasePayload {
+            credentials: store.as_ref().unwrap().load_all().await?,
+            ..Default::default()
+        };
+        if let Ok(path) = std::env::var("ENCUMBRANCE_VAULT_PATH") {
+            let key_path = std::env::var("SEALING_KEY_PATH")?;
+            let vault = Vault::new(path, Arc::new(KeyFileProvider::new(key_path)));
+            for (service, record) in vault.load().await?.iter() {
+                if record.state == RotationState::Rotated {
+                    payload
+                        .passwords
+                        .insert(service.clone(), record.password.clone());
+                }
+            }
+        }
+        Ok(payload)
+    };
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    let server = axum::serve(listener, app);
-    server
-        .with_graceful_shutdown(async {
-            shutdown_receiver.await.ok();
-        })
-        .await
-        .ok();
-    log::info!("Received credentials. Shutting down server.");
+    // A failed release is retried rather than crashing, as a restart would
+    // resume the agents the payload stopped
+    let release_accounts = async {
+        let mut retry_in = Duration::from_secs(1);
+        loop {
+            match release.run(&payload).await {
+                Ok(decision) => return decision,
+                Err(e) => {
+                    log::error!(
+                        "Failed to release accounts, retrying in {:?}: {}",
+                        retry_in,
+                        e
+                    );
+                    tokio::time::sleep(retry_in).await;
+                    retry_in = (retry_in * 2).min(Duration::from_secs(5 * 60));
+                }
+            }
+        }
+    };
 
-    let tokens = shared_state.twitter_token_pair.lock().await.take().unwrap();
-    let twitter_client = shared_state.twitter_builder.with_auth(tokens);
-    event_loop::event_loop(twitter_client).await.unwrap();
+    let decision = tokio::select! {
+        _ = accept_logins => return,
+        decision = release_accounts => decision,
+    };
+    drop(login_receiver);
+    agents.stop_all().await;
+    log::info!(
+        "Released accounts at {}; stopped every agent.",
+        decision.released_at
+    );
+    // Keep serving /release so the owner can collect it
+    std::future::pending::<()>().await;
 }
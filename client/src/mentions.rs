@@ -0,0 +1,90 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::credentials::write_atomic;
+use crate::twitter::{conversation::Conversation, mentions::Mention};
+
+#[derive(Serialize, Deserialize)]
+struct CursorFile {
+    since_id: String,
+}
+
+/// The newest mention an agent has handled, so it never answers one twice.
+/// Persisted to a file when opened with one.
+#[derive(Default)]
+pub struct MentionCursor {
+    since_id: Mutex<Option<String>>,
+    path: Option<PathBuf>,
+}
+
+impl MentionCursor {
+    /// An in-memory cursor, lost on restart.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cursor for `user_id` in `dir`, starting unset if it has no file yet.
+    pub async fn open(dir: impl Into<PathBuf>, user_id: &str) -> eyre::Result<Self> {
+        if user_id.is_empty() || !user_id.bytes().all(|b| b.is_ascii_digit()) {
+            eyre::bail!("Invalid user ID: {:?}", user_id);
+        }
+        let path = dir.into().join(format!("{}.mentions.json", user_id));
+        let since_id = match tokio::fs::read(&path).await {
+            Ok(contents) => Some(serde_json::from_slice::<CursorFile>(&contents)?.since_id),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            since_id: Mutex::new(since_id),
+            path: Some(path),
+        })
+    }
+
+    pub fn since_id(&self) -> Option<String> {
+        self.since_id.lock().unwrap().clone()
+    }
+
+    /// Moves the cursor to `id`, which must be newer than any mention seen.
+    pub async fn advance(&self, id: &str) -> eyre::Result<()> {
+        if let Some(path) = &self.path {
+            let contents = serde_json::to_vec(&CursorFile {
+                since_id: id.to_string(),
+            })?;
+            write_atomic(path, &contents).await?;
+        }
+        *self.since_id.lock().unwrap() = Some(id.to_string());
+        Ok(())
+    }
+}
+
+/// The user message that hands `mention` to the agent, with the `thread` it
+/// replies to if fetched, followed by `instructions`.
+pub fn mention_prompt(
+    mention: &Mention,
+    thread: Option<&Conversation>,
+    instructions: &str,
+) -> String {
+    let author = match &mention.author {
+        Some(author) => format!("@{} ({})", author.username, author.name),
+        None => "someone".to_string(),
+    };
+    let mut prompt = format!(
+        "New mention from {}, tweet ID {}:\n{}\n",
+        author, mention.id, mention.text
+    );
+    if let Some(thread) = thread.filter(|thread| !thread.tweets.is_empty()) {
+        prompt.push_str(&format!(
+            "\nIt replies to this thread, oldest first:\n{}",
+            thread.transcript()
+        ));
+    } else if let Some(replied_to) = &mention.in_reply_to {
+        prompt.push_str(&format!(
+            "\nIt replies to tweet {}:\n{}\n",
+            replied_to.id, replied_to.text
+        ));
+    }
+    prompt.push('\n');
+    prompt.push_str(instructions);
+    prompt
+}
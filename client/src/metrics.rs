@@ -0,0 +1,271 @@
+use std::{
+    future::Future,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use prometheus::{
+    CounterVec, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use reqwest::StatusCode;
+
+use crate::llm::{Message, TokenUsage};
+use crate::status::StatusReport;
+
+/// LLM calls range from sub-second local models to minute-long completions.
+const LLM_LATENCY_BUCKETS: &[f64] = &[0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("Invalid metric definitions"));
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// How a tool call ended, as its `outcome` label.
+pub(crate) enum ToolOutcome {
+    Ok,
+    /// Turned back to the model to try again, e.g. a duplicate tweet.
+    Rejected,
+    Error,
+}
+
+/// Counters and histograms updated as the agents run, shared by every
+/// account in the process.
+pub struct Metrics {
+    registry: Registry,
+    llm_requests: IntCounterVec,
+    llm_request_duration: HistogramVec,
+    llm_tokens: IntCounterVec,
+    tool_invocations: IntCounterVec,
+    twitter_requests: IntCounterVec,
+    rate_limit_waits: IntCounterVec,
+    rate_limit_wait_seconds: CounterVec,
+    agent_runs: IntCounterVec,
+}
+
+fn counter_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> eyre::Result<IntCounterVec> {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels)?;
+    registry.register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+impl Metrics {
+    fn new() -> eyre::Result<Self> {
+        let registry = Registry::new();
+        let llm_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "llm_request_duration_seconds",
+                "Time taken by LLM completion requests.",
+            )
+            .buckets(LLM_LATENCY_BUCKETS.to_vec()),
+            &["backend", "model"],
+        )?;
+        registry.register(Box::new(llm_request_duration.clone()))?;
+        let rate_limit_wait_seconds = CounterVec::new(
+            Opts::new(
+                "twitter_rate_limit_wait_seconds_total",
+                "Time spent waiting for Twitter rate-limit windows to reset.",
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(rate_limit_wait_seconds.clone()))?;
+
+        Ok(Self {
+            llm_requests: counter_vec(
+                &registry,
+                "llm_requests_total",
+                "LLM completion requests, by outcome.",
+                &["backend", "model", "outcome"],
+            )?,
+            llm_request_duration,
+            llm_tokens: counter_vec(
+                &registry,
+                "llm_tokens_total",
+                "Tokens the LLM reported using, by input or output.",
+                &["backend", "model", "kind"],
+            )?,
+            tool_invocations: counter_vec(
+                &registry,
+                "tool_invocations_total",
+                "Tool calls the agent executed, by outcome.",
+                &["tool", "outcome"],
+            )?,
+            twitter_requests: counter_vec(
+                &registry,
+                "twitter_requests_total",
+                "Twitter API responses by endpoint and HTTP status, or `error` when no response arrived.",
+                &["endpoint", "status"],
+            )?,
+            rate_limit_waits: counter_vec(
+                &registry,
+                "twitter_rate_limit_waits_total",
+                "Times a request waited for a rate-limit window to reset.",
+                &["endpoint"],
+            )?,
+            rate_limit_wait_seconds,
+            agent_runs: counter_vec(
+                &registry,
+                "agent_runs_total",
+                "Agent runs, by outcome.",
+                &["outcome"],
+            )?,
+            registry,
+        })
+    }
+
+    /// Times `call` and counts its outcome and the tokens it used.
+    pub(crate) async fn observe_llm_call(
+        &self,
+        backend: &str,
+        model: &str,
+        call: impl Future<Output = eyre::Result<(Message, Option<TokenUsage>)>>,
+    ) -> eyre::Result<Message> {
+        let started = Instant::now();
+        let result = call.await;
+        self.llm_request_duration
+            .with_label_values(&[backend, model])
+            .observe(started.elapsed().as_secs_f64());
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        self.llm_requests
+            .with_label_values(&[backend, model, outcome])
+            .inc();
+        let (message, usage) = result?;
+        if let Some(usage) = usage {
+            self.llm_tokens
+                .with_label_values(&[backend, model, "input"])
+                .inc_by(usage.input);
+            self.llm_tokens
+                .with_label_values(&[backend, model, "output"])
+                .inc_by(usage.output);
+        }
+        Ok(message)
+    }
+
+    /// `tool` must be a registered tool's name, never one the model made up.
+    pub(crate) fn record_tool_invocation(&self, tool: &str, outcome: ToolOutcome) {
+        let outcome = match outcome {
+            ToolOutcome::Ok => "ok",
+            ToolOutcome::Rejected => "rejected",
+            ToolOutcome::Error => "error",
+        };
+        self.tool_invocations
+            .with_label_values(&[tool, outcome])
+            .inc();
+    }
+
+    /// `status` is `None` when the request failed without a response.
+    pub(crate) fn record_twitter_request(&self, endpoint: &str, status: Option<StatusCode>) {
+        let status = status.map_or("error".to_string(), |status| status.as_u16().to_string());
+        self.twitter_requests
+            .with_label_values(&[endpoint, &status])
+            .inc();
+    }
+
+    pub(crate) fn record_rate_limit_wait(&self, endpoint: &str, wait: Duration) {
+        self.rate_limit_waits.with_label_values(&[endpoint]).inc();
+        self.rate_limit_wait_seconds
+            .with_label_values(&[endpoint])
+            .inc_by(wait.as_secs_f64());
+    }
+
+    pub(crate) fn record_agent_run(&self, outcome: &str) {
+        self.agent_runs.with_label_values(&[outcome]).inc();
+    }
+}
+
+fn gauge_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> eyre::Result<GaugeVec> {
+    let gauge = GaugeVec::new(Opts::new(name, help), labels)?;
+    registry.register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+/// Renders the process-wide metrics plus gauges for `status` in the
+/// Prometheus text format. The gauges are built fresh from the status on
+/// every scrape, so accounts that went away never linger.
+pub fn render(status: &StatusReport) -> eyre::Result<String> {
+    let registry = Registry::new();
+
+    let started_at = IntGauge::new(
+        "process_start_time_seconds",
+        "Unix timestamp the server started at.",
+    )?;
+    registry.register(Box::new(started_at.clone()))?;
+    started_at.set(status.started_at as i64);
+    let released = IntGauge::new(
+        "agent_released",
+        "1 once the accounts were handed back to their owner.",
+    )?;
+    registry.register(Box::new(released.clone()))?;
+    released.set(status.released as i64);
+
+    let up = gauge_vec(
+        &registry,
+        "agent_up",
+        "1 while the account's agent is running.",
+        &["account", "username"],
+    )?;
+    let last_action = gauge_vec(
+        &registry,
+        "agent_last_action_timestamp_seconds",
+        "Unix timestamp of the agent's last tool call.",
+        &["account", "tool"],
+    )?;
+    let next_run = gauge_vec(
+        &registry,
+        "agent_next_run_timestamp_seconds",
+        "Unix timestamp of the agent's next run.",
+        &["account"],
+    )?;
+    let remaining = gauge_vec(
+        &registry,
+        "twitter_rate_limit_remaining",
+        "Requests left in the endpoint's rate-limit window.",
+        &["account", "endpoint"],
+    )?;
+    let reset_at = gauge_vec(
+        &registry,
+        "twitter_rate_limit_reset_timestamp_seconds",
+        "Unix timestamp the endpoint's rate-limit window resets at.",
+        &["account", "endpoint"],
+    )?;
+
+    for account in &status.accounts {
+        let id = account.account.id.as_str();
+        up.with_label_values(&[id, &account.account.username])
+            .set(account.account.running as u8 as f64);
+        if let Some(action) = &account.activity.last_action {
+            last_action
+                .with_label_values(&[id, &action.tool])
+                .set(action.at as f64);
+        }
+        if let Some(at) = account.activity.next_run_at {
+            next_run.with_label_values(&[id]).set(at as f64);
+        }
+        for (endpoint, budget) in &account.rate_limits {
+            remaining
+                .with_label_values(&[id, endpoint])
+                .set(budget.remaining as f64);
+            reset_at
+                .with_label_values(&[id, endpoint])
+                .set(budget.reset_at as f64);
+        }
+    }
+
+    let mut families = metrics().registry.gather();
+    families.extend(registry.gather());
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&families, &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
@@ -0,0 +1,283 @@
+//! Hands the account back. Once the configured unlock time has passed, the
+//! enclave reveals the credentials it holds, encrypted to the owner's X25519
+//! public key, and stops acting as the account.
+//!
+//! The decision is persisted (sealed, so it can't be forged from outside) and
+//! is final: a release survives restarts and is never re-encrypted or revoked.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::clock::Clock;
+use crate::credentials::{write_atomic, StoredCredentials};
+use crate::encumbrance::Secret;
+use crate::sealing::SealingKeyProvider;
+
+/// Label the decision sealing key is derived under, and HKDF info for the
+/// key the release itself is encrypted under.
+const RELEASE_KEY_LABEL: &str = "twitter-encumbrance/release/v1";
+/// Upper bound on how long `run` sleeps between checks.
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct ReleasePolicy {
+    /// Unix seconds from which the account may be released.
+    pub unlock_at: u64,
+    /// The owner's X25519 public key.
+    pub recipient: PublicKey,
+}
+
+impl ReleasePolicy {
+    /// `RELEASE_AT` (Unix seconds) and `RELEASE_RECIPIENT` (hex X25519 public
+    /// key), or `None` if neither is set, in which case the account is never
+    /// released.
+    pub fn from_env() -> eyre::Result<Option<Self>> {
+        let (unlock_at, recipient) = match (
+            std::env::var("RELEASE_AT"),
+            std::env::var("RELEASE_RECIPIENT"),
+        ) {
+            (Err(_), Err(_)) => return Ok(None),
+            (Ok(unlock_at), Ok(recipient)) => (unlock_at, recipient),
+            _ => eyre::bail!("RELEASE_AT and RELEASE_RECIPIENT must be set together"),
+        };
+        Ok(Some(Self {
+            unlock_at: unlock_at
+                .parse()
+                .map_err(|_| eyre::eyre!("RELEASE_AT must be Unix seconds"))?,
+            recipient: parse_public_key(&recipient)?,
+        }))
+    }
+}
+
+/// Rejects low-order keys, such as all zeros, which every secret turns into
+/// the same shared secret.
+pub fn parse_public_key(hex_key: &str) -> eyre::Result<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key)?
+        .try_into()
+        .map_err(|_| eyre::eyre!("X25519 public key must be 32 bytes"))?;
+    let key = PublicKey::from(bytes);
+    if !EphemeralSecret::random_from_rng(OsRng)
+        .diffie_hellman(&key)
+        .was_contributory()
+    {
+        eyre::bail!("X25519 public key has low order");
+    }
+    Ok(key)
+}
+
+/// Everything handed back to the owner.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ReleasePayload {
+    /// Each account's credentials, keyed by user ID.
+    pub credentials: BTreeMap<String, StoredCredentials>,
+    /// Rotated passwords, keyed by service.
+    pub passwords: BTreeMap<String, Secret>,
+}
+
+/// A payload encrypted to one recipient: X25519 with a fresh ephemeral key,
+/// HKDF-SHA256, then AES-256-GCM.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedRelease {
+    /// Hex.
+    pub ephemeral_public_key: String,
+    /// Hex.
+    pub nonce: String,
+    /// Base64.
+    pub ciphertext: String,
+}
+
+fn release_key(
+    shared_secret: &[u8; 32],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> eyre::Result<Zeroizing<[u8; 32]>> {
+    let salt = [ephemeral.as_bytes().as_slice(), recipient.as_bytes()].concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(RELEASE_KEY_LABEL.as_bytes(), key.as_mut())
+        .map_err(|_| eyre::eyre!("Failed to derive release key"))?;
+    Ok(key)
+}
+
+pub fn encrypt_to(recipient: &PublicKey, plaintext: &[u8]) -> eyre::Result<EncryptedRelease> {
+    let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(recipient);
+    // Anyone could compute the key for a low-order recipient
+    if !shared_secret.was_contributory() {
+        eyre::bail!("Release recipient key has low order");
+    }
+    let key = release_key(shared_secret.as_bytes(), &ephemeral, recipient)?;
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()))
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre::eyre!("Failed to encrypt release"))?;
+    Ok(EncryptedRelease {
+        ephemeral_public_key: hex::encode(ephemeral.as_bytes()),
+        nonce: hex::encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+/// The owner's side of `encrypt_to`.
+pub fn decrypt(
+    secret: &StaticSecret,
+    release: &EncryptedRelease,
+) -> eyre::Result<Zeroizing<Vec<u8>>> {
+    let ephemeral = parse_public_key(&release.ephemeral_public_key)?;
+    let shared_secret = secret.diffie_hellman(&ephemeral);
+    let key = release_key(
+        shared_secret.as_bytes(),
+        &ephemeral,
+        &PublicKey::from(secret),
+    )?;
+
+    let nonce = hex::decode(&release.nonce)?;
+    if nonce.len() != 12 {
+        eyre::bail!("Release nonce must be 12 bytes");
+    }
+    let ciphertext = STANDARD.decode(&release.ciphertext)?;
+    let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| eyre::eyre!("Failed to decrypt release: wrong key or corrupted"))?;
+    Ok(Zeroizing::new(plaintext))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseDecision {
+    /// Unix seconds at which the release was made.
+    pub released_at: u64,
+    pub unlock_at: u64,
+    /// Hex X25519 public key the payload is encrypted to.
+    pub recipient: String,
+    pub release: EncryptedRelease,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReleaseStatus {
+    Locked { unlock_at: u64, recipient: String },
+    Released(ReleaseDecision),
+}
+
+/// Applies a `ReleasePolicy` and persists its decision at `path`.
+pub struct Release {
+    policy: ReleasePolicy,
+    clock: Arc<dyn Clock>,
+    path: PathBuf,
+    keys: Arc<dyn SealingKeyProvider>,
+    decision: Mutex<Option<ReleaseDecision>>,
+}
+
+impl Release {
+    /// Loads any decision already made, which takes precedence over `policy`.
+    pub async fn open(
+        policy: ReleasePolicy,
+        clock: Arc<dyn Clock>,
+        path: impl Into<PathBuf>,
+        keys: Arc<dyn SealingKeyProvider>,
+    ) -> eyre::Result<Self> {
+        let path = path.into();
+        let decision = match tokio::fs::read(&path).await {
+            Ok(sealed) => {
+                let plaintext = keys.derive_key(RELEASE_KEY_LABEL)?.open(&sealed)?;
+                Some(serde_json::from_slice::<ReleaseDecision>(&plaintext)?)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            policy,
+            clock,
+            path,
+            keys,
+            decision: Mutex::new(decision),
+        })
+    }
+
+    pub fn status(&self) -> ReleaseStatus {
+        match self.decision.lock().unwrap().clone() {
+            Some(decision) => ReleaseStatus::Released(decision),
+            None => ReleaseStatus::Locked {
+                unlock_at: self.policy.unlock_at,
+                recipient: hex::encode(self.policy.recipient.as_bytes()),
+            },
+        }
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.decision.lock().unwrap().is_some()
+    }
+
+    /// Releases `payload` if the unlock time has passed. Returns the decision
+    /// once one has been made, which from then on is returned unchanged.
+    pub async fn evaluate(
+        &self,
+        payload: &ReleasePayload,
+    ) -> eyre::Result<Option<ReleaseDecision>> {
+        if let Some(decision) = self.decision.lock().unwrap().clone() {
+            return Ok(Some(decision));
+        }
+        let now = self.clock.now();
+        if now < self.policy.unlock_at {
+            return Ok(None);
+        }
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(payload)?);
+        let decision = ReleaseDecision {
+            released_at: now,
+            unlock_at: self.policy.unlock_at,
+            recipient: hex::encode(self.policy.recipient.as_bytes()),
+            release: encrypt_to(&self.policy.recipient, &plaintext)?,
+        };
+        // Persist before publishing, so a crash can't lead to a second,
+        // different release
+        let sealed = self
+            .keys
+            .derive_key(RELEASE_KEY_LABEL)?
+            .seal(&serde_json::to_vec(&decision)?)?;
+        write_atomic(&self.path, &sealed).await?;
+        *self.decision.lock().unwrap() = Some(decision.clone());
+        log::info!("Released account to {}", decision.recipient);
+        Ok(Some(decision))
+    }
+
+    /// Waits for the unlock time, then releases what `payload` returns. The
+    /// payload is only gathered then, so it holds the latest credentials
+    /// rather than ones that have since rotated.
+    pub async fn run<F, Fut>(&self, payload: F) -> eyre::Result<ReleaseDecision>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = eyre::Result<ReleasePayload>>,
+    {
+        loop {
+            if let Some(decision) = self.decision.lock().unwrap().clone() {
+                return Ok(decision);
+            }
+            if self.clock.now() >= self.policy.unlock_at {
+                if let Some(decision) = self.evaluate(&payload().await?).await? {
+                    return Ok(decision);
+                }
+            }
+            let remaining = self.policy.unlock_at.saturating_sub(self.clock.now());
+            let wait = Duration::from_secs(remaining.max(1)).min(MAX_POLL_INTERVAL);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use cron::Schedule;
+use rand::Rng;
+
+use crate::clock::Clock;
+use crate::settings::{CatchUp, ScheduleSettings};
+
+/// Gives up looking for a slot after this many candidates, e.g. for a cron
+/// expression whose every slot falls in quiet hours.
+const MAX_CANDIDATES: usize = 10_000;
+
+struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+fn parse_time(field: &str, time: &str) -> eyre::Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| eyre::eyre!("schedule.quiet_hours.{} must be HH:MM, got {}", field, time))
+}
+
+/// `ScheduleSettings`, parsed.
+struct Rules {
+    cron: Option<Schedule>,
+    timezone: Tz,
+    quiet_hours: Option<QuietHours>,
+    jitter_secs: u64,
+    min_posts_per_day: u32,
+    max_posts_per_day: u32,
+    catch_up: CatchUp,
+}
+
+impl Rules {
+    fn parse(settings: &ScheduleSettings) -> eyre::Result<Self> {
+        let cron = settings
+            .cron
+            .as_deref()
+            .map(Schedule::from_str)
+            .transpose()
+            .map_err(|e| eyre::eyre!("Invalid schedule.cron: {}", e))?;
+        let timezone = Tz::from_str(&settings.timezone)
+            .map_err(|_| eyre::eyre!("Unknown schedule.timezone: {}", settings.timezone))?;
+        let quiet_hours = match &settings.quiet_hours {
+            Some(quiet) => {
+                let quiet = QuietHours {
+                    start: parse_time("start", &quiet.start)?,
+                    end: parse_time("end", &quiet.end)?,
+                };
+                if quiet.start == quiet.end {
+                    eyre::bail!("schedule.quiet_hours must not start and end together");
+                }
+                Some(quiet)
+            }
+            None => None,
+        };
+        if settings.max_posts_per_day == 0 {
+            eyre::bail!("schedule.max_posts_per_day must be positive");
+        }
+        if settings.min_posts_per_day > settings.max_posts_per_day {
+            eyre::bail!("schedule.min_posts_per_day exceeds max_posts_per_day");
+        }
+        Ok(Self {
+            cron,
+            timezone,
+            quiet_hours,
+            jitter_secs: settings.jitter_secs,
+            min_posts_per_day: settings.min_posts_per_day,
+            max_posts_per_day: settings.max_posts_per_day,
+            catch_up: settings.catch_up,
+        })
+    }
+
+    fn local(&self, at: u64) -> DateTime<Tz> {
+        self.timezone
+            .timestamp_opt(at as i64, 0)
+            .single()
+            .expect("Unix timestamps map to exactly one local time")
+    }
+
+    fn day(&self, at: u64) -> NaiveDate {
+        self.local(at).date_naive()
+    }
+
+    /// Local times skipped by a DST change resolve to the hour after.
+    fn timestamp(&self, local: NaiveDateTime) -> u64 {
+        let resolved = self
+            .timezone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(local + TimeDelta::hours(1)))
+                    .earliest()
+            })
+            .expect("DST gaps are shorter than an hour");
+        resolved.timestamp() as u64
+    }
+
+    /// Midnight at the start of the day after `at`'s.
+    fn next_day(&self, at: u64) -> u64 {
+        let tomorrow = self.day(at).succ_opt().expect("Date out of range");
+        self.timestamp(tomorrow.and_time(NaiveTime::MIN))
+    }
+
+    fn is_quiet(&self, at: u64) -> bool {
+        self.quiet_hours
+            .as_ref()
+            .is_some_and(|quiet| quiet.contains(self.local(at).time()))
+    }
+
+    /// The first moment from `at` outside quiet hours.
+    fn awake_from(&self, at: u64) -> u64 {
+        let Some(quiet) = self.quiet_hours.as_ref().filter(|_| self.is_quiet(at)) else {
+            return at;
+        };
+        let local = self.local(at).naive_local();
+        let mut end = local.date().and_time(quiet.end);
+        if end <= local {
+            end += TimeDelta::days(1);
+        }
+        self.timestamp(end)
+    }
+
+    /// The last moment of `at`'s day before quiet hours that run into
+    /// midnight, or midnight itself.
+    fn awake_until(&self, at: u64) -> u64 {
+        let midnight = self.next_day(at);
+        match &self.quiet_hours {
+            Some(quiet) if self.is_quiet(midnight - 1) => {
+                let start = self.timestamp(self.day(at).and_time(quiet.start));
+                if start > at {
+                    start
+                } else {
+                    at
+                }
+            }
+            _ => midnight,
+        }
+    }
+
+    /// Cron slots after `after`, outside quiet hours.
+    fn slots_after(&self, after: u64) -> impl Iterator<Item = u64> + '_ {
+        let cron = self
+            .cron
+            .as_ref()
+            .expect("Only called with a cron schedule");
+        cron.after(&self.local(after))
+            .take(MAX_CANDIDATES)
+            .map(|slot| slot.timestamp() as u64)
+            .filter(|slot| !self.is_quiet(*slot))
+    }
+
+    fn jitter(&self) -> u64 {
+        if self.jitter_secs == 0 {
+            return 0;
+        }
+        rand::thread_rng().gen_range(0..=self.jitter_secs)
+    }
+}
+
+/// Checks `settings` without building a scheduler.
+pub fn validate(settings: &ScheduleSettings) -> eyre::Result<()> {
+    Rules::parse(settings).map(|_| ())
+}
+
+#[derive(Default)]
+struct State {
+    /// The latest cron slot run, caught up on or skipped.
+    last_slot: Option<u64>,
+    /// Posts per local day, for today and yesterday.
+    posts: BTreeMap<NaiveDate, u32>,
+}
+
+impl State {
+    fn posts_on(&self, day: NaiveDate) -> u32 {
+        self.posts.get(&day).copied().unwrap_or(0)
+    }
+
+    fn record_post(&mut self, day: NaiveDate) {
+        *self.posts.entry(day).or_default() += 1;
+        let yesterday = day.pred_opt().unwrap_or(day);
+        self.posts.retain(|post_day, _| *post_day >= yesterday);
+    }
+}
+
+/// Decides when an agent runs next, from `ScheduleSettings` and `clock`.
+pub struct Scheduler {
+    rules: Rules,
+    clock: Arc<dyn Clock>,
+    state: Mutex<State>,
+}
+
+impl Scheduler {
+    pub fn new(settings: &ScheduleSettings, clock: Arc<dyn Clock>) -> eyre::Result<Self> {
+        Ok(Self {
+            rules: Rules::parse(settings)?,
+            clock,
+            state: Mutex::default(),
+        })
+    }
+
+    /// Resumes from before a restart: cron slots are counted from
+    /// `last_run`, the account's last scheduled run, so the ones missed while
+    /// down are caught up on, and `posts`, when the account posted, count
+    /// towards their days' limits.
+    pub fn with_history(self, last_run: Option<u64>, posts: impl IntoIterator<Item = u64>) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.last_slot = last_run;
+            for at in posts {
+                state.record_post(self.rules.day(at));
+            }
+        }
+        self
+    }
+
+    /// Counts a post made just now, whether by a scheduled run or not.
+    pub fn record_post(&self) {
+        let day = self.rules.day(self.clock.now());
+        self.state.lock().unwrap().record_post(day);
+    }
+
+    /// How many more posts today allows.
+    pub fn posts_left(&self) -> u32 {
+        let today = self.rules.day(self.clock.now());
+        let posted = self.state.lock().unwrap().posts_on(today);
+        self.rules.max_posts_per_day.saturating_sub(posted)
+    }
+
+    /// Whether it is quiet hours now.
+    pub fn is_quiet(&self) -> bool {
+        self.rules.is_quiet(self.clock.now())
+    }
+
+    /// Unix timestamp of the next run, no earlier than `earliest`. Plans one
+    /// run per call, so call it once per run.
+    pub fn next_run(&self, earliest: u64) -> u64 {
+        let rules = &self.rules;
+        let now = self.clock.now();
+        let earliest = earliest.max(now);
+        let mut state = self.state.lock().unwrap();
+
+        // The slot this run takes, if it takes one
+        let (mut at, mut slot) = match &rules.cron {
+            None => (earliest + rules.jitter(), None),
+            Some(_) => {
+                let last_slot = *state.last_slot.get_or_insert(now);
+                let missed: Vec<u64> = rules
+                    .slots_after(last_slot)
+                    .take_while(|slot| *slot <= now)
+                    .collect();
+                match (missed.first(), missed.last(), rules.catch_up) {
+                    (Some(&first), _, CatchUp::All) => (earliest, Some(first)),
+                    (_, Some(&latest), CatchUp::Once) => (earliest, Some(latest)),
+                    (_, latest, _) => match rules.slots_after(*latest.unwrap_or(&last_slot)).next()
+                    {
+                        Some(next) => (next.max(earliest) + rules.jitter(), Some(next)),
+                        None => (earliest, None),
+                    },
+                }
+            }
+        };
+
+        for _ in 0..MAX_CANDIDATES {
+            if rules.is_quiet(at) {
+                at = rules.awake_from(at) + rules.jitter();
+                continue;
+            }
+            if state.posts_on(rules.day(at)) >= rules.max_posts_per_day {
+                let tomorrow = rules.next_day(at);
+                (at, slot) = match &rules.cron {
+                    None => (tomorrow, None),
+                    Some(_) => match rules.slots_after(tomorrow - 1).next() {
+                        Some(next) => (next, Some(next)),
+                        None => (tomorrow, None),
+                    },
+                };
+                at += rules.jitter();
+                continue;
+            }
+            break;
+        }
+
+        // On the first day before `at` whose posts and planned runs fall
+        // short of the minimum, spread extra runs over what is left of it
+        let mut from = earliest;
+        while rules.min_posts_per_day > 0 && from < at {
+            let until = rules.awake_until(from);
+            let planned = match &rules.cron {
+                _ if at >= until => 0,
+                None => u32::MAX,
+                Some(_) => 1 + rules.slots_after(at).take_while(|s| *s < until).count() as u32,
+            };
+            let short = rules
+                .min_posts_per_day
+                .saturating_sub(state.posts_on(rules.day(from)).saturating_add(planned));
+            if short > 0 && until > from {
+                let extra = rules.awake_from(from + (until - from) / (short as u64 + 1));
+                if extra < at {
+                    at = extra;
+                    slot = None;
+                }
+                break;
+            }
+            from = rules.awake_from(rules.next_day(from));
+        }
+
+        if let Some(slot) = slot {
+            state.last_slot = Some(slot);
+        }
+        at
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Version byte prepended to every sealed blob.
+const SEAL_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Source of the root secret that sealing keys are derived from. In
+/// production this must be something only the enclave can read, e.g. the
+/// SGX sealing key Gramine exposes under `/dev/attestation/keys/`.
+pub trait SealingKeyProvider: Send + Sync {
+    fn root_key(&self) -> eyre::Result<Zeroizing<Vec<u8>>>;
+
+    /// Derives the key for one purpose, so that blobs sealed for one use can
+    /// never be opened as another.
+    fn derive_key(&self, label: &str) -> eyre::Result<SealingKey> {
+        let root_key = self.root_key()?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, &root_key)
+            .expand(label.as_bytes(), key.as_mut())
+            .map_err(|_| eyre::eyre!("Failed to derive sealing key"))?;
+        Ok(SealingKey {
+            key,
+            label: label.to_string(),
+        })
+    }
+}
+
+/// Reads the root secret from a file, such as an enclave-only key device.
+pub struct KeyFileProvider {
+    path: PathBuf,
+}
+
+impl KeyFileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SealingKeyProvider for KeyFileProvider {
+    fn root_key(&self) -> eyre::Result<Zeroizing<Vec<u8>>> {
+        let key = std::fs::read(&self.path).map_err(|e| {
+            eyre::eyre!("Failed to read sealing key {}: {}", self.path.display(), e)
+        })?;
+        if key.is_empty() {
+            eyre::bail!("Sealing key {} is empty", self.path.display());
+        }
+        Ok(Zeroizing::new(key))
+    }
+}
+
+/// A fixed root secret, for tests. Offers no protection at all.
+pub struct StaticKeyProvider(pub Vec<u8>);
+
+impl SealingKeyProvider for StaticKeyProvider {
+    fn root_key(&self) -> eyre::Result<Zeroizing<Vec<u8>>> {
+        Ok(Zeroizing::new(self.0.clone()))
+    }
+}
+
+/// An AES-256-GCM key bound to the label it was derived for.
+pub struct SealingKey {
+    key: Zeroizing<[u8; 32]>,
+    label: String,
+}
+
+impl SealingKey {
+    /// Encrypts `plaintext` as `version || nonce || ciphertext`, with the
+    /// label as associated data.
+    pub fn seal(&self, plaintext: &[u8]) -> eyre::Result<Vec<u8>> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.key.as_ref()));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: self.label.as_bytes(),
+                },
+            )
+            .map_err(|_| eyre::eyre!("Failed to seal {}", self.label))?;
+
+        let mut sealed = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        sealed.push(SEAL_VERSION);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, sealed: &[u8]) -> eyre::Result<Zeroizing<Vec<u8>>> {
+        let (version, rest) = sealed
+            .split_first()
+            .ok_or_else(|| eyre::eyre!("Sealed {} is empty", self.label))?;
+        if *version != SEAL_VERSION {
+            eyre::bail!("Unsupported sealed {} version {}", self.label, version);
+        }
+        if rest.len() < NONCE_LEN {
+            eyre::bail!("Sealed {} is truncated", self.label);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.key.as_ref()));
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: self.label.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                eyre::eyre!(
+                    "Failed to unseal {}: wrong key or corrupted data",
+                    self.label
+                )
+            })?;
+        Ok(Zeroizing::new(plaintext))
+    }
+}
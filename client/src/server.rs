@@ -0,0 +1,483 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tower_http::cors::CorsLayer;
+
+use crate::accounts::{AccountSummary, Agents, AuthorizedAccount};
+use crate::action_log::{ActionLog, LogEntry};
+use crate::attestation::AttestationReport;
+use crate::metrics;
+use crate::release::{Release, ReleaseStatus};
+use crate::settings::LoadedSettings;
+use crate::status::{self, HealthReport, StatusReport};
+use crate::twitter::{
+    auth::{self, Pkce, TwitterTokenPair, UserAuth},
+    builder::TwitterBuilder,
+    error::TwitterError,
+};
+use crate::watchdog::WatchdogReport;
+
+/// How long a login may take from `/login` to `/callback`.
+pub const PENDING_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+/// Ties a pending login to the browser that started it, so a callback URL
+/// lured into another browser can't complete it there.
+const LOGIN_SESSION_COOKIE: &str = "login_session";
+
+struct PendingLogin<T> {
+    session: String,
+    started: Instant,
+    login: T,
+}
+
+/// Logins awaiting their callback, keyed by what the callback echoes back:
+/// the request token for OAuth 1.0a, `state` for OAuth 2.0. Each is handed
+/// out at most once and forgotten after the TTL.
+pub struct PendingLogins<T> {
+    ttl: Duration,
+    entries: std::sync::Mutex<HashMap<String, PendingLogin<T>>>,
+}
+
+impl<T> PendingLogins<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn insert(&self, key: String, session: String, login: T) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, pending| pending.started.elapsed() < self.ttl);
+        entries.insert(
+            key,
+            PendingLogin {
+                session,
+                started: Instant::now(),
+                login,
+            },
+        );
+    }
+
+    /// Removes the login under `key`, returning it only if it is still live
+    /// and `session` started it.
+    fn take(&self, key: &str, session: Option<&str>) -> Result<T, LoginError> {
+        let pending = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove(key)
+            .ok_or(LoginError::UnknownLogin)?;
+        if pending.started.elapsed() >= self.ttl {
+            return Err(LoginError::Expired);
+        }
+        if session != Some(pending.session.as_str()) {
+            return Err(LoginError::WrongSession);
+        }
+        Ok(pending.login)
+    }
+
+    /// Logins still waiting for their callback.
+    pub fn len(&self) -> usize {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .values()
+            .filter(|pending| pending.started.elapsed() < self.ttl)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Why `/callback` refused a login. Every variant is the caller's problem
+/// except `Twitter`.
+#[derive(Debug, thiserror::Error)]
+pub enum LoginError {
+    #[error("login was cancelled")]
+    Denied,
+    #[error("authorization failed: {0}")]
+    Authorization(String),
+    #[error("missing query parameter: {0}")]
+    MissingParameter(&'static str),
+    #[error("unknown or already used login")]
+    UnknownLogin,
+    #[error("login expired, start again at /login")]
+    Expired,
+    #[error("login was started in another browser")]
+    WrongSession,
+    #[error("twitter rejected the login: {0}")]
+    Twitter(#[from] TwitterError),
+    #[error("the accounts have been released, no more logins are accepted")]
+    Released,
+}
+
+impl IntoResponse for LoginError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            LoginError::Denied | LoginError::WrongSession => StatusCode::FORBIDDEN,
+            LoginError::Authorization(_)
+            | LoginError::MissingParameter(_)
+            | LoginError::UnknownLogin
+            | LoginError::Expired => StatusCode::BAD_REQUEST,
+            LoginError::Twitter(_) => StatusCode::BAD_GATEWAY,
+            LoginError::Released => StatusCode::GONE,
+        };
+        log::warn!("Login failed: {}", self);
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedState {
+    pub tee_url: String,
+    pub twitter_builder: TwitterBuilder,
+    /// OAuth 1.0a request tokens awaiting their callback.
+    pub pending_oauth1: Arc<PendingLogins<TwitterTokenPair>>,
+    /// OAuth 2.0 PKCE verifiers awaiting their callback.
+    pub pending_oauth2: Arc<PendingLogins<Pkce>>,
+    pub login_sender: mpsc::UnboundedSender<AuthorizedAccount>,
+    pub attestation: Arc<AttestationReport>,
+    pub action_log: Arc<ActionLog>,
+    pub agents: Arc<Agents>,
+    pub release: Option<Arc<Release>>,
+    pub settings: Option<Arc<LoadedSettings>>,
+    /// Unix timestamp.
+    pub started_at: u64,
+}
+
+impl SharedState {
+    /// Returns the state and a receiver yielding each account as its login
+    /// completes.
+    pub fn new(
+        tee_url: String,
+        twitter_builder: TwitterBuilder,
+        attestation: AttestationReport,
+        action_log: Arc<ActionLog>,
+        agents: Arc<Agents>,
+    ) -> (Self, mpsc::UnboundedReceiver<AuthorizedAccount>) {
+        let (login_sender, login_receiver) = mpsc::unbounded_channel();
+        let shared_state = Self {
+            tee_url,
+            twitter_builder,
+            pending_oauth1: Arc::new(PendingLogins::new(PENDING_LOGIN_TTL)),
+            pending_oauth2: Arc::new(PendingLogins::new(PENDING_LOGIN_TTL)),
+            login_sender,
+            attestation: Arc::new(attestation),
+            action_log,
+            agents,
+            release: None,
+            settings: None,
+            started_at: status::now(),
+        };
+        (shared_state, login_receiver)
+    }
+
+    pub fn with_login_ttl(mut self, ttl: Duration) -> Self {
+        self.pending_oauth1 = Arc::new(PendingLogins::new(ttl));
+        self.pending_oauth2 = Arc::new(PendingLogins::new(ttl));
+        self
+    }
+
+    pub fn with_release(mut self, release: Arc<Release>) -> Self {
+        self.release = Some(release);
+        self
+    }
+
+    pub fn with_settings(mut self, settings: Arc<LoadedSettings>) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+}
+
+/// Either an OAuth 1.0a or an OAuth 2.0 callback, successful or not.
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    oauth_token: Option<String>,
+    oauth_verifier: Option<String>,
+    /// OAuth 1.0a: the request token, when the user cancelled.
+    denied: Option<String>,
+    code: Option<String>,
+    state: Option<String>,
+    /// OAuth 2.0: e.g. `access_denied` when the user cancelled.
+    error: Option<String>,
+}
+
+fn login_session(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == LOGIN_SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+pub async fn login(
+    State(shared_state): State<SharedState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, LoginError> {
+    shared_state.check_accepts_logins()?;
+    let callback_url = format!("{}/callback", shared_state.tee_url.clone(),);
+    // Reused so one browser can have several logins in flight
+    let session = login_session(&headers)
+        .map(str::to_string)
+        .unwrap_or_else(auth::random_token);
+    let secure = if shared_state.tee_url.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    let cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+        LOGIN_SESSION_COOKIE,
+        session,
+        PENDING_LOGIN_TTL.as_secs(),
+        secure
+    );
+
+    let url = if shared_state.twitter_builder.oauth2.is_some() {
+        let state = auth::random_token();
+        let pkce = Pkce::generate();
+        let url = shared_state.twitter_builder.oauth2_authorize_url(
+            &callback_url,
+            &state,
+            &pkce.challenge,
+        )?;
+        shared_state.pending_oauth2.insert(state, session, pkce);
+        url
+    } else {
+        let oauth_tokens = shared_state
+            .twitter_builder
+            .request_oauth_token(callback_url)
+            .await?;
+        let url = shared_state
+            .twitter_builder
+            .authenticate_url(&oauth_tokens.token);
+        shared_state
+            .pending_oauth1
+            .insert(oauth_tokens.token.clone(), session, oauth_tokens);
+        url
+    };
+
+    Ok((
+        AppendHeaders([(header::SET_COOKIE, cookie)]),
+        Redirect::temporary(&url),
+    ))
+}
+
+pub async fn callback(
+    State(shared_state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Result<String, LoginError> {
+    shared_state.check_accepts_logins()?;
+    let session = login_session(&headers);
+
+    if let Some(denied) = query.denied {
+        shared_state.pending_oauth1.take(&denied, session).ok();
+        return Err(LoginError::Denied);
+    }
+
+    if let Some(state) = query.state {
+        let pkce = shared_state.pending_oauth2.take(&state, session)?;
+        match query.error.as_deref() {
+            Some("access_denied") => return Err(LoginError::Denied),
+            Some(error) => return Err(LoginError::Authorization(error.to_string())),
+            None => {}
+        }
+        let code = query.code.ok_or(LoginError::MissingParameter("code"))?;
+        let callback_url = format!("{}/callback", shared_state.tee_url);
+        let token = shared_state
+            .twitter_builder
+            .exchange_oauth2_code(&code, &callback_url, &pkce.verifier)
+            .await?;
+        return finish_login(&shared_state, UserAuth::OAuth2(token)).await;
+    }
+
+    let oauth_token = query
+        .oauth_token
+        .ok_or(LoginError::MissingParameter("oauth_token"))?;
+    let oauth_verifier = query
+        .oauth_verifier
+        .ok_or(LoginError::MissingParameter("oauth_verifier"))?;
+    let twitter_token_pair = shared_state.pending_oauth1.take(&oauth_token, session)?;
+
+    let token_pair = shared_state
+        .twitter_builder
+        .authorize_token(
+            twitter_token_pair.token,
+            twitter_token_pair.secret,
+            oauth_verifier,
+        )
+        .await?;
+
+    finish_login(&shared_state, UserAuth::OAuth1(token_pair)).await
+}
+
+/// Hands the account to whoever starts its agent.
+async fn finish_login(
+    shared_state: &SharedState,
+    user_auth: UserAuth,
+) -> Result<String, LoginError> {
+    let twitter_client = shared_state
+        .twitter_builder
+        .with_user_auth(user_auth.clone());
+    let x_info = twitter_client.get_user_info().await?;
+
+    let msg = format!("Succesfully logged into {}", x_info.name);
+    // Only closed once the accounts are released
+    shared_state
+        .login_sender
+        .send(AuthorizedAccount {
+            user: x_info,
+            auth: user_auth,
+        })
+        .map_err(|_| LoginError::Released)?;
+    Ok(msg)
+}
+
+pub async fn attestation(State(shared_state): State<SharedState>) -> Json<AttestationReport> {
+    Json(shared_state.attestation.as_ref().clone())
+}
+
+/// Default and maximum page size for `/actions`.
+const ACTIONS_PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ActionsQuery {
+    /// First `seq` to return.
+    #[serde(default)]
+    from: u64,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct ActionsResponse {
+    /// Signs entries written by this run; matches the attested key.
+    public_key: String,
+    total: u64,
+    entries: Vec<LogEntry>,
+}
+
+pub async fn actions(
+    State(shared_state): State<SharedState>,
+    Query(query): Query<ActionsQuery>,
+) -> Json<ActionsResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(ACTIONS_PAGE_SIZE)
+        .min(ACTIONS_PAGE_SIZE);
+    let action_log = &shared_state.action_log;
+    Json(ActionsResponse {
+        public_key: action_log.public_key(),
+        total: action_log.len().await,
+        entries: action_log.entries(query.from, limit).await,
+    })
+}
+
+pub async fn accounts(State(shared_state): State<SharedState>) -> Json<Vec<AccountSummary>> {
+    Json(shared_state.agents.accounts())
+}
+
+/// Each account's report, keyed by user ID.
+pub async fn watchdog(
+    State(shared_state): State<SharedState>,
+) -> Json<BTreeMap<String, WatchdogReport>> {
+    Json(shared_state.agents.watchdog_reports())
+}
+
+impl SharedState {
+    fn is_released(&self) -> bool {
+        self.release
+            .as_ref()
+            .is_some_and(|release| release.is_released())
+    }
+
+    /// Agents are stopped only for a release, after which an account that
+    /// logs in would never be acted as.
+    fn check_accepts_logins(&self) -> Result<(), LoginError> {
+        if self.is_released() || self.agents.is_stopped() {
+            return Err(LoginError::Released);
+        }
+        Ok(())
+    }
+
+    fn status(&self) -> StatusReport {
+        StatusReport {
+            started_at: self.started_at,
+            released: self.is_released(),
+            accounts: self.agents.statuses(),
+        }
+    }
+}
+
+pub async fn status(State(shared_state): State<SharedState>) -> Json<StatusReport> {
+    Json(shared_state.status())
+}
+
+/// 503 while unhealthy, so plain HTTP probes notice.
+pub async fn health(State(shared_state): State<SharedState>) -> (StatusCode, Json<HealthReport>) {
+    let report = HealthReport::new(&shared_state.agents.accounts(), shared_state.is_released());
+    let code = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(report))
+}
+
+pub async fn metrics(State(shared_state): State<SharedState>) -> Response {
+    match metrics::render(&shared_state.status()) {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => {
+            log::error!("Failed to render metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The agent config as loaded, with its hash. 404 when none is set.
+pub async fn config(
+    State(shared_state): State<SharedState>,
+) -> Result<Json<LoadedSettings>, StatusCode> {
+    let settings = shared_state.settings.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(settings.as_ref().clone()))
+}
+
+/// 404 when no release policy is configured.
+pub async fn release(
+    State(shared_state): State<SharedState>,
+) -> Result<Json<ReleaseStatus>, StatusCode> {
+    let release = shared_state.release.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(release.status()))
+}
+
+pub fn router(shared_state: SharedState) -> Router {
+    Router::new()
+        .route("/login", axum::routing::get(login))
+        .route("/callback", axum::routing::get(callback))
+        .route("/attestation", axum::routing::get(attestation))
+        .route("/actions", axum::routing::get(actions))
+        .route("/accounts", axum::routing::get(accounts))
+        .route("/watchdog", axum::routing::get(watchdog))
+        .route("/release", axum::routing::get(release))
+        .route("/config", axum::routing::get(config))
+        .route("/status", axum::routing::get(status))
+        .route("/health", axum::routing::get(health))
+        .route("/metrics", axum::routing::get(metrics))
+        .layer(CorsLayer::permissive())
+        .with_state(shared_state)
+}
@@ -0,0 +1,304 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::schedule;
+use crate::tools::ToolRegistry;
+
+const DEFAULT_SYSTEM_PROMPT: &str = "You are great at coming up with surprising links and unhinged and deranged analogies between things and see the underlying similarity between seemingly different ideas.
+And you are very snarky.
+Your words should bring out people's sentiments and can go viral.
+You need to make a tweet that is an unhinged joke about crypto.
+You think Ethereum L1 roadmap politics discussion is like supporting your local football team, you don't want it but everybody talks about it so you force yourself to read the ethresearch posts just to get invested in the characters.
+Don't use hashtags.";
+
+/// Well under Twitter's own daily posting limits, so a fast schedule can't
+/// get the account flagged as spam.
+const DEFAULT_MAX_POSTS_PER_DAY: u32 = 12;
+
+/// Who the agent is and how it runs, loaded from `AGENT_CONFIG_PATH`.
+/// Missing fields keep their defaults; unknown ones are rejected so typos
+/// never silently fall back to them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentSettings {
+    /// Tools offered to the model, by name; every available tool when unset.
+    pub tools: Option<Vec<String>>,
+    pub model: ModelSettings,
+    pub persona: PersonaSettings,
+    pub schedule: ScheduleSettings,
+    pub mentions: MentionSettings,
+    pub limits: LimitSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelSettings {
+    /// Overrides `LLM_MODEL` and the backend's default model.
+    pub name: Option<String>,
+    pub max_tokens: u32,
+    pub temperature: f32,
+}
+
+impl Default for ModelSettings {
+    fn default() -> Self {
+        Self {
+            name: None,
+            max_tokens: 500,
+            temperature: 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersonaSettings {
+    pub system_prompt: String,
+    /// The user message that starts every run.
+    pub prompt: String,
+}
+
+impl Default for PersonaSettings {
+    fn default() -> Self {
+        Self {
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            prompt: "make a tweet".to_string(),
+        }
+    }
+}
+
+/// When the agent runs. Without `cron` it runs as often as `min_interval_secs`
+/// and the tweet rate limit allow; the other rules apply either way.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleSettings {
+    /// Minimum pause between runs, however much rate-limit budget is left.
+    pub min_interval_secs: u64,
+    /// Run at these times, e.g. `0 30 9,13,18 * * *`: seconds, minutes,
+    /// hours, day of month, month, day of week and an optional year.
+    pub cron: Option<String>,
+    /// IANA name, e.g. `Europe/Berlin`, for `cron`, quiet hours and days.
+    pub timezone: String,
+    /// Each run is delayed by up to this much, chosen at random.
+    pub jitter_secs: u64,
+    /// Runs are held until these end.
+    pub quiet_hours: Option<QuietHours>,
+    /// Extra runs are spread over the rest of the day when the posts so far
+    /// and the runs still scheduled would fall short of this.
+    pub min_posts_per_day: u32,
+    /// Posts a day, scheduled or answering mentions. Once reached, the agent
+    /// posts nothing more until the next day.
+    pub max_posts_per_day: u32,
+    /// What to do about `cron` slots missed while the agent was down.
+    pub catch_up: CatchUp,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            min_interval_secs: 30,
+            cron: None,
+            timezone: "UTC".to_string(),
+            jitter_secs: 0,
+            quiet_hours: None,
+            min_posts_per_day: 0,
+            max_posts_per_day: DEFAULT_MAX_POSTS_PER_DAY,
+            catch_up: CatchUp::default(),
+        }
+    }
+}
+
+/// Local times as `HH:MM`; `start` after `end` spans midnight.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CatchUp {
+    /// Wait for the next slot.
+    Skip,
+    /// Run once for all of them.
+    #[default]
+    Once,
+    /// Run once for each, `min_interval_secs` apart.
+    All,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MentionSettings {
+    /// Hand each new mention to the agent, besides the scheduled runs. They
+    /// wait out quiet hours and the daily post limit like scheduled runs.
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    /// Tweets of the thread above a mention shown with it; 0 shows only the
+    /// one it replies to.
+    pub thread_depth: usize,
+    /// Follows each mention in the message the agent is given.
+    pub instructions: String,
+}
+
+impl Default for MentionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_secs: 120,
+            thread_depth: 10,
+            instructions: "Answer with the reply tool if the mention deserves an answer; \
+                           otherwise say why you are ignoring it."
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    /// Model responses per run before it is abandoned, so a model that keeps
+    /// calling tools cannot act forever.
+    pub max_turns: usize,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self { max_turns: 10 }
+    }
+}
+
+impl AgentSettings {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.model.name.as_deref().is_some_and(str::is_empty) {
+            eyre::bail!("model.name must not be empty");
+        }
+        if self.model.max_tokens == 0 {
+            eyre::bail!("model.max_tokens must be positive");
+        }
+        if !(0.0..=2.0).contains(&self.model.temperature) {
+            eyre::bail!("model.temperature must be between 0 and 2");
+        }
+        if self.persona.system_prompt.trim().is_empty() {
+            eyre::bail!("persona.system_prompt must not be empty");
+        }
+        if self.persona.prompt.trim().is_empty() {
+            eyre::bail!("persona.prompt must not be empty");
+        }
+        if self.schedule.min_interval_secs == 0 {
+            eyre::bail!("schedule.min_interval_secs must be positive");
+        }
+        schedule::validate(&self.schedule)?;
+        if self.mentions.enabled {
+            if self.mentions.poll_interval_secs == 0 {
+                eyre::bail!("mentions.poll_interval_secs must be positive");
+            }
+            if self.mentions.instructions.trim().is_empty() {
+                eyre::bail!("mentions.instructions must not be empty");
+            }
+            if !self.enables("reply") {
+                eyre::bail!("mentions need the reply tool");
+            }
+        }
+        if self.limits.max_turns == 0 {
+            eyre::bail!("limits.max_turns must be positive");
+        }
+        if let Some(tools) = &self.tools {
+            if tools.is_empty() {
+                eyre::bail!("tools must not be empty");
+            }
+            let known = ToolRegistry::names();
+            for (i, tool) in tools.iter().enumerate() {
+                if !known.contains(&tool.as_str()) {
+                    eyre::bail!("Unknown tool: {}", tool);
+                }
+                if tools[..i].contains(tool) {
+                    eyre::bail!("Tool {} listed twice", tool);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the model may call `tool`.
+    pub fn enables(&self, tool: &str) -> bool {
+        self.tools
+            .as_ref()
+            .is_none_or(|tools| tools.iter().any(|enabled| enabled == tool))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsFormat {
+    Toml,
+    Yaml,
+}
+
+impl SettingsFormat {
+    /// Picks the format from the file extension.
+    pub fn from_path(path: &Path) -> eyre::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => eyre::bail!(
+                "Agent config {} must end in .toml, .yaml or .yml",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Validated settings and the exact text they came from. Served at `/config`
+/// and attested by hash, so observers can check which persona is running.
+#[derive(Serialize, Debug, Clone)]
+pub struct LoadedSettings {
+    #[serde(skip)]
+    pub settings: AgentSettings,
+    pub format: SettingsFormat,
+    pub source: String,
+    /// Hex SHA-256 of `source`.
+    pub sha256: String,
+}
+
+impl LoadedSettings {
+    pub fn parse(source: String, format: SettingsFormat) -> eyre::Result<Self> {
+        let settings: AgentSettings = match format {
+            SettingsFormat::Toml => toml::from_str(&source)?,
+            SettingsFormat::Yaml => serde_yaml::from_str(&source)?,
+        };
+        settings.validate()?;
+        Ok(Self {
+            settings,
+            format,
+            sha256: hex::encode(Sha256::digest(source.as_bytes())),
+            source,
+        })
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let format = SettingsFormat::from_path(path)?;
+        let source = tokio::fs::read_to_string(path).await?;
+        Self::parse(source, format)
+    }
+
+    /// The defaults, rendered as TOML so they are published and hashed like a
+    /// loaded file.
+    pub fn defaults() -> eyre::Result<Self> {
+        Self::parse(
+            toml::to_string(&AgentSettings::default())?,
+            SettingsFormat::Toml,
+        )
+    }
+
+    /// Loads `AGENT_CONFIG_PATH`, or the defaults when it is unset.
+    pub async fn from_env() -> eyre::Result<Self> {
+        match std::env::var("AGENT_CONFIG_PATH") {
+            Ok(path) => Self::load(path).await,
+            Err(_) => Self::defaults(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::accounts::AccountSummary;
+use crate::twitter::rate_limit::RateLimitBudget;
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// The last tool call an agent executed.
+#[derive(Serialize, Debug, Clone)]
+pub struct LastAction {
+    pub tool: String,
+    /// Unix timestamp.
+    pub at: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AgentActivity {
+    pub last_action: Option<LastAction>,
+    /// Unix timestamp of the next agent run, unset while one is running.
+    pub next_run_at: Option<u64>,
+}
+
+/// What an agent is doing, kept up to date by its event loop.
+#[derive(Default)]
+pub struct AgentStatus {
+    activity: Mutex<AgentActivity>,
+}
+
+impl AgentStatus {
+    pub fn activity(&self) -> AgentActivity {
+        self.activity.lock().unwrap().clone()
+    }
+
+    pub(crate) fn record_action(&self, tool: &str) {
+        self.activity.lock().unwrap().last_action = Some(LastAction {
+            tool: tool.to_string(),
+            at: now(),
+        });
+    }
+
+    pub(crate) fn schedule_next_run(&self, delay: Option<Duration>) {
+        self.activity.lock().unwrap().next_run_at = delay.map(|delay| now() + delay.as_secs());
+    }
+}
+
+/// One account in `/status`.
+#[derive(Serialize, Debug, Clone)]
+pub struct AccountStatus {
+    #[serde(flatten)]
+    pub account: AccountSummary,
+    #[serde(flatten)]
+    pub activity: AgentActivity,
+    /// The last budget Twitter reported, keyed by endpoint.
+    pub rate_limits: BTreeMap<&'static str, RateLimitBudget>,
+}
+
+/// Served at `/status`.
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusReport {
+    /// Unix timestamp the server started at.
+    pub started_at: u64,
+    pub released: bool,
+    pub accounts: Vec<AccountStatus>,
+}
+
+/// Served at `/health`; unhealthy while any agent has stopped before the
+/// accounts were released.
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthReport {
+    pub healthy: bool,
+    /// User IDs of stopped agents.
+    pub stopped: Vec<String>,
+}
+
+impl HealthReport {
+    pub fn new(accounts: &[AccountSummary], released: bool) -> Self {
+        let stopped: Vec<String> = accounts
+            .iter()
+            .filter(|account| !account.running)
+            .map(|account| account.id.clone())
+            .collect();
+        Self {
+            healthy: released || stopped.is_empty(),
+            stopped,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::action_log::{Action, ActionLog};
+use crate::llm::{FunctionDefinition, ImageBackend, StaticImageBackend, ToolCall};
+use crate::metrics::{metrics, ToolOutcome};
+use crate::schedule::Scheduler;
+use crate::twitter::{builder::TwitterClient, error::TwitterError, rate_limit::TWEETS_ENDPOINT};
+
+pub mod react;
+pub mod tweet;
+
+pub use react::{Like, Retweet};
+pub use tweet::{QuoteTweet, Reply, TweetJoke, TweetThread, TweetWithMedia};
+
+/// What a tool can reach while it runs.
+pub struct ToolContext<'c> {
+    pub twitter_client: &'c TwitterClient<'c>,
+    /// ID of the account the agent acts as.
+    pub user_id: &'c str,
+    pub action_log: &'c ActionLog,
+    /// Counts posts against the account's daily limit.
+    pub scheduler: &'c Scheduler,
+    /// Set once the call is rejected back to the model.
+    rejected: AtomicBool,
+}
+
+impl<'c> ToolContext<'c> {
+    pub fn new(
+        twitter_client: &'c TwitterClient<'c>,
+        user_id: &'c str,
+        action_log: &'c ActionLog,
+        scheduler: &'c Scheduler,
+    ) -> Self {
+        Self {
+            twitter_client,
+            user_id,
+            action_log,
+            scheduler,
+            rejected: AtomicBool::new(false),
+        }
+    }
+
+    /// Records an ID Twitter returned, e.g. of a posted tweet, which counts
+    /// towards today's posts.
+    pub async fn record_response(&self, endpoint: &str, id: &str) -> eyre::Result<()> {
+        if endpoint == TWEETS_ENDPOINT {
+            self.scheduler.record_post();
+        }
+        self.action_log
+            .record(
+                self.user_id,
+                Action::TwitterResponse {
+                    endpoint: endpoint.to_string(),
+                    id: id.to_string(),
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Feeds rejections the model can fix back to it as tool output, so it
+    /// gets a chance to try again; anything else fails the run.
+    pub fn recover(&self, e: TwitterError) -> eyre::Result<String> {
+        match e {
+            TwitterError::DuplicateContent(_) => Ok(self
+                .reject("Rejected as a duplicate, come up with something different".to_string())),
+            TwitterError::InvalidTweet(reason) => {
+                Ok(self.reject(format!("Rejected as invalid: {}", reason)))
+            }
+            e => {
+                log::warn!("Tool call failed: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The rejection to return if `posts` more tweets would go over today's
+    /// limit.
+    pub fn refuse_posts(&self, posts: usize) -> Option<String> {
+        let left = self.scheduler.posts_left();
+        (posts > left as usize).then(|| {
+            self.reject(format!(
+                "Rejected: only {} more tweets may be posted today",
+                left
+            ))
+        })
+    }
+
+    /// Marks the call as rejected, so it isn't counted as a success, and
+    /// passes `output` through for the model.
+    pub fn reject(&self, output: String) -> String {
+        self.rejected.store(true, Ordering::Relaxed);
+        output
+    }
+}
+
+/// Something the agent can call. `Args` is deserialized from the model's
+/// JSON arguments, so `parameters` and `Args` describe the same shape and
+/// live side by side in each tool's impl.
+#[async_trait::async_trait]
+pub trait Tool: Send + Sync {
+    type Args: DeserializeOwned + Send;
+
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// JSON schema for `Args`.
+    fn parameters(&self) -> Value;
+
+    /// Whether a call may run alongside neighbouring calls from the same
+    /// assistant turn.
+    fn runs_concurrently(&self) -> bool {
+        true
+    }
+
+    /// Returns the text fed back to the model. Errors the model can recover
+    /// from should be returned as `Ok` so it gets a chance to try again.
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: Self::Args) -> eyre::Result<String>;
+}
+
+/// `Tool` with its arguments type erased, so tools can share a registry.
+#[async_trait::async_trait]
+trait DynTool: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn definition(&self) -> FunctionDefinition;
+
+    fn runs_concurrently(&self) -> bool;
+
+    async fn call(&self, ctx: &ToolContext<'_>, arguments: &str) -> eyre::Result<String>;
+}
+
+#[async_trait::async_trait]
+impl<T: Tool> DynTool for T {
+    fn name(&self) -> &'static str {
+        Tool::name(self)
+    }
+
+    fn definition(&self) -> FunctionDefinition {
+        FunctionDefinition {
+            name: Tool::name(self).to_string(),
+            description: Some(self.description().to_string()),
+            parameters: self.parameters(),
+        }
+    }
+
+    fn runs_concurrently(&self) -> bool {
+        Tool::runs_concurrently(self)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, arguments: &str) -> eyre::Result<String> {
+        match serde_json::from_str::<T::Args>(arguments) {
+            Ok(args) => self.invoke(ctx, args).await,
+            Err(e) => Ok(ctx.reject(format!("Invalid arguments for {}: {}", Tool::name(self), e))),
+        }
+    }
+}
+
+/// The tools offered to the model, in the order they are advertised.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn DynTool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every tool the agent knows; `tweet_with_media` only with `images`.
+    pub fn all(images: Option<Arc<dyn ImageBackend>>) -> Self {
+        let tools = Self::new()
+            .with_tool(TweetJoke)
+            .with_tool(TweetThread)
+            .with_tool(Reply)
+            .with_tool(QuoteTweet)
+            .with_tool(Like)
+            .with_tool(Retweet);
+        match images {
+            Some(images) => tools.with_tool(TweetWithMedia::new(images)),
+            None => tools,
+        }
+    }
+
+    /// Names of every tool the agent knows, whether or not an image backend
+    /// is configured.
+    pub fn names() -> Vec<&'static str> {
+        // The backend is never called, it only lets the tool be registered
+        Self::all(Some(Arc::new(StaticImageBackend(Vec::new()))))
+            .tools
+            .iter()
+            .map(|tool| tool.name())
+            .collect()
+    }
+
+    /// Panics if a tool with the same name is already registered.
+    pub fn with_tool<T: Tool + 'static>(mut self, tool: T) -> Self {
+        assert!(
+            self.get(Tool::name(&tool)).is_none(),
+            "Tool {} registered twice",
+            Tool::name(&tool)
+        );
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    /// Drops every tool `keep` rejects by name.
+    pub fn retain(mut self, keep: impl Fn(&str) -> bool) -> Self {
+        self.tools.retain(|tool| keep(tool.name()));
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn DynTool> {
+        self.tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .map(|tool| tool.as_ref())
+    }
+
+    pub fn definitions(&self) -> Vec<FunctionDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    /// Unknown tools run alone, as there is nothing to say they are safe.
+    pub fn runs_concurrently(&self, tool_call: &ToolCall) -> bool {
+        self.get(&tool_call.function.name)
+            .is_some_and(|tool| tool.runs_concurrently())
+    }
+
+    pub async fn invoke(
+        &self,
+        ctx: &ToolContext<'_>,
+        tool_call: &ToolCall,
+    ) -> eyre::Result<String> {
+        let function_call = &tool_call.function;
+        match self.get(&function_call.name) {
+            Some(tool) => {
+                let result = tool.call(ctx, &function_call.arguments).await;
+                let outcome = match &result {
+                    Err(_) => ToolOutcome::Error,
+                    Ok(_) if ctx.rejected.load(Ordering::Relaxed) => ToolOutcome::Rejected,
+                    Ok(_) => ToolOutcome::Ok,
+                };
+                metrics().record_tool_invocation(tool.name(), outcome);
+                result
+            }
+            None => {
+                // Not labelled with the name, which the model made up
+                metrics().record_tool_invocation("unknown", ToolOutcome::Error);
+                Ok(format!("Unknown tool: {}", function_call.name))
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{Tool, ToolContext};
+use crate::twitter::rate_limit::{LIKES_ENDPOINT, RETWEETS_ENDPOINT};
+
+#[derive(Deserialize, Debug)]
+pub struct ReactArgs {
+    pub tweet_id: String,
+}
+
+fn react_parameters(action: &str) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "tweet_id": { "type": "string", "description": format!("ID of the tweet to {}.", action) }
+        },
+        "required": ["tweet_id"],
+    })
+}
+
+pub struct Like;
+
+#[async_trait::async_trait]
+impl Tool for Like {
+    type Args = ReactArgs;
+
+    fn name(&self) -> &'static str {
+        "like"
+    }
+
+    fn description(&self) -> &'static str {
+        "Likes a tweet."
+    }
+
+    fn parameters(&self) -> Value {
+        react_parameters("like")
+    }
+
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: ReactArgs) -> eyre::Result<String> {
+        let user_id = ctx.user_id.to_string();
+        match ctx
+            .twitter_client
+            .like(user_id, args.tweet_id.clone())
+            .await
+        {
+            Ok(()) => {
+                ctx.record_response(LIKES_ENDPOINT, &args.tweet_id).await?;
+                Ok(format!("Liked tweet {}", args.tweet_id))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
+
+pub struct Retweet;
+
+#[async_trait::async_trait]
+impl Tool for Retweet {
+    type Args = ReactArgs;
+
+    fn name(&self) -> &'static str {
+        "retweet"
+    }
+
+    fn description(&self) -> &'static str {
+        "Retweets a tweet."
+    }
+
+    fn parameters(&self) -> Value {
+        react_parameters("retweet")
+    }
+
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: ReactArgs) -> eyre::Result<String> {
+        let user_id = ctx.user_id.to_string();
+        match ctx
+            .twitter_client
+            .retweet(user_id, args.tweet_id.clone())
+            .await
+        {
+            Ok(()) => {
+                ctx.record_response(RETWEETS_ENDPOINT, &args.tweet_id)
+                    .await?;
+                Ok(format!("Retweeted tweet {}", args.tweet_id))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
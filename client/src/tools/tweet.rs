@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{Tool, ToolContext};
+use crate::llm::ImageBackend;
+use crate::twitter::{
+    rate_limit::{MEDIA_UPLOAD_ENDPOINT, TWEETS_ENDPOINT},
+    tweet::{Thread, Tweet},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct TweetJokeArgs {
+    pub joke: String,
+}
+
+pub struct TweetJoke;
+
+#[async_trait::async_trait]
+impl Tool for TweetJoke {
+    type Args = TweetJokeArgs;
+
+    fn name(&self) -> &'static str {
+        "tweet_joke"
+    }
+
+    fn description(&self) -> &'static str {
+        "Tweets a joke."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "joke": { "type": "string", "description": "The joke to be tweeted." }
+            },
+            "required": ["joke"],
+        })
+    }
+
+    /// Tweets are posted one at a time so they land on the timeline in the
+    /// order the model chose.
+    fn runs_concurrently(&self) -> bool {
+        false
+    }
+
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: TweetJokeArgs) -> eyre::Result<String> {
+        if let Some(rejection) = ctx.refuse_posts(1) {
+            return Ok(rejection);
+        }
+        let tweet = Tweet::new(args.joke);
+        match ctx.twitter_client.raw_tweet(tweet).await {
+            Ok(id) => {
+                ctx.record_response(TWEETS_ENDPOINT, &id).await?;
+                Ok("Tweeted successfully".to_string())
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReplyArgs {
+    pub tweet_id: String,
+    pub text: String,
+}
+
+pub struct Reply;
+
+#[async_trait::async_trait]
+impl Tool for Reply {
+    type Args = ReplyArgs;
+
+    fn name(&self) -> &'static str {
+        "reply"
+    }
+
+    fn description(&self) -> &'static str {
+        "Replies to a tweet."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "tweet_id": { "type": "string", "description": "ID of the tweet to reply to." },
+                "text": { "type": "string", "description": "Text of the reply." }
+            },
+            "required": ["tweet_id", "text"],
+        })
+    }
+
+    fn runs_concurrently(&self) -> bool {
+        false
+    }
+
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: ReplyArgs) -> eyre::Result<String> {
+        if let Some(rejection) = ctx.refuse_posts(1) {
+            return Ok(rejection);
+        }
+        let mut tweet = Tweet::new(args.text);
+        tweet.set_reply_tweet_id(args.tweet_id);
+        match ctx.twitter_client.raw_tweet(tweet).await {
+            Ok(id) => {
+                ctx.record_response(TWEETS_ENDPOINT, &id).await?;
+                Ok(format!("Replied with tweet {}", id))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TweetThreadArgs {
+    pub text: String,
+}
+
+pub struct TweetThread;
+
+#[async_trait::async_trait]
+impl Tool for TweetThread {
+    type Args = TweetThreadArgs;
+
+    fn name(&self) -> &'static str {
+        "tweet_thread"
+    }
+
+    fn description(&self) -> &'static str {
+        "Tweets text too long for one tweet as a thread, split between sentences."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": "Full text of the thread." }
+            },
+            "required": ["text"],
+        })
+    }
+
+    fn runs_concurrently(&self) -> bool {
+        false
+    }
+
+    /// Only the parts still up are recorded; a thread that failed midway was
+    /// deleted again.
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: TweetThreadArgs) -> eyre::Result<String> {
+        let thread = match Thread::split(&args.text) {
+            Ok(thread) => thread,
+            Err(e) => return ctx.recover(e),
+        };
+        if let Some(rejection) = ctx.refuse_posts(thread.parts().len()) {
+            return Ok(rejection);
+        }
+        match ctx.twitter_client.post_thread(thread).await {
+            Ok(ids) => {
+                for id in &ids {
+                    ctx.record_response(TWEETS_ENDPOINT, id).await?;
+                }
+                Ok(format!(
+                    "Tweeted a thread of {} tweets starting with tweet {}",
+                    ids.len(),
+                    ids[0]
+                ))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QuoteTweetArgs {
+    pub tweet_id: String,
+    pub text: String,
+}
+
+pub struct QuoteTweet;
+
+#[async_trait::async_trait]
+impl Tool for QuoteTweet {
+    type Args = QuoteTweetArgs;
+
+    fn name(&self) -> &'static str {
+        "quote_tweet"
+    }
+
+    fn description(&self) -> &'static str {
+        "Quote-tweets a tweet with a comment."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "tweet_id": { "type": "string", "description": "ID of the tweet to quote." },
+                "text": { "type": "string", "description": "Comment on the quoted tweet." }
+            },
+            "required": ["tweet_id", "text"],
+        })
+    }
+
+    fn runs_concurrently(&self) -> bool {
+        false
+    }
+
+    async fn invoke(&self, ctx: &ToolContext<'_>, args: QuoteTweetArgs) -> eyre::Result<String> {
+        if let Some(rejection) = ctx.refuse_posts(1) {
+            return Ok(rejection);
+        }
+        let mut tweet = Tweet::new(args.text);
+        tweet.set_quote_tweet_id(args.tweet_id);
+        match ctx.twitter_client.raw_tweet(tweet).await {
+            Ok(id) => {
+                ctx.record_response(TWEETS_ENDPOINT, &id).await?;
+                Ok(format!("Quoted with tweet {}", id))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TweetWithMediaArgs {
+    pub text: String,
+    pub image_prompt: String,
+}
+
+/// Generates an image, uploads it and tweets it. Only registered when an
+/// image backend is configured.
+pub struct TweetWithMedia {
+    images: Arc<dyn ImageBackend>,
+}
+
+impl TweetWithMedia {
+    pub fn new(images: Arc<dyn ImageBackend>) -> Self {
+        Self { images }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for TweetWithMedia {
+    type Args = TweetWithMediaArgs;
+
+    fn name(&self) -> &'static str {
+        "tweet_with_media"
+    }
+
+    fn description(&self) -> &'static str {
+        "Tweets text together with an image generated from a prompt."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": "Text of the tweet." },
+                "image_prompt": { "type": "string", "description": "Description of the image to generate." }
+            },
+            "required": ["text", "image_prompt"],
+        })
+    }
+
+    fn runs_concurrently(&self) -> bool {
+        false
+    }
+
+    async fn invoke(
+        &self,
+        ctx: &ToolContext<'_>,
+        args: TweetWithMediaArgs,
+    ) -> eyre::Result<String> {
+        // Check the text before paying for an image and an upload
+        let mut tweet = Tweet::new(args.text);
+        if let Err(e) = tweet.validate() {
+            return ctx.recover(e);
+        }
+        if let Some(rejection) = ctx.refuse_posts(1) {
+            return Ok(rejection);
+        }
+
+        let image = match self.images.generate(&args.image_prompt).await {
+            Ok(image) => image,
+            Err(e) => return Ok(ctx.reject(format!("Image generation failed: {}", e))),
+        };
+        let media_id = match ctx.twitter_client.upload_media(image, None).await {
+            Ok(media_id) => {
+                ctx.record_response(MEDIA_UPLOAD_ENDPOINT, &media_id)
+                    .await?;
+                media_id
+            }
+            Err(e) => return ctx.recover(e),
+        };
+        tweet.set_media_ids(vec![media_id]);
+        match ctx.twitter_client.raw_tweet(tweet).await {
+            Ok(id) => {
+                ctx.record_response(TWEETS_ENDPOINT, &id).await?;
+                Ok(format!("Tweeted with media as tweet {}", id))
+            }
+            Err(e) => ctx.recover(e),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::collections::HashSet;
+
+use super::builder::TwitterClient;
+
+/// What this client has done to the account since it was created, so it can
+/// be told apart from activity by anyone else holding the credentials.
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    /// IDs returned by `raw_tweet`.
+    pub tweets: HashSet<String>,
+    /// IDs moved here from `tweets` by `delete_tweet`. Still the client's
+    /// own, should a deleted tweet show up on a lagging timeline.
+    pub deleted_tweets: HashSet<String>,
+    /// Tweet IDs passed to `like`.
+    pub likes: HashSet<String>,
+    /// Tweet IDs passed to `retweet`.
+    pub retweets: HashSet<String>,
+}
+
+impl TwitterClient<'_> {
+    pub fn activity(&self) -> Activity {
+        self.activity.lock().unwrap().clone()
+    }
+}
@@ -1,4 +1,11 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand_core::{OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::error::{decode_json, response_text, TwitterError};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct RequestTokenRequestQuery {
@@ -12,6 +19,8 @@ struct RequestTokenResponseBody {
     oauth_callback_confirmed: bool,
 }
 
+// Only read by the manual login test commented out at the end of this file.
+#[allow(dead_code)]
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct CallbackUrlQuery {
     oauth_token: String,
@@ -25,29 +34,31 @@ pub struct TwitterTokenPair {
 }
 
 pub async fn request_oauth_token(
+    oauth_base_url: &str,
     app_key: String,
     app_secret: String,
     callback_url: String,
-) -> eyre::Result<TwitterTokenPair> {
+) -> Result<TwitterTokenPair, TwitterError> {
     let secrets = reqwest_oauth1::Secrets::new(app_key, app_secret);
     let query = RequestTokenRequestQuery {
         oauth_callback: callback_url.to_string(),
     };
     let response = reqwest_oauth1::Client::new()
-        .post("https://api.twitter.com/oauth/request_token")
+        .post(format!("{}/oauth/request_token", oauth_base_url))
         .sign(secrets)
         .query(&query)
         .generate_signature()?
         .send()
         .await?;
-    let status = response.status();
-    if !status.is_success() {
-        eyre::bail!(response.text().await?);
+    let body = response_text(response).await?;
+    let request_token_body = serde_urlencoded::from_str::<RequestTokenResponseBody>(&body)
+        .map_err(|e| TwitterError::decode(e, body.clone()))?;
+    if !request_token_body.oauth_callback_confirmed {
+        return Err(TwitterError::decode(
+            "oauth_callback_confirmed is false",
+            body,
+        ));
     }
-    let response_bytes = response.bytes().await?;
-    let request_token_body =
-        serde_urlencoded::from_bytes::<RequestTokenResponseBody>(&response_bytes)?;
-    assert!(request_token_body.oauth_callback_confirmed);
     Ok(TwitterTokenPair {
         token: request_token_body.oauth_token,
         secret: request_token_body.oauth_token_secret,
@@ -68,33 +79,29 @@ struct AccessTokenRequestQuery {
 }
 
 pub async fn authorize_token(
+    oauth_base_url: &str,
     app_key: String,
     app_secret: String,
     oauth_token: String,
     oauth_token_secret: String,
     oauth_verifier: String,
-) -> eyre::Result<TwitterTokenPair> {
+) -> Result<TwitterTokenPair, TwitterError> {
     let query = AccessTokenRequestQuery { oauth_verifier };
 
     let secrets =
         reqwest_oauth1::Secrets::new(app_key, app_secret).token(oauth_token, oauth_token_secret);
 
     let response = reqwest_oauth1::Client::new()
-        .post("https://api.twitter.com/oauth/access_token")
+        .post(format!("{}/oauth/access_token", oauth_base_url))
         .sign(secrets)
         .query(&query)
         .generate_signature()?
         .send()
         .await?;
 
-    let status = response.status();
-    if !status.is_success() {
-        eyre::bail!(response.text().await?);
-    }
-    let response_bytes = response.bytes().await?;
-
-    let access_token_body =
-        serde_urlencoded::from_bytes::<AccessTokenResponseBody>(&response_bytes)?;
+    let body = response_text(response).await?;
+    let access_token_body = serde_urlencoded::from_str::<AccessTokenResponseBody>(&body)
+        .map_err(|e| TwitterError::decode(e, body.clone()))?;
 
     Ok(TwitterTokenPair {
         token: access_token_body.oauth_token,
@@ -102,6 +109,206 @@ pub async fn authorize_token(
     })
 }
 
+/// Scopes requested when none are configured: enough for every agent tool,
+/// plus `offline.access` for a refresh token.
+pub const DEFAULT_OAUTH2_SCOPES: &[&str] = &[
+    "tweet.read",
+    "tweet.write",
+    "users.read",
+    "like.read",
+    "like.write",
+    "offline.access",
+];
+
+/// The app's OAuth 2.0 client, as registered in the developer portal.
+#[derive(Debug, Clone)]
+pub struct OAuth2Config {
+    pub client_id: String,
+    /// `None` for public clients.
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+impl OAuth2Config {
+    pub fn new(client_id: String) -> Self {
+        Self {
+            client_id,
+            client_secret: None,
+            scopes: DEFAULT_OAUTH2_SCOPES
+                .iter()
+                .map(|scope| scope.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn with_client_secret(mut self, client_secret: String) -> Self {
+        self.client_secret = Some(client_secret);
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+}
+
+/// An OAuth 2.0 user-context token.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    /// Only issued with the `offline.access` scope.
+    pub refresh_token: Option<String>,
+    /// Unix timestamp after which `access_token` stops working.
+    pub expires_at: Option<u64>,
+    /// Space-separated scopes actually granted.
+    pub scope: String,
+}
+
+/// User credentials for either auth scheme. Untagged so credentials stored
+/// before OAuth 2.0 support still load.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum UserAuth {
+    OAuth1(TwitterTokenPair),
+    OAuth2(OAuth2Token),
+}
+
+/// PKCE (RFC 7636) verifier and its S256 challenge, one pair per login.
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate() -> Self {
+        let verifier = random_token();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+/// 32 random bytes, base64url encoded: suitable for PKCE verifiers and
+/// `state` parameters.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[derive(Serialize)]
+struct AuthorizeQuery<'a> {
+    response_type: &'static str,
+    client_id: &'a str,
+    redirect_uri: &'a str,
+    scope: String,
+    state: &'a str,
+    code_challenge: &'a str,
+    code_challenge_method: &'static str,
+}
+
+pub fn oauth2_authorize_url(
+    authorize_base_url: &str,
+    config: &OAuth2Config,
+    redirect_uri: &str,
+    state: &str,
+    code_challenge: &str,
+) -> String {
+    let query = AuthorizeQuery {
+        response_type: "code",
+        client_id: &config.client_id,
+        redirect_uri,
+        scope: config.scopes.join(" "),
+        state,
+        code_challenge,
+        code_challenge_method: "S256",
+    };
+    format!(
+        "{}/i/oauth2/authorize?{}",
+        authorize_base_url,
+        serde_urlencoded::to_string(&query).unwrap_or_default()
+    )
+}
+
+#[derive(Deserialize)]
+struct OAuth2TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    #[serde(default)]
+    scope: String,
+}
+
+/// Posts a grant to the token endpoint, authenticating confidential clients
+/// with HTTP Basic as Twitter requires.
+async fn request_oauth2_token(
+    oauth_base_url: &str,
+    config: &OAuth2Config,
+    form: &[(&str, &str)],
+) -> Result<OAuth2Token, TwitterError> {
+    let mut form = form.to_vec();
+    form.push(("client_id", &config.client_id));
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/2/oauth2/token", oauth_base_url))
+        .form(&form);
+    if let Some(client_secret) = &config.client_secret {
+        request = request.basic_auth(&config.client_id, Some(client_secret));
+    }
+    let body = response_text(request.send().await?).await?;
+    let response: OAuth2TokenResponse = decode_json(body)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Ok(OAuth2Token {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        expires_at: response.expires_in.map(|expires_in| now + expires_in),
+        scope: response.scope,
+    })
+}
+
+/// Exchanges the `code` from the authorize redirect for a token.
+pub async fn exchange_oauth2_code(
+    oauth_base_url: &str,
+    config: &OAuth2Config,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<OAuth2Token, TwitterError> {
+    request_oauth2_token(
+        oauth_base_url,
+        config,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", code_verifier),
+        ],
+    )
+    .await
+}
+
+/// Trades a refresh token for a new token. Refresh tokens are single use, so
+/// the returned token's `refresh_token` replaces the one passed in.
+pub async fn refresh_oauth2_token(
+    oauth_base_url: &str,
+    config: &OAuth2Config,
+    refresh_token: &str,
+) -> Result<OAuth2Token, TwitterError> {
+    request_oauth2_token(
+        oauth_base_url,
+        config,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ],
+    )
+    .await
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use super::*;
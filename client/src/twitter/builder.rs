@@ -1,28 +1,106 @@
-use oauth1_request::signature_method::hmac_sha1::HmacSha1;
-use reqwest_oauth1::{Client, OAuthClientProvider, Secrets, Signer};
+use std::sync::{Arc, Mutex};
 
-use super::auth::{self, TwitterTokenPair};
+use reqwest_oauth1::{OAuthClientProvider, Secrets};
+
+use super::{
+    activity::Activity,
+    auth::{self, OAuth2Config, OAuth2Token, TwitterTokenPair, UserAuth},
+    conversation::TweetCache,
+    error::TwitterError,
+    http::TwitterHttpClient,
+    oauth2::OAuth2Session,
+    rate_limit::RateLimiter,
+};
+
+pub const DEFAULT_API_BASE_URL: &str = "https://api.twitter.com";
+pub const DEFAULT_UPLOAD_BASE_URL: &str = "https://upload.twitter.com";
+pub const DEFAULT_OAUTH_BASE_URL: &str = "https://api.twitter.com";
+pub const DEFAULT_AUTHORIZE_BASE_URL: &str = "https://twitter.com";
 
 #[derive(Debug, Clone)]
 pub struct TwitterBuilder {
     pub consumer_key: String,
     pub consumer_secret: String,
+    /// Base for the v2 REST endpoints, e.g. `{api_base_url}/2/tweets`.
+    pub api_base_url: String,
+    /// Base for the v1.1 media upload endpoint.
+    pub upload_base_url: String,
+    /// Base for the OAuth 1.0a request/authenticate/access token endpoints
+    /// and the OAuth 2.0 token endpoint.
+    pub oauth_base_url: String,
+    /// Base for the OAuth 2.0 authorize page users are sent to.
+    pub authorize_base_url: String,
+    /// Set to log users in with OAuth 2.0 instead of OAuth 1.0a.
+    pub oauth2: Option<OAuth2Config>,
 }
 
 pub struct TwitterClient<'a> {
-    pub client: Client<Signer<'a, Secrets<'a>, HmacSha1>>,
+    pub client: TwitterHttpClient<'a>,
+    pub(crate) rate_limits: Arc<RateLimiter>,
+    pub(crate) activity: Mutex<Activity>,
+    pub(crate) tweets: TweetCache,
+    pub(crate) api_base_url: String,
+    pub(crate) upload_base_url: String,
 }
 
 impl TwitterBuilder {
     pub fn new(consumer_key: String, consumer_secret: String) -> Self {
-        Self { consumer_key, consumer_secret }
+        Self {
+            consumer_key,
+            consumer_secret,
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+            upload_base_url: DEFAULT_UPLOAD_BASE_URL.to_string(),
+            oauth_base_url: DEFAULT_OAUTH_BASE_URL.to_string(),
+            authorize_base_url: DEFAULT_AUTHORIZE_BASE_URL.to_string(),
+            oauth2: None,
+        }
+    }
+
+    pub fn with_api_base_url(mut self, api_base_url: String) -> Self {
+        self.api_base_url = trim_base_url(api_base_url);
+        self
+    }
+
+    pub fn with_upload_base_url(mut self, upload_base_url: String) -> Self {
+        self.upload_base_url = trim_base_url(upload_base_url);
+        self
+    }
+
+    pub fn with_oauth_base_url(mut self, oauth_base_url: String) -> Self {
+        self.oauth_base_url = trim_base_url(oauth_base_url);
+        self
+    }
+
+    pub fn with_authorize_base_url(mut self, authorize_base_url: String) -> Self {
+        self.authorize_base_url = trim_base_url(authorize_base_url);
+        self
+    }
+
+    pub fn with_oauth2(mut self, oauth2: OAuth2Config) -> Self {
+        self.oauth2 = Some(oauth2);
+        self
+    }
+
+    fn oauth2_config(&self) -> Result<&OAuth2Config, TwitterError> {
+        self.oauth2
+            .as_ref()
+            .ok_or(TwitterError::OAuth2NotConfigured)
+    }
+
+    /// Where to send the user to approve a request token.
+    pub fn authenticate_url(&self, oauth_token: &str) -> String {
+        format!(
+            "{}/oauth/authenticate?oauth_token={}",
+            self.oauth_base_url, oauth_token
+        )
     }
 
     pub async fn request_oauth_token(
         &self,
         callback_url: String,
-    ) -> eyre::Result<TwitterTokenPair> {
+    ) -> Result<TwitterTokenPair, TwitterError> {
         auth::request_oauth_token(
+            &self.oauth_base_url,
             self.consumer_key.clone(),
             self.consumer_secret.clone(),
             callback_url,
@@ -35,8 +113,9 @@ impl TwitterBuilder {
         oauth_token: String,
         oauth_token_secret: String,
         oauth_verifier: String,
-    ) -> eyre::Result<TwitterTokenPair> {
+    ) -> Result<TwitterTokenPair, TwitterError> {
         auth::authorize_token(
+            &self.oauth_base_url,
             self.consumer_key.clone(),
             self.consumer_secret.clone(),
             oauth_token,
@@ -46,16 +125,93 @@ impl TwitterBuilder {
         .await
     }
 
-    // pub fn from_access_tokens(tokens: AccessTokens) -> Self {
+    /// Where to send the user to approve the app with OAuth 2.0. `state` and
+    /// the PKCE challenge must be fresh for every login.
+    pub fn oauth2_authorize_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        code_challenge: &str,
+    ) -> Result<String, TwitterError> {
+        Ok(auth::oauth2_authorize_url(
+            &self.authorize_base_url,
+            self.oauth2_config()?,
+            redirect_uri,
+            state,
+            code_challenge,
+        ))
+    }
+
+    pub async fn exchange_oauth2_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuth2Token, TwitterError> {
+        auth::exchange_oauth2_code(
+            &self.oauth_base_url,
+            self.oauth2_config()?,
+            code,
+            redirect_uri,
+            code_verifier,
+        )
+        .await
+    }
 
-    // }
+    pub async fn refresh_oauth2_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<OAuth2Token, TwitterError> {
+        auth::refresh_oauth2_token(&self.oauth_base_url, self.oauth2_config()?, refresh_token).await
+    }
 
-    pub fn with_auth(&self, tokens: TwitterTokenPair) -> TwitterClient {
+    /// A client signing with OAuth 1.0a.
+    pub fn with_auth(&self, tokens: TwitterTokenPair) -> TwitterClient<'static> {
         let secrets = Secrets::new(self.consumer_key.clone(), self.consumer_secret.clone())
             .token(tokens.token, tokens.secret);
+        self.client(TwitterHttpClient::OAuth1(
+            reqwest::Client::new().oauth1(secrets),
+        ))
+    }
+
+    /// A client sending an OAuth 2.0 bearer token, refreshed as it expires
+    /// if OAuth 2.0 is configured.
+    pub fn with_oauth2_token(&self, token: OAuth2Token) -> TwitterClient<'static> {
+        self.client(TwitterHttpClient::OAuth2 {
+            client: reqwest::Client::new(),
+            session: OAuth2Session::new(token, self.oauth2.clone(), self.oauth_base_url.clone()),
+        })
+    }
 
-        let client = reqwest::Client::new();
-        // client.oauth1(secrets)
-        TwitterClient { client: client.oauth1(secrets) }
+    pub fn with_user_auth(&self, auth: UserAuth) -> TwitterClient<'static> {
+        match auth {
+            UserAuth::OAuth1(tokens) => self.with_auth(tokens),
+            UserAuth::OAuth2(token) => self.with_oauth2_token(token),
+        }
     }
+
+    fn client<'a>(&self, client: TwitterHttpClient<'a>) -> TwitterClient<'a> {
+        TwitterClient {
+            client,
+            rate_limits: Arc::new(RateLimiter::default()),
+            activity: Mutex::new(Activity::default()),
+            tweets: TweetCache::default(),
+            api_base_url: self.api_base_url.clone(),
+            upload_base_url: self.upload_base_url.clone(),
+        }
+    }
+}
+
+impl TwitterClient<'_> {
+    pub(crate) fn api_url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base_url, path)
+    }
+
+    pub(crate) fn upload_url(&self, path: &str) -> String {
+        format!("{}{}", self.upload_base_url, path)
+    }
+}
+
+fn trim_base_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
 }
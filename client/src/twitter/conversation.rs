@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use serde::Deserialize;
+
+use super::{
+    builder::TwitterClient,
+    error::{decode_json, TwitterError},
+    mentions::Author,
+    rate_limit::TWEET_LOOKUP_ENDPOINT,
+    timeline::ReferencedTweet,
+};
+
+/// Tweets kept by `TweetCache` before the oldest are evicted.
+const CACHE_CAPACITY: usize = 1000;
+
+/// A tweet in a conversation, as looked up by ID.
+#[derive(Debug, Clone)]
+pub struct ThreadTweet {
+    pub id: String,
+    pub text: String,
+    pub conversation_id: Option<String>,
+    /// Unset if Twitter left the author out of the response.
+    pub author: Option<Author>,
+    /// The tweet this one replies to, if any.
+    pub in_reply_to_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupData {
+    id: String,
+    text: String,
+    author_id: Option<String>,
+    conversation_id: Option<String>,
+    #[serde(default)]
+    referenced_tweets: Vec<ReferencedTweet>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct Includes {
+    #[serde(default)]
+    users: Vec<Author>,
+}
+
+/// `data` is missing for tweets that were deleted or can't be seen, with the
+/// reason under `errors`.
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    data: Option<LookupData>,
+    #[serde(default)]
+    includes: Includes,
+}
+
+/// Tweets already looked up, so walking overlapping threads doesn't refetch
+/// them. Tweets can't be edited through the API, so entries never go stale;
+/// the oldest are evicted once `CACHE_CAPACITY` is reached.
+#[derive(Default)]
+pub(crate) struct TweetCache {
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    tweets: HashMap<String, ThreadTweet>,
+    /// Insertion order, oldest first.
+    order: VecDeque<String>,
+}
+
+impl TweetCache {
+    pub(crate) fn get(&self, id: &str) -> Option<ThreadTweet> {
+        self.inner.lock().unwrap().tweets.get(id).cloned()
+    }
+
+    pub(crate) fn insert(&self, tweet: ThreadTweet) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.tweets.contains_key(&tweet.id) {
+            return;
+        }
+        if inner.order.len() >= CACHE_CAPACITY {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.tweets.remove(&oldest);
+            }
+        }
+        inner.order.push_back(tweet.id.clone());
+        inner.tweets.insert(tweet.id.clone(), tweet);
+    }
+}
+
+/// The replies leading up to a tweet, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    pub tweets: Vec<ThreadTweet>,
+    /// False if the walk stopped short of the root, at the depth limit or a
+    /// tweet that couldn't be fetched.
+    pub complete: bool,
+}
+
+impl Conversation {
+    /// One line per tweet, e.g. `[123] @alice: text`, for the agent's prompt.
+    pub fn transcript(&self) -> String {
+        let mut transcript = String::new();
+        if !self.complete {
+            transcript.push_str("(earlier tweets not shown)\n");
+        }
+        for tweet in &self.tweets {
+            let author = match &tweet.author {
+                Some(author) => format!("@{}", author.username),
+                None => "someone".to_string(),
+            };
+            let text = tweet.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            transcript.push_str(&format!("[{}] {}: {}\n", tweet.id, author, text));
+        }
+        transcript
+    }
+}
+
+impl TwitterClient<'_> {
+    /// The tweet with `id`, or `None` if it was deleted or can't be seen.
+    /// Served from the cache when looked up before.
+    pub async fn get_tweet(&self, id: &str) -> Result<Option<ThreadTweet>, TwitterError> {
+        if let Some(tweet) = self.tweets.get(id) {
+            return Ok(Some(tweet));
+        }
+        let url = self.api_url(&format!(
+            "/2/tweets/{}?tweet.fields=author_id,conversation_id,referenced_tweets\
+             &expansions=author_id&user.fields=username,name",
+            id
+        ));
+        let resp = self
+            .send(TWEET_LOOKUP_ENDPOINT, || self.client.get(url.clone()))
+            .await?;
+        let response: LookupResponse = decode_json(resp)?;
+        let Some(data) = response.data else {
+            return Ok(None);
+        };
+        let tweet = ThreadTweet {
+            author: data.author_id.and_then(|author_id| {
+                response
+                    .includes
+                    .users
+                    .into_iter()
+                    .find(|user| user.id == author_id)
+            }),
+            in_reply_to_id: data
+                .referenced_tweets
+                .into_iter()
+                .find(|referenced| referenced.kind == "replied_to")
+                .map(|referenced| referenced.id),
+            id: data.id,
+            text: data.text,
+            conversation_id: data.conversation_id,
+        };
+        self.tweets.insert(tweet.clone());
+        Ok(Some(tweet))
+    }
+
+    /// `tweet_id` and up to `max_depth - 1` of the tweets it replies to,
+    /// walking towards the root of its conversation.
+    pub async fn get_conversation(
+        &self,
+        tweet_id: &str,
+        max_depth: usize,
+    ) -> Result<Conversation, TwitterError> {
+        let mut tweets = Vec::new();
+        let mut next = Some(tweet_id.to_string());
+        let mut complete = true;
+        while let Some(id) = next.take() {
+            if tweets.len() >= max_depth {
+                complete = false;
+                break;
+            }
+            match self.get_tweet(&id).await? {
+                Some(tweet) => {
+                    next = tweet.in_reply_to_id.clone();
+                    tweets.push(tweet);
+                }
+                None => {
+                    complete = false;
+                    break;
+                }
+            }
+        }
+        tweets.reverse();
+        Ok(Conversation { tweets, complete })
+    }
+}
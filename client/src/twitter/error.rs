@@ -0,0 +1,189 @@
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::Deserialize;
+
+use super::rate_limit::header_u64;
+
+/// v1.1 error codes that map onto the variants below.
+/// See https://developer.x.com/en/support/x-api/error-troubleshooting
+const V1_RATE_LIMIT_EXCEEDED: u32 = 88;
+const V1_COULD_NOT_AUTHENTICATE: u32 = 32;
+const V1_INVALID_OR_EXPIRED_TOKEN: u32 = 89;
+const V1_BAD_AUTHENTICATION_DATA: u32 = 215;
+const V1_ACCOUNT_SUSPENDED: u32 = 64;
+const V1_ACCOUNT_LOCKED: u32 = 326;
+const V1_DUPLICATE_STATUS: u32 = 187;
+
+/// v2 problem `type` for an exhausted monthly post cap.
+const V2_USAGE_CAPPED: &str = "https://api.twitter.com/2/problems/usage-capped";
+/// v2 reports duplicates as a bare `about:blank` 403, so they can only be
+/// told apart by this exact `detail`.
+const V2_DUPLICATE_DETAIL: &str = "You are not allowed to create a Tweet with duplicate content.";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TwitterError {
+    #[error("rate limited (resets at {reset_at:?})")]
+    RateLimited {
+        /// Unix timestamp from `x-rate-limit-reset`, if present.
+        reset_at: Option<u64>,
+        problem: Box<ApiProblem>,
+    },
+    #[error("authorization revoked or invalid: {0}")]
+    Unauthorized(Box<ApiProblem>),
+    #[error("duplicate content: {0}")]
+    DuplicateContent(Box<ApiProblem>),
+    #[error("account suspended or locked: {0}")]
+    Suspended(Box<ApiProblem>),
+    #[error("twitter api error ({status}): {problem}")]
+    Api {
+        status: StatusCode,
+        problem: Box<ApiProblem>,
+    },
+    #[error("invalid tweet: {0}")]
+    InvalidTweet(String),
+    #[error("failed to encode request: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error("failed to decode response: {message}, body: {body}")]
+    Decode { message: String, body: String },
+    #[error("oauth request failed: {0}")]
+    OAuth(#[from] reqwest_oauth1::Error),
+    #[error("oauth signing failed: {0}")]
+    Signer(#[from] reqwest_oauth1::SignerError),
+    #[error("oauth2 refresh token revoked: {0}")]
+    RefreshRevoked(String),
+    #[error("oauth2 is not configured")]
+    OAuth2NotConfigured,
+    #[error("http request failed: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+impl TwitterError {
+    /// Errors after which retrying with the same credentials is pointless.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            TwitterError::Unauthorized(_)
+                | TwitterError::Suspended(_)
+                | TwitterError::RefreshRevoked(_)
+        )
+    }
+
+    pub fn decode(message: impl ToString, body: impl Into<String>) -> Self {
+        TwitterError::Decode {
+            message: message.to_string(),
+            body: body.into(),
+        }
+    }
+
+    /// Classifies a non-success response from either the v2 or v1.1 API.
+    pub fn from_response(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        let problem = Box::new(ApiProblem::parse(body));
+
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || problem.has_code(V1_RATE_LIMIT_EXCEEDED)
+            || problem.has_type(V2_USAGE_CAPPED)
+        {
+            let reset_at = header_u64(headers, "x-rate-limit-reset");
+            return TwitterError::RateLimited { reset_at, problem };
+        }
+        if problem.has_code(V1_ACCOUNT_SUSPENDED) || problem.has_code(V1_ACCOUNT_LOCKED) {
+            return TwitterError::Suspended(problem);
+        }
+        if problem.has_code(V1_DUPLICATE_STATUS)
+            || problem.detail.as_deref() == Some(V2_DUPLICATE_DETAIL)
+        {
+            return TwitterError::DuplicateContent(problem);
+        }
+        if status == StatusCode::UNAUTHORIZED
+            || problem.has_code(V1_COULD_NOT_AUTHENTICATE)
+            || problem.has_code(V1_INVALID_OR_EXPIRED_TOKEN)
+            || problem.has_code(V1_BAD_AUTHENTICATION_DATA)
+        {
+            return TwitterError::Unauthorized(problem);
+        }
+        TwitterError::Api { status, problem }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ApiErrorEntry {
+    pub code: Option<u32>,
+    pub message: Option<String>,
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// Union of the v2 problem JSON (`title`/`detail`/`type`/`errors`) and the
+/// v1.1 `errors[{code, message}]` shapes.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ApiProblem {
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub errors: Vec<ApiErrorEntry>,
+    /// Raw body, kept for bodies that are not JSON at all (e.g. OAuth endpoints).
+    #[serde(skip)]
+    pub body: String,
+}
+
+impl ApiProblem {
+    pub fn parse(body: &str) -> Self {
+        let mut problem: ApiProblem = serde_json::from_str(body).unwrap_or_default();
+        problem.body = body.to_string();
+        problem
+    }
+
+    fn has_code(&self, code: u32) -> bool {
+        self.errors.iter().any(|e| e.code == Some(code))
+    }
+
+    fn has_type(&self, kind: &str) -> bool {
+        self.kind.as_deref() == Some(kind)
+            || self.errors.iter().any(|e| e.kind.as_deref() == Some(kind))
+    }
+}
+
+impl std::fmt::Display for ApiProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(detail) = self.detail.as_ref().or(self.title.as_ref()) {
+            return write!(f, "{}", detail);
+        }
+        let messages: Vec<String> = self
+            .errors
+            .iter()
+            .map(
+                |e| match (e.code, e.message.as_ref().or(e.detail.as_ref())) {
+                    (Some(code), Some(message)) => format!("{} (code {})", message, code),
+                    (None, Some(message)) => message.clone(),
+                    (Some(code), None) => format!("code {}", code),
+                    (None, None) => String::new(),
+                },
+            )
+            .collect();
+        if messages.is_empty() {
+            write!(f, "{}", self.body)
+        } else {
+            write!(f, "{}", messages.join("; "))
+        }
+    }
+}
+
+/// Turns a response into its body text, or a classified error on non-2xx.
+pub async fn response_text(resp: reqwest::Response) -> Result<String, TwitterError> {
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = resp.text().await?;
+    if !status.is_success() {
+        log::error!("Twitter request failed with status {}: {}", status, body);
+        return Err(TwitterError::from_response(status, &headers, &body));
+    }
+    Ok(body)
+}
+
+/// Decodes a successful response body as JSON.
+pub fn decode_json<T: serde::de::DeserializeOwned>(body: String) -> Result<T, TwitterError> {
+    serde_json::from_str(&body).map_err(|e| TwitterError::decode(e, body))
+}
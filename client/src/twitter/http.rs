@@ -0,0 +1,90 @@
+use oauth1_request::signature_method::hmac_sha1::HmacSha1;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    multipart::Form,
+    Response,
+};
+use reqwest_oauth1::{Client, Secrets, Signer};
+
+use super::{error::TwitterError, oauth2::OAuth2Session};
+
+pub type OAuthRequestBuilder<'a> =
+    reqwest_oauth1::RequestBuilder<Signer<'a, Secrets<'a>, HmacSha1>>;
+
+/// An HTTP client that signs requests as the user, with either OAuth 1.0a or
+/// an OAuth 2.0 bearer token.
+pub enum TwitterHttpClient<'a> {
+    OAuth1(Client<Signer<'a, Secrets<'a>, HmacSha1>>),
+    OAuth2 {
+        client: reqwest::Client,
+        session: OAuth2Session,
+    },
+}
+
+impl<'a> TwitterHttpClient<'a> {
+    pub fn get(&self, url: String) -> TwitterRequest<'a> {
+        match self {
+            TwitterHttpClient::OAuth1(client) => TwitterRequest::OAuth1(client.get(url)),
+            TwitterHttpClient::OAuth2 { client, session } => {
+                TwitterRequest::OAuth2(client.get(url).bearer_auth(session.access_token()))
+            }
+        }
+    }
+
+    pub fn post(&self, url: String) -> TwitterRequest<'a> {
+        match self {
+            TwitterHttpClient::OAuth1(client) => TwitterRequest::OAuth1(client.post(url)),
+            TwitterHttpClient::OAuth2 { client, session } => {
+                TwitterRequest::OAuth2(client.post(url).bearer_auth(session.access_token()))
+            }
+        }
+    }
+
+    pub fn delete(&self, url: String) -> TwitterRequest<'a> {
+        match self {
+            TwitterHttpClient::OAuth1(client) => TwitterRequest::OAuth1(client.delete(url)),
+            TwitterHttpClient::OAuth2 { client, session } => {
+                TwitterRequest::OAuth2(client.delete(url).bearer_auth(session.access_token()))
+            }
+        }
+    }
+}
+
+/// A request under construction, signed when sent. Short-lived, so the size
+/// difference between the variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
+pub enum TwitterRequest<'a> {
+    OAuth1(OAuthRequestBuilder<'a>),
+    OAuth2(reqwest::RequestBuilder),
+}
+
+impl TwitterRequest<'_> {
+    pub fn header(self, key: HeaderName, value: &'static str) -> Self {
+        let value = HeaderValue::from_static(value);
+        match self {
+            TwitterRequest::OAuth1(builder) => TwitterRequest::OAuth1(builder.header(key, value)),
+            TwitterRequest::OAuth2(builder) => TwitterRequest::OAuth2(builder.header(key, value)),
+        }
+    }
+
+    pub fn body(self, body: String) -> Self {
+        match self {
+            TwitterRequest::OAuth1(builder) => TwitterRequest::OAuth1(builder.body(body)),
+            TwitterRequest::OAuth2(builder) => TwitterRequest::OAuth2(builder.body(body)),
+        }
+    }
+
+    pub fn multipart(self, form: Form) -> Self {
+        match self {
+            TwitterRequest::OAuth1(builder) => TwitterRequest::OAuth1(builder.multipart(form)),
+            TwitterRequest::OAuth2(builder) => TwitterRequest::OAuth2(builder.multipart(form)),
+        }
+    }
+
+    pub async fn send(self) -> Result<Response, TwitterError> {
+        match self {
+            TwitterRequest::OAuth1(builder) => Ok(builder.send().await?),
+            TwitterRequest::OAuth2(builder) => Ok(builder.send().await?),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Deserialize;
+
+use super::{
+    builder::TwitterClient,
+    error::{decode_json, TwitterError},
+    rate_limit::MENTIONS_ENDPOINT,
+    timeline::{ReferencedTweet, TweetData},
+};
+
+/// Most mentions a single request returns.
+const MAX_RESULTS: u32 = 100;
+/// Pages fetched per poll; anything older is dropped rather than answered
+/// hours late.
+const MAX_PAGES: usize = 5;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Author {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+}
+
+/// A tweet mentioning the account, with what the agent needs to answer it.
+#[derive(Debug, Clone)]
+pub struct Mention {
+    pub id: String,
+    pub text: String,
+    pub conversation_id: Option<String>,
+    /// Unset if Twitter left the author out of the response.
+    pub author: Option<Author>,
+    /// The tweet this one replies to, if any and if Twitter returned it.
+    pub in_reply_to: Option<TweetData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MentionData {
+    id: String,
+    text: String,
+    author_id: Option<String>,
+    conversation_id: Option<String>,
+    #[serde(default)]
+    referenced_tweets: Vec<ReferencedTweet>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct Includes {
+    #[serde(default)]
+    users: Vec<Author>,
+    #[serde(default)]
+    tweets: Vec<TweetData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    next_token: Option<String>,
+}
+
+/// A page of mentions. `data` and `includes` are omitted when there are none.
+#[derive(Debug, Deserialize)]
+struct MentionsResponse {
+    #[serde(default)]
+    data: Vec<MentionData>,
+    #[serde(default)]
+    includes: Includes,
+    meta: Option<Meta>,
+}
+
+impl MentionsResponse {
+    fn into_mentions(self) -> impl Iterator<Item = Mention> {
+        let Includes { users, tweets } = self.includes;
+        self.data.into_iter().map(move |data| {
+            let in_reply_to = data
+                .referenced_tweets
+                .iter()
+                .find(|referenced| referenced.kind == "replied_to")
+                .and_then(|referenced| tweets.iter().find(|tweet| tweet.id == referenced.id))
+                .cloned();
+            Mention {
+                author: data
+                    .author_id
+                    .and_then(|id| users.iter().find(|user| user.id == id).cloned()),
+                id: data.id,
+                text: data.text,
+                conversation_id: data.conversation_id,
+                in_reply_to,
+            }
+        })
+    }
+}
+
+impl TwitterClient<'_> {
+    /// Mentions of `user_id` newer than `since_id`, oldest first.
+    pub async fn get_mentions(
+        &self,
+        user_id: &str,
+        since_id: Option<&str>,
+    ) -> Result<Vec<Mention>, TwitterError> {
+        let mut query = format!(
+            "max_results={}&tweet.fields=author_id,conversation_id,referenced_tweets\
+             &expansions=author_id,referenced_tweets.id&user.fields=username,name",
+            MAX_RESULTS
+        );
+        if let Some(since_id) = since_id {
+            query.push_str(&format!("&since_id={}", since_id));
+        }
+
+        let mut mentions = Vec::new();
+        let mut pagination_token: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let mut url = self.api_url(&format!("/2/users/{}/mentions?{}", user_id, query));
+            if let Some(token) = &pagination_token {
+                url.push_str(&format!("&pagination_token={}", token));
+            }
+            let resp = self
+                .send(MENTIONS_ENDPOINT, || self.client.get(url.clone()))
+                .await?;
+            let response: MentionsResponse = decode_json(resp)?;
+            pagination_token = response
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.next_token.clone());
+            mentions.extend(response.into_mentions());
+            if pagination_token.is_none() {
+                break;
+            }
+        }
+        mentions.reverse();
+        Ok(mentions)
+    }
+}
@@ -1,8 +1,16 @@
+pub mod activity;
 pub mod auth;
 pub mod builder;
+pub mod conversation;
+pub mod error;
+pub mod http;
 pub mod info;
+pub mod mentions;
+pub mod oauth2;
 pub mod post;
+pub mod rate_limit;
 pub mod react;
+pub mod timeline;
 pub mod tweet;
 
 pub fn get_callback_url(callback_base_url: String) -> String {
@@ -0,0 +1,240 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::StatusCode;
+
+use super::{
+    auth::{self, OAuth2Config, OAuth2Token},
+    builder::TwitterClient,
+    error::TwitterError,
+    http::TwitterHttpClient,
+};
+
+/// Tokens are refreshed this long before they expire.
+pub const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// How long to wait before retrying a refresh that failed transiently.
+const REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// How many times a rotated token is offered to the sink before giving up.
+const SAVE_ATTEMPTS: u32 = 3;
+const SAVE_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Persists tokens as they rotate. Twitter refresh tokens are single use, so
+/// a rotated token that isn't saved is lost on restart.
+#[async_trait::async_trait]
+pub trait OAuth2TokenSink: Send + Sync {
+    async fn save(&self, token: &OAuth2Token) -> eyre::Result<()>;
+}
+
+/// An OAuth 2.0 token plus what it takes to refresh it.
+pub struct OAuth2Session {
+    token: RwLock<OAuth2Token>,
+    /// `None` if the builder had no OAuth 2.0 client, so the token can't be
+    /// refreshed.
+    config: Option<OAuth2Config>,
+    oauth_base_url: String,
+    sink: Option<Arc<dyn OAuth2TokenSink>>,
+    /// Serializes refreshes, since each one spends the refresh token.
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+    /// Set once a refresh is rejected; every later request fails with it.
+    revoked: Mutex<Option<String>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl OAuth2Session {
+    pub fn new(token: OAuth2Token, config: Option<OAuth2Config>, oauth_base_url: String) -> Self {
+        Self {
+            token: RwLock::new(token),
+            config,
+            oauth_base_url,
+            sink: None,
+            refreshing: Arc::new(tokio::sync::Mutex::new(())),
+            revoked: Mutex::new(None),
+        }
+    }
+
+    pub fn token(&self) -> OAuth2Token {
+        self.token.read().unwrap().clone()
+    }
+
+    pub(crate) fn access_token(&self) -> String {
+        self.token.read().unwrap().access_token.clone()
+    }
+
+    /// Time until the token should be refreshed, or `None` if it never
+    /// expires or can't be refreshed.
+    fn until_refresh(&self) -> Option<Duration> {
+        self.config.as_ref()?;
+        let expires_at = self.token.read().unwrap().expires_at?;
+        let refresh_at = expires_at.saturating_sub(REFRESH_MARGIN.as_secs());
+        Some(Duration::from_secs(refresh_at.saturating_sub(now())))
+    }
+
+    fn check_revoked(&self) -> Result<(), TwitterError> {
+        match self.revoked.lock().unwrap().clone() {
+            Some(reason) => Err(TwitterError::RefreshRevoked(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces `stale_access_token` with a fresh one. Concurrent callers
+    /// holding the same stale token share a single refresh.
+    async fn refresh(&self, stale_access_token: &str) -> Result<(), TwitterError> {
+        let _refreshing = self.refreshing.lock().await;
+        self.check_revoked()?;
+        if self.access_token() != stale_access_token {
+            return Ok(());
+        }
+        let Some(config) = &self.config else {
+            return Err(TwitterError::OAuth2NotConfigured);
+        };
+        let Some(refresh_token) = self.token.read().unwrap().refresh_token.clone() else {
+            return Err(self.revoke("no refresh token; was offline.access granted?"));
+        };
+
+        let token =
+            match auth::refresh_oauth2_token(&self.oauth_base_url, config, &refresh_token).await {
+                Ok(token) => token,
+                // The token endpoint answers 400 for revoked or spent tokens
+                Err(TwitterError::Api { status, problem }) if status == StatusCode::BAD_REQUEST => {
+                    return Err(self.revoke(&problem.to_string()))
+                }
+                Err(TwitterError::Unauthorized(problem)) => {
+                    return Err(self.revoke(&problem.to_string()))
+                }
+                Err(e) => return Err(e),
+            };
+        *self.token.write().unwrap() = token.clone();
+        log::info!("Refreshed OAuth 2.0 access token");
+        if let Some(sink) = &self.sink {
+            self.save(sink.as_ref(), &token).await?;
+        }
+        Ok(())
+    }
+
+    /// Saves a rotated token, retrying a few times. The previous refresh
+    /// token is already spent, so a token that can't be saved is lost on
+    /// restart; the session is then revoked rather than left running on it
+    /// unnoticed.
+    async fn save(
+        &self,
+        sink: &dyn OAuth2TokenSink,
+        token: &OAuth2Token,
+    ) -> Result<(), TwitterError> {
+        let mut attempt = 1;
+        loop {
+            match sink.save(token).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < SAVE_ATTEMPTS => {
+                    log::warn!(
+                        "Failed to persist refreshed OAuth 2.0 token, retrying: {}",
+                        e
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(SAVE_RETRY_INTERVAL).await;
+                }
+                Err(e) => {
+                    return Err(self.revoke(&format!("failed to persist rotated token: {}", e)))
+                }
+            }
+        }
+    }
+
+    fn revoke(&self, reason: &str) -> TwitterError {
+        log::error!("OAuth 2.0 session revoked: {}", reason);
+        *self.revoked.lock().unwrap() = Some(reason.to_string());
+        TwitterError::RefreshRevoked(reason.to_string())
+    }
+}
+
+impl TwitterClient<'_> {
+    fn oauth2_session(&self) -> Option<&OAuth2Session> {
+        match &self.client {
+            TwitterHttpClient::OAuth2 { session, .. } => Some(session),
+            TwitterHttpClient::OAuth1(_) => None,
+        }
+    }
+
+    /// Saves every refreshed token to `sink`.
+    pub fn with_token_sink(mut self, sink: Arc<dyn OAuth2TokenSink>) -> Self {
+        if let TwitterHttpClient::OAuth2 { session, .. } = &mut self.client {
+            session.sink = Some(sink);
+        }
+        self
+    }
+
+    /// The current OAuth 2.0 token, if this client uses one.
+    pub fn oauth2_token(&self) -> Option<OAuth2Token> {
+        self.oauth2_session().map(OAuth2Session::token)
+    }
+
+    /// Refreshes the token if it is about to expire. A no-op for OAuth 1.0a.
+    pub(crate) async fn ensure_fresh_token(&self) -> Result<(), TwitterError> {
+        let Some(session) = self.oauth2_session() else {
+            return Ok(());
+        };
+        session.check_revoked()?;
+        if session.until_refresh().is_some_and(|wait| wait.is_zero()) {
+            session.refresh(&session.access_token()).await?;
+        }
+        Ok(())
+    }
+
+    /// Refreshes after a 401 on `access_token`. Returns whether the request
+    /// is worth retrying.
+    pub(crate) async fn refresh_after_unauthorized(
+        &self,
+        access_token: Option<&str>,
+    ) -> Result<bool, TwitterError> {
+        match (self.oauth2_session(), access_token) {
+            (Some(session), Some(access_token)) if session.config.is_some() => {
+                session.refresh(access_token).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Held for the whole of each OAuth 2.0 refresh, from spending the
+    /// refresh token to saving its replacement. Whoever stops this client's
+    /// agent takes it first, so a refresh is never cut off halfway. `None`
+    /// for OAuth 1.0a clients.
+    pub fn oauth2_refresh_lock(&self) -> Option<Arc<tokio::sync::Mutex<()>>> {
+        self.oauth2_session()
+            .map(|session| session.refreshing.clone())
+    }
+
+    pub(crate) fn current_access_token(&self) -> Option<String> {
+        self.oauth2_session().map(OAuth2Session::access_token)
+    }
+
+    /// Refreshes the OAuth 2.0 token ahead of every expiry, so requests never
+    /// wait on it. Only returns once refreshing has become impossible, with a
+    /// fatal error; never returns for OAuth 1.0a clients.
+    pub async fn keep_oauth2_token_fresh(&self) -> Result<(), TwitterError> {
+        let Some(session) = self.oauth2_session() else {
+            return std::future::pending().await;
+        };
+        loop {
+            let Some(wait) = session.until_refresh() else {
+                return std::future::pending().await;
+            };
+            tokio::time::sleep(wait).await;
+            match self.ensure_fresh_token().await {
+                Ok(()) => {}
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => {
+                    log::warn!("OAuth 2.0 refresh failed, retrying: {}", e);
+                    tokio::time::sleep(REFRESH_RETRY_INTERVAL).await;
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use serde::Deserialize;
 
-use super::{builder::TwitterClient, tweet::Tweet};
+use super::{
+    builder::TwitterClient,
+    error::{decode_json, TwitterError},
+    rate_limit::{DELETE_TWEET_ENDPOINT, MEDIA_UPLOAD_ENDPOINT, TWEETS_ENDPOINT},
+    tweet::{validate_tweet_id, Thread, Tweet},
+};
 
 #[derive(Debug, Deserialize)]
 struct SendTweetData {
@@ -14,6 +19,16 @@ struct SendTweetResponse {
     data: SendTweetData,
 }
 
+#[derive(Debug, Deserialize)]
+struct DeleteTweetData {
+    deleted: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteTweetResponse {
+    data: DeleteTweetData,
+}
+
 #[derive(Deserialize, Debug)]
 struct MediaUploadResponse {
     // media_data: String,
@@ -21,66 +36,108 @@ struct MediaUploadResponse {
 }
 
 impl TwitterClient<'_> {
-    pub async fn raw_tweet(&self, tweet: Tweet) -> eyre::Result<String> {
+    pub async fn raw_tweet(&self, tweet: Tweet) -> Result<String, TwitterError> {
         tweet.validate()?;
         let body = serde_json::to_string(&tweet)?;
         let resp = self
-            .client
-            .post("https://api.twitter.com/2/tweets".to_string())
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(body)
-            .send()
+            .send(TWEETS_ENDPOINT, || {
+                self.client
+                    .post(self.api_url("/2/tweets"))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+            })
             .await?;
 
-        let body = resp.text().await?;
+        let response: SendTweetResponse = decode_json(resp)?;
+        log::info!("Tweet response: {:?}", response);
+        self.activity
+            .lock()
+            .unwrap()
+            .tweets
+            .insert(response.data.id.clone());
+        Ok(response.data.id)
+    }
 
-        let tweet_response: Result<SendTweetResponse, _> = serde_json::from_str(&body);
-        match tweet_response {
-            Ok(response) => {
-                log::info!("Tweet response: {:?}", response);
-                Ok(response.data.id)
+    /// Posts each part of `thread` as a reply to the one before, and returns
+    /// their IDs in order. If a part fails, the ones already posted are
+    /// deleted before its error is returned.
+    pub async fn post_thread(&self, thread: Thread) -> Result<Vec<String>, TwitterError> {
+        thread.validate()?;
+        let mut ids: Vec<String> = Vec::new();
+        for part in thread.parts() {
+            let mut tweet = Tweet::new(part.clone());
+            if let Some(previous) = ids.last().map(String::as_str).or(thread.reply_tweet_id()) {
+                tweet.set_reply_tweet_id(previous.to_string());
+            }
+            match self.raw_tweet(tweet).await {
+                Ok(id) => ids.push(id),
+                Err(e) => {
+                    self.roll_back(&ids).await;
+                    return Err(e);
+                }
             }
-            Err(e) => {
-                log::error!("Failed to decode tweet response: {:?}, body: {}", e, body);
-                Err(eyre::eyre!("Failed to decode tweet response"))
+        }
+        Ok(ids)
+    }
+
+    /// Deletes the posted parts of a thread, newest first. Best effort: a
+    /// part that can't be deleted is logged and left up.
+    async fn roll_back(&self, ids: &[String]) {
+        for id in ids.iter().rev() {
+            match self.delete_tweet(id).await {
+                Ok(()) => log::info!("Deleted tweet {} of an unfinished thread", id),
+                Err(e) => log::error!(
+                    "Failed to delete tweet {} of an unfinished thread: {}",
+                    id,
+                    e
+                ),
             }
         }
     }
 
+    pub async fn delete_tweet(&self, tweet_id: &str) -> Result<(), TwitterError> {
+        validate_tweet_id(tweet_id)?;
+        let url = self.api_url(&format!("/2/tweets/{}", tweet_id));
+        let resp = self
+            .send(DELETE_TWEET_ENDPOINT, || self.client.delete(url.clone()))
+            .await?;
+        let response: DeleteTweetResponse = decode_json(resp.clone())?;
+        if !response.data.deleted {
+            return Err(TwitterError::decode("tweet was not deleted", resp));
+        }
+        let mut activity = self.activity.lock().unwrap();
+        if activity.tweets.remove(tweet_id) {
+            activity.deleted_tweets.insert(tweet_id.to_string());
+        }
+        Ok(())
+    }
+
     pub async fn upload_media(
         &self,
         media_bytes: Vec<u8>,
         additional_owners: Option<Vec<String>>,
-    ) -> eyre::Result<String> {
-        let mut form = reqwest::multipart::Form::new()
-            .part("media", reqwest::multipart::Part::bytes(media_bytes));
-        if let Some(additional_owners) = additional_owners {
-            form = form.text("additional_owners", additional_owners.join(","));
-        }
+    ) -> Result<String, TwitterError> {
+        // Multipart forms can't be cloned, so rebuild it for every attempt
+        let form = || {
+            let mut form = reqwest::multipart::Form::new().part(
+                "media",
+                reqwest::multipart::Part::bytes(media_bytes.clone()),
+            );
+            if let Some(additional_owners) = &additional_owners {
+                form = form.text("additional_owners", additional_owners.join(","));
+            }
+            form
+        };
         let resp = self
-            .client
-            .post("https://upload.twitter.com/1.1/media/upload.json".to_string())
-            .multipart(form)
-            .send()
+            .send(MEDIA_UPLOAD_ENDPOINT, || {
+                self.client
+                    .post(self.upload_url("/1.1/media/upload.json"))
+                    .multipart(form())
+            })
             .await?;
-        let body = resp.text().await?;
-        let media_upload_response: Result<MediaUploadResponse, _> = serde_json::from_str(&body);
-        match media_upload_response {
-            Ok(response) => {
-                log::info!("Media upload response: {:?}", response);
-                Ok(response.media_id_string)
-            }
-            Err(e) => {
-                log::error!(
-                    "Failed to decode media upload response: {:?}, body: {}",
-                    e,
-                    body
-                );
-                Err(eyre::eyre!("Failed to decode media upload response"))
-            }
-        }
-        // let media_upload_response: MediaUploadResponse = resp.json().await?;
-        // Ok(media_upload_response.media_id_string)
+        let response: MediaUploadResponse = decode_json(resp)?;
+        log::info!("Media upload response: {:?}", response);
+        Ok(response.media_id_string)
     }
 
     // pub async fn tweet(&self, tweet: String) -> eyre::Result<String> {
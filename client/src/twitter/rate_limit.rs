@@ -0,0 +1,200 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::Serialize;
+
+use crate::metrics::metrics;
+
+use super::{
+    builder::TwitterClient,
+    error::{response_text, TwitterError},
+    http::TwitterRequest,
+};
+
+/// How many times a 429 is retried after waiting for the window to reset.
+const MAX_RATE_LIMIT_RETRIES: usize = 2;
+/// Twitter rate-limit windows are 15 minutes; used when no reset time is known.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+pub const TWEETS_ENDPOINT: &str = "POST /2/tweets";
+pub const DELETE_TWEET_ENDPOINT: &str = "DELETE /2/tweets/:id";
+pub const USERS_ME_ENDPOINT: &str = "GET /2/users/me";
+pub const LIKES_ENDPOINT: &str = "POST /2/users/:id/likes";
+pub const RETWEETS_ENDPOINT: &str = "POST /2/users/:id/retweets";
+pub const MEDIA_UPLOAD_ENDPOINT: &str = "POST /1.1/media/upload.json";
+pub const USER_TWEETS_ENDPOINT: &str = "GET /2/users/:id/tweets";
+pub const LIKED_TWEETS_ENDPOINT: &str = "GET /2/users/:id/liked_tweets";
+pub const MENTIONS_ENDPOINT: &str = "GET /2/users/:id/mentions";
+pub const TWEET_LOOKUP_ENDPOINT: &str = "GET /2/tweets/:id";
+
+/// The budget Twitter reported for one endpoint on its last response.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitBudget {
+    pub limit: Option<u32>,
+    pub remaining: u32,
+    /// Unix timestamp at which `remaining` resets to `limit`.
+    pub reset_at: u64,
+}
+
+impl RateLimitBudget {
+    /// Counts that don't fit a `u32` are treated as missing.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header_u32 = |name| header_u64(headers, name).and_then(|n| u32::try_from(n).ok());
+        Some(Self {
+            limit: header_u32("x-rate-limit-limit"),
+            remaining: header_u32("x-rate-limit-remaining")?,
+            reset_at: header_u64(headers, "x-rate-limit-reset")?,
+        })
+    }
+
+    /// Time left until the window resets, zero if it already has.
+    pub fn until_reset(&self) -> Duration {
+        until(self.reset_at)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0 && !self.until_reset().is_zero()
+    }
+}
+
+/// Per-endpoint record of the `x-rate-limit-*` headers seen so far.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<&'static str, RateLimitBudget>>,
+}
+
+impl RateLimiter {
+    pub fn budget(&self, endpoint: &str) -> Option<RateLimitBudget> {
+        self.buckets.lock().unwrap().get(endpoint).copied()
+    }
+
+    pub fn budgets(&self) -> HashMap<&'static str, RateLimitBudget> {
+        self.buckets.lock().unwrap().clone()
+    }
+
+    /// Keeps the budget from a response's `x-rate-limit-*` headers, if it
+    /// has them.
+    pub fn record(&self, endpoint: &'static str, headers: &HeaderMap) {
+        if let Some(budget) = RateLimitBudget::from_headers(headers) {
+            log::debug!("Rate limit for {}: {:?}", endpoint, budget);
+            self.buckets.lock().unwrap().insert(endpoint, budget);
+        }
+    }
+
+    /// Sleeps until the endpoint's window resets if its bucket is empty.
+    pub async fn wait_for_budget(&self, endpoint: &str) {
+        let Some(budget) = self.budget(endpoint) else {
+            return;
+        };
+        if budget.is_exhausted() {
+            let wait = budget.until_reset() + Duration::from_secs(1);
+            log::warn!("Rate limit for {} exhausted, waiting {:?}", endpoint, wait);
+            metrics().record_rate_limit_wait(endpoint, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+pub(crate) fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn until(reset_at: u64) -> Duration {
+    Duration::from_secs(reset_at.saturating_sub(now()))
+}
+
+/// How long to back off after a rate limit, falling back to a full window
+/// when no reset time was reported.
+pub fn backoff(reset_at: Option<u64>) -> Duration {
+    reset_at.map_or(DEFAULT_WINDOW, |reset_at| {
+        until(reset_at) + Duration::from_secs(1)
+    })
+}
+
+impl<'a> TwitterClient<'a> {
+    /// Sends the request produced by `build`, waiting out exhausted buckets,
+    /// retrying 429s once the window resets and retrying a 401 once after
+    /// refreshing an OAuth 2.0 token. `build` is called per attempt so every
+    /// retry carries a fresh OAuth signature or the current bearer token.
+    pub(crate) async fn send<F>(
+        &self,
+        endpoint: &'static str,
+        build: F,
+    ) -> Result<String, TwitterError>
+    where
+        F: Fn() -> TwitterRequest<'a>,
+    {
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            self.rate_limits.wait_for_budget(endpoint).await;
+            self.ensure_fresh_token().await?;
+            let access_token = self.current_access_token();
+            let resp = match build().send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    metrics().record_twitter_request(endpoint, None);
+                    return Err(e);
+                }
+            };
+            metrics().record_twitter_request(endpoint, Some(resp.status()));
+            self.rate_limits.record(endpoint, resp.headers());
+
+            // An OAuth 2.0 token can expire or be invalidated early
+            if resp.status() == StatusCode::UNAUTHORIZED
+                && !refreshed
+                && self
+                    .refresh_after_unauthorized(access_token.as_deref())
+                    .await?
+            {
+                refreshed = true;
+                continue;
+            }
+
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RATE_LIMIT_RETRIES {
+                attempt += 1;
+                let wait = backoff(header_u64(resp.headers(), "x-rate-limit-reset"));
+                log::warn!(
+                    "Got 429 from {}, retrying in {:?} (attempt {}/{})",
+                    endpoint,
+                    wait,
+                    attempt,
+                    MAX_RATE_LIMIT_RETRIES
+                );
+                metrics().record_rate_limit_wait(endpoint, wait);
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+
+            return response_text(resp).await;
+        }
+    }
+
+    /// The last known rate-limit budget for `endpoint` (one of the
+    /// `*_ENDPOINT` constants in this module).
+    pub fn rate_limit_budget(&self, endpoint: &str) -> Option<RateLimitBudget> {
+        self.rate_limits.budget(endpoint)
+    }
+
+    /// Every budget recorded so far, keyed by endpoint.
+    pub fn rate_limit_budgets(&self) -> HashMap<&'static str, RateLimitBudget> {
+        self.rate_limits.budgets()
+    }
+
+    /// The live budgets, for watching them after the client moves into an
+    /// agent.
+    pub fn rate_limiter(&self) -> Arc<RateLimiter> {
+        self.rate_limits.clone()
+    }
+}
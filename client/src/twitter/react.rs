@@ -1,6 +1,11 @@
 use serde::Serialize;
 
-use super::builder::TwitterClient;
+use super::{
+    builder::TwitterClient,
+    error::TwitterError,
+    rate_limit::{LIKES_ENDPOINT, RETWEETS_ENDPOINT},
+    tweet::validate_tweet_id,
+};
 
 #[derive(Debug, Serialize)]
 struct LikeTweet {
@@ -8,25 +13,35 @@ struct LikeTweet {
 }
 
 impl TwitterClient<'_> {
-    pub async fn like(&self, x_id: String, tweet_id: String) -> eyre::Result<()> {
-        let _ = self
-            .client
-            .post(format!("https://api.twitter.com/2/users/{}/likes", x_id))
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(serde_json::to_string(&LikeTweet { tweet_id })?)
-            .send()
-            .await?;
+    pub async fn like(&self, x_id: String, tweet_id: String) -> Result<(), TwitterError> {
+        validate_tweet_id(&tweet_id)?;
+        let body = serde_json::to_string(&LikeTweet {
+            tweet_id: tweet_id.clone(),
+        })?;
+        self.send(LIKES_ENDPOINT, || {
+            self.client
+                .post(self.api_url(&format!("/2/users/{}/likes", x_id)))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+        })
+        .await?;
+        self.activity.lock().unwrap().likes.insert(tweet_id);
         Ok(())
     }
 
-    pub async fn retweet(&self, x_id: String, tweet_id: String) -> eyre::Result<()> {
-        let _ = self
-            .client
-            .post(format!("https://api.twitter.com/2/users/{}/retweets", x_id))
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(serde_json::to_string(&LikeTweet { tweet_id })?)
-            .send()
-            .await?;
+    pub async fn retweet(&self, x_id: String, tweet_id: String) -> Result<(), TwitterError> {
+        validate_tweet_id(&tweet_id)?;
+        let body = serde_json::to_string(&LikeTweet {
+            tweet_id: tweet_id.clone(),
+        })?;
+        self.send(RETWEETS_ENDPOINT, || {
+            self.client
+                .post(self.api_url(&format!("/2/users/{}/retweets", x_id)))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+        })
+        .await?;
+        self.activity.lock().unwrap().retweets.insert(tweet_id);
         Ok(())
     }
 }
@@ -0,0 +1,71 @@
+use serde::Deserialize;
+
+use super::{
+    builder::TwitterClient,
+    error::{decode_json, TwitterError},
+    rate_limit::{LIKED_TWEETS_ENDPOINT, USER_TWEETS_ENDPOINT},
+};
+
+/// Most tweets a single timeline request returns.
+const MAX_RESULTS: u32 = 100;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReferencedTweet {
+    /// `retweeted`, `quoted` or `replied_to`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TweetData {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub referenced_tweets: Vec<ReferencedTweet>,
+}
+
+impl TweetData {
+    /// The ID of the retweeted tweet, if this is a retweet.
+    pub fn retweeted_id(&self) -> Option<&str> {
+        self.referenced_tweets
+            .iter()
+            .find(|referenced| referenced.kind == "retweeted")
+            .map(|referenced| referenced.id.as_str())
+    }
+}
+
+/// A page of tweets. `data` is omitted when there are none.
+#[derive(Debug, Deserialize)]
+struct TweetListResponse {
+    #[serde(default)]
+    data: Vec<TweetData>,
+}
+
+impl TwitterClient<'_> {
+    /// The account's most recent tweets, retweets and replies, newest first.
+    pub async fn get_user_tweets(&self, user_id: &str) -> Result<Vec<TweetData>, TwitterError> {
+        let url = self.api_url(&format!(
+            "/2/users/{}/tweets?max_results={}&tweet.fields=referenced_tweets",
+            user_id, MAX_RESULTS
+        ));
+        let resp = self
+            .send(USER_TWEETS_ENDPOINT, || self.client.get(url.clone()))
+            .await?;
+        let response: TweetListResponse = decode_json(resp)?;
+        Ok(response.data)
+    }
+
+    /// The tweets the account most recently liked, newest first.
+    pub async fn get_liked_tweets(&self, user_id: &str) -> Result<Vec<TweetData>, TwitterError> {
+        let url = self.api_url(&format!(
+            "/2/users/{}/liked_tweets?max_results={}",
+            user_id, MAX_RESULTS
+        ));
+        let resp = self
+            .send(LIKED_TWEETS_ENDPOINT, || self.client.get(url.clone()))
+            .await?;
+        let response: TweetListResponse = decode_json(resp)?;
+        Ok(response.data)
+    }
+}
@@ -1,5 +1,58 @@
 use serde::Serialize;
 
+use super::error::TwitterError;
+
+/// Longest tweet text, in weighted characters, accepted by `Tweet::validate`.
+pub const MAX_TWEET_LENGTH: usize = 280;
+
+/// What a link counts for once Twitter wraps it in t.co.
+const URL_LENGTH: usize = 23;
+
+/// Code points weighing one character; everything else, e.g. CJK and emoji,
+/// weighs two. From twitter-text's v3 config.
+const LIGHT_RANGES: &[(u32, u32)] = &[(0, 4351), (8192, 8205), (8208, 8223), (8242, 8247)];
+
+fn char_weight(c: char) -> usize {
+    let light = LIGHT_RANGES
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&(c as u32)));
+    if light {
+        1
+    } else {
+        2
+    }
+}
+
+/// Length of `text` as Twitter counts it against `MAX_TWEET_LENGTH`. Emoji
+/// sequences count per code point, which errs on the long side.
+pub fn weighted_length(text: &str) -> usize {
+    text.split_inclusive(char::is_whitespace)
+        .map(|chunk| {
+            let word = chunk.trim_end();
+            let is_url = ["http://", "https://"]
+                .iter()
+                .any(|scheme| word.len() > scheme.len() && word.starts_with(scheme));
+            if is_url {
+                URL_LENGTH + chunk[word.len()..].chars().map(char_weight).sum::<usize>()
+            } else {
+                chunk.chars().map(char_weight).sum()
+            }
+        })
+        .sum()
+}
+
+/// Tweet IDs are decimal snowflakes; anything else would only fail at the API
+/// after other side effects (e.g. a media upload) have already happened.
+pub fn validate_tweet_id(tweet_id: &str) -> Result<(), TwitterError> {
+    if tweet_id.is_empty() || !tweet_id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TwitterError::InvalidTweet(format!(
+            "Invalid tweet ID: {:?}",
+            tweet_id
+        )));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Serialize)]
 struct Reply {
     in_reply_to_tweet_id: String,
@@ -29,16 +82,29 @@ impl Tweet {
         }
     }
 
-    pub fn validate(&self) -> eyre::Result<()> {
+    pub fn validate(&self) -> Result<(), TwitterError> {
+        let invalid = |msg: &str| Err(TwitterError::InvalidTweet(msg.to_string()));
         if self.text.is_empty() {
-            eyre::bail!("Tweet text cannot be empty");
+            return invalid("Tweet text cannot be empty");
+        }
+        if weighted_length(&self.text) > MAX_TWEET_LENGTH {
+            return invalid(&format!(
+                "Tweet text cannot be longer than {} characters, counting CJK and emoji twice",
+                MAX_TWEET_LENGTH
+            ));
+        }
+        if let Some(quote_tweet_id) = &self.quote_tweet_id {
+            validate_tweet_id(quote_tweet_id)?;
+        }
+        if let Some(reply) = &self.reply {
+            validate_tweet_id(&reply.in_reply_to_tweet_id)?;
         }
         if self.quote_tweet_id.is_some() && self.reply.is_some() {
-            eyre::bail!("Tweet cannot be both a quote and a reply");
+            return invalid("Tweet cannot be both a quote and a reply");
         }
         if let Some(media) = &self.media {
             if media.media_ids.is_empty() {
-                eyre::bail!("Media IDs cannot be empty");
+                return invalid("Media IDs cannot be empty");
             }
         }
         Ok(())
@@ -58,3 +124,113 @@ impl Tweet {
         self.media = Some(Media { media_ids });
     }
 }
+
+/// Text too long for one tweet, split into parts posted as replies to each
+/// other.
+#[derive(Debug, Clone)]
+pub struct Thread {
+    parts: Vec<String>,
+    reply: Option<String>,
+}
+
+impl Thread {
+    /// Splits `text` between sentences, or between words or within them if a
+    /// sentence or word doesn't fit in a tweet on its own.
+    pub fn split(text: &str) -> Result<Self, TwitterError> {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let fits = |text: &str| weighted_length(text.trim_end()) <= MAX_TWEET_LENGTH;
+        let mut add = |piece: &str| {
+            if !fits(&format!("{}{}", current, piece)) {
+                parts.push(current.trim().to_string());
+                current.clear();
+            }
+            current.push_str(piece);
+        };
+        for sentence in sentences(text.trim()) {
+            if fits(sentence) {
+                add(sentence);
+                continue;
+            }
+            for word in sentence.split_inclusive(char::is_whitespace) {
+                if fits(word) {
+                    add(word);
+                    continue;
+                }
+                for piece in hard_split(word) {
+                    add(piece);
+                }
+            }
+        }
+        parts.push(current.trim().to_string());
+        parts.retain(|part| !part.is_empty());
+        if parts.is_empty() {
+            return Err(TwitterError::InvalidTweet(
+                "Thread text cannot be empty".to_string(),
+            ));
+        }
+        Ok(Self { parts, reply: None })
+    }
+
+    pub fn parts(&self) -> &[String] {
+        &self.parts
+    }
+
+    /// Starts the thread as a reply to `reply_tweet_id`.
+    pub fn set_reply_tweet_id(&mut self, reply_tweet_id: String) {
+        self.reply = Some(reply_tweet_id);
+    }
+
+    pub fn reply_tweet_id(&self) -> Option<&str> {
+        self.reply.as_deref()
+    }
+
+    /// Checks every part, so nothing is posted if any would be rejected.
+    pub fn validate(&self) -> Result<(), TwitterError> {
+        if let Some(reply) = &self.reply {
+            validate_tweet_id(reply)?;
+        }
+        self.parts
+            .iter()
+            .try_for_each(|part| Tweet::new(part.clone()).validate())
+    }
+}
+
+/// `text` cut after sentence-ending punctuation and line breaks, keeping the
+/// whitespace that follows.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for chunk in text.split_inclusive(char::is_whitespace) {
+        end += chunk.len();
+        let word = chunk
+            .trim_end()
+            .trim_end_matches(['"', '\'', ')', '”', '’']);
+        if chunk.ends_with('\n') || word.ends_with(['.', '!', '?', '…']) {
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+/// `word` in pieces that each fit in a tweet.
+fn hard_split(word: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut length = 0;
+    for (i, c) in word.char_indices() {
+        length += char_weight(c);
+        if length > MAX_TWEET_LENGTH {
+            pieces.push(&word[start..i]);
+            start = i;
+            length = char_weight(c);
+        }
+    }
+    pieces.push(&word[start..]);
+    pieces
+}
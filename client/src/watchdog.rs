@@ -0,0 +1,176 @@
+use std::{
+    collections::HashSet,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::twitter::{builder::TwitterClient, error::TwitterError, info::UserInfo};
+
+/// How often the account is checked for activity the agent didn't cause.
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Activity on the account that this process did not perform, meaning
+/// someone else can still act as the account.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Anomaly {
+    UnknownTweet {
+        id: String,
+        text: String,
+    },
+    UnknownLike {
+        tweet_id: String,
+    },
+    ProfileChanged {
+        field: &'static str,
+        before: String,
+        after: String,
+    },
+}
+
+/// Served at `/watchdog`. `alert` stays set once raised.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct WatchdogReport {
+    pub alert: bool,
+    /// Unix timestamp of the last completed check.
+    pub last_check: Option<u64>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// What the account looked like as of the last check.
+struct Baseline {
+    tweets: HashSet<String>,
+    likes: HashSet<String>,
+    profile: UserInfo,
+}
+
+/// Periodically compares the account's timeline, likes and profile against
+/// what `TwitterClient` itself did, and raises an alert on anything else.
+///
+/// The first check only records a baseline, so activity from before the
+/// agent took over is never reported.
+pub struct Watchdog {
+    interval: Duration,
+    baseline: tokio::sync::Mutex<Option<Baseline>>,
+    report: Mutex<WatchdogReport>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHECK_INTERVAL)
+    }
+}
+
+impl Watchdog {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            baseline: tokio::sync::Mutex::new(None),
+            report: Mutex::new(WatchdogReport::default()),
+        }
+    }
+
+    pub fn report(&self) -> WatchdogReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Runs one check and returns the anomalies it found.
+    pub async fn check(
+        &self,
+        twitter_client: &TwitterClient<'_>,
+        user_id: &str,
+    ) -> Result<Vec<Anomaly>, TwitterError> {
+        let tweets = twitter_client.get_user_tweets(user_id).await?;
+        let likes = twitter_client.get_liked_tweets(user_id).await?;
+        let profile = twitter_client.get_user_info().await?;
+        // Read after fetching, so anything the agent did meanwhile is known
+        let activity = twitter_client.activity();
+
+        let mut baseline = self.baseline.lock().await;
+        let mut anomalies = Vec::new();
+        match baseline.as_mut() {
+            None => {
+                *baseline = Some(Baseline {
+                    tweets: tweets.into_iter().map(|tweet| tweet.id).collect(),
+                    likes: likes.into_iter().map(|tweet| tweet.id).collect(),
+                    profile,
+                });
+            }
+            Some(baseline) => {
+                for tweet in tweets {
+                    if !baseline.tweets.insert(tweet.id.clone()) {
+                        continue;
+                    }
+                    let ours = activity.tweets.contains(&tweet.id)
+                        || activity.deleted_tweets.contains(&tweet.id)
+                        || tweet
+                            .retweeted_id()
+                            .is_some_and(|id| activity.retweets.contains(id));
+                    if !ours {
+                        anomalies.push(Anomaly::UnknownTweet {
+                            id: tweet.id,
+                            text: tweet.text,
+                        });
+                    }
+                }
+                for tweet in likes {
+                    if baseline.likes.insert(tweet.id.clone())
+                        && !activity.likes.contains(&tweet.id)
+                    {
+                        anomalies.push(Anomaly::UnknownLike { tweet_id: tweet.id });
+                    }
+                }
+                anomalies.extend(profile_changes(&baseline.profile, &profile));
+                baseline.profile = profile;
+            }
+        }
+
+        let mut report = self.report.lock().unwrap();
+        report.last_check = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|now| now.as_secs());
+        if !anomalies.is_empty() {
+            log::error!("Out-of-band account activity: {:?}", anomalies);
+            report.alert = true;
+            report.anomalies.extend(anomalies.iter().cloned());
+        }
+        Ok(anomalies)
+    }
+
+    /// Checks every `interval` until the account is lost.
+    pub async fn run(&self, twitter_client: &TwitterClient<'_>, user_id: &str) -> eyre::Result<()> {
+        loop {
+            match self.check(twitter_client, user_id).await {
+                Ok(_) => {}
+                Err(e) if e.is_fatal() => return Err(e.into()),
+                Err(e) => log::warn!("Watchdog check failed: {}", e),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+fn profile_changes(before: &UserInfo, after: &UserInfo) -> Vec<Anomaly> {
+    let fields = [
+        ("name", &before.name, &after.name),
+        ("username", &before.username, &after.username),
+        ("description", &before.description, &after.description),
+        (
+            "profile_image_url",
+            &before.profile_image_url,
+            &after.profile_image_url,
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(field, before, after)| Anomaly::ProfileChanged {
+            field,
+            before: before.clone(),
+            after: after.clone(),
+        })
+        .collect()
+}
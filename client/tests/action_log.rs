@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use client::{
+    action_log::{verify_chain, Action, ActionLog},
+    attestation::EnclaveKey,
+    llm::Message,
+};
+
+fn response(id: &str) -> Action {
+    Action::TwitterResponse {
+        endpoint: "POST /2/tweets".to_string(),
+        id: id.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn tampering_breaks_the_chain() {
+    let log = ActionLog::new(Arc::new(EnclaveKey::generate()));
+    for id in ["1", "2", "3"] {
+        log.record("1", response(id)).await.unwrap();
+    }
+    let entries = log.entries(0, usize::MAX).await;
+    verify_chain(&entries).unwrap();
+
+    let mut edited = entries.clone();
+    edited[1].action = response("4");
+    assert!(verify_chain(&edited).is_err());
+
+    let mut dropped = entries.clone();
+    dropped.remove(1);
+    assert!(verify_chain(&dropped).is_err());
+
+    // Entries only verify under the key that signed them
+    let mut forged = entries.clone();
+    forged[2].public_key = EnclaveKey::generate().public_key_hex();
+    assert!(verify_chain(&forged).is_err());
+
+    // The account is signed too, so entries can't be moved between accounts
+    assert_eq!(entries[0].account.as_deref(), Some("1"));
+    let mut moved = entries.clone();
+    moved[0].account = Some("2".to_string());
+    assert!(verify_chain(&moved).is_err());
+}
+
+#[tokio::test]
+async fn reopened_log_continues_the_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("actions.jsonl");
+
+    let first_run = ActionLog::open(Arc::new(EnclaveKey::generate()), &path)
+        .await
+        .unwrap();
+    first_run.record("1", response("1")).await.unwrap();
+    drop(first_run);
+
+    let second_run = ActionLog::open(Arc::new(EnclaveKey::generate()), &path)
+        .await
+        .unwrap();
+    let entry = second_run.record("1", response("2")).await.unwrap();
+    assert_eq!(entry.seq, 1);
+
+    let reopened = ActionLog::open(Arc::new(EnclaveKey::generate()), &path)
+        .await
+        .unwrap();
+    let entries = reopened.entries(0, usize::MAX).await;
+    assert_eq!(entries.len(), 2);
+    assert_ne!(entries[0].public_key, entries[1].public_key);
+
+    // A tampered file is refused on startup
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, contents.replace("\"id\":\"1\"", "\"id\":\"9\"")).unwrap();
+    assert!(ActionLog::open(Arc::new(EnclaveKey::generate()), &path)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn history_is_kept_per_account() {
+    let log = ActionLog::new(Arc::new(EnclaveKey::generate()));
+    let prompt = |mention_id: Option<&str>| Action::Prompt {
+        messages: vec![Message::user("make a tweet")],
+        mention_id: mention_id.map(str::to_string),
+    };
+    log.record("1", prompt(None)).await.unwrap();
+    log.record("1", response("10")).await.unwrap();
+    log.record("2", prompt(None)).await.unwrap();
+    log.record("2", response("20")).await.unwrap();
+    log.record("2", response("21")).await.unwrap();
+
+    let entries = log.entries(0, usize::MAX).await;
+    assert_eq!(log.last_run_at("1").await, Some(entries[0].timestamp));
+    assert_eq!(log.posted_at("1", 0).await.len(), 1);
+    assert_eq!(log.posted_at("2", 0).await.len(), 2);
+    assert!(log
+        .posted_at("2", entries[4].timestamp + 1)
+        .await
+        .is_empty());
+    assert_eq!(log.last_run_at("3").await, None);
+
+    // Answering a mention isn't a scheduled run
+    log.record("3", prompt(Some("99"))).await.unwrap();
+    assert_eq!(log.last_run_at("3").await, None);
+}
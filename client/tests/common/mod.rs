@@ -0,0 +1,28 @@
+//! Helpers shared by the integration tests. Each test binary uses only some.
+#![allow(dead_code)]
+
+use client::twitter::{
+    auth::TwitterTokenPair,
+    builder::{TwitterBuilder, TwitterClient},
+};
+use mock_twitter::MockTwitter;
+
+/// A builder pointed at `mock` for every API, with its app credentials.
+pub fn builder_for(mock: &MockTwitter) -> TwitterBuilder {
+    let config = mock.config();
+    TwitterBuilder::new(config.consumer_key.clone(), config.consumer_secret.clone())
+        .with_api_base_url(mock.base_url().to_string())
+        .with_upload_base_url(mock.base_url().to_string())
+        .with_oauth_base_url(mock.base_url().to_string())
+}
+
+/// A fresh OAuth 1.0a access token issued by `mock`.
+pub fn access_tokens(mock: &MockTwitter) -> TwitterTokenPair {
+    let (token, secret) = mock.issue_access_token();
+    TwitterTokenPair { token, secret }
+}
+
+/// A client acting as `mock`'s user.
+pub fn client_for(mock: &MockTwitter) -> TwitterClient<'static> {
+    builder_for(mock).with_auth(access_tokens(mock))
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use client::{
+    credentials::{AccountStore, CredentialStore, SealedFileStore, StoredCredentials},
+    sealing::{SealingKeyProvider, StaticKeyProvider},
+    twitter::auth::{TwitterTokenPair, UserAuth},
+};
+
+fn credentials() -> StoredCredentials {
+    StoredCredentials {
+        consumer_key: "consumer-key".to_string(),
+        consumer_secret: "consumer-secret".to_string(),
+        auth: UserAuth::OAuth1(TwitterTokenPair {
+            token: "access-token".to_string(),
+            secret: "access-secret".to_string(),
+        }),
+    }
+}
+
+#[tokio::test]
+async fn sealed_credentials_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("credentials.sealed");
+    let store = SealedFileStore::new(&path, Arc::new(StaticKeyProvider(vec![7; 32])));
+
+    assert!(store.load().await.unwrap().is_none());
+    store.save(&credentials()).await.unwrap();
+
+    // A fresh store over the same file, as after a restart
+    let store = SealedFileStore::new(&path, Arc::new(StaticKeyProvider(vec![7; 32])));
+    let loaded = store.load().await.unwrap().unwrap();
+    assert_eq!(loaded.consumer_secret, "consumer-secret");
+    let UserAuth::OAuth1(token_pair) = loaded.auth else {
+        panic!("expected OAuth 1.0a credentials");
+    };
+    assert_eq!(token_pair.token, "access-token");
+    assert_eq!(token_pair.secret, "access-secret");
+
+    let on_disk = std::fs::read(&path).unwrap();
+    let needle = b"access-secret";
+    assert!(!on_disk.windows(needle.len()).any(|w| w == needle));
+}
+
+#[tokio::test]
+async fn sealed_credentials_need_the_same_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("credentials.sealed");
+    SealedFileStore::new(&path, Arc::new(StaticKeyProvider(vec![7; 32])))
+        .save(&credentials())
+        .await
+        .unwrap();
+
+    let other_enclave = SealedFileStore::new(&path, Arc::new(StaticKeyProvider(vec![8; 32])));
+    assert!(other_enclave.load().await.is_err());
+}
+
+#[tokio::test]
+async fn credentials_from_before_oauth2_still_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("credentials.sealed");
+    let keys = StaticKeyProvider(vec![7; 32]);
+    let legacy = serde_json::json!({
+        "consumer_key": "consumer-key",
+        "consumer_secret": "consumer-secret",
+        "token_pair": { "token": "access-token", "secret": "access-secret" },
+    });
+    let sealed = keys
+        .derive_key("twitter-encumbrance/credentials/v1")
+        .unwrap()
+        .seal(legacy.to_string().as_bytes())
+        .unwrap();
+    std::fs::write(&path, sealed).unwrap();
+
+    let loaded = SealedFileStore::new(&path, Arc::new(keys))
+        .load()
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(loaded.auth, UserAuth::OAuth1(pair) if pair.secret == "access-secret"));
+}
+
+#[tokio::test]
+async fn account_store_keeps_one_file_per_account() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = AccountStore::new(dir.path(), Arc::new(StaticKeyProvider(vec![7; 32])));
+    assert!(store.load_all().await.unwrap().is_empty());
+
+    store
+        .account("111")
+        .unwrap()
+        .save(&credentials())
+        .await
+        .unwrap();
+    let mut other = credentials();
+    other.auth = UserAuth::OAuth1(TwitterTokenPair {
+        token: "other-token".to_string(),
+        secret: "other-secret".to_string(),
+    });
+    store.account("222").unwrap().save(&other).await.unwrap();
+
+    let loaded = store.load_all().await.unwrap();
+    assert_eq!(loaded.keys().collect::<Vec<_>>(), vec!["111", "222"]);
+    assert!(matches!(
+        &loaded["222"].auth,
+        UserAuth::OAuth1(pair) if pair.token == "other-token"
+    ));
+
+    // IDs never become paths outside the directory
+    assert!(store.account("../111").is_err());
+}
@@ -0,0 +1,1322 @@
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine;
+use client::{
+    accounts::{AgentConfig, Agents, AuthorizedAccount},
+    action_log::{self, Action, ActionLog},
+    attestation::{AttestationClaims, AttestationReport, EnclaveKey, MockAttestationProvider},
+    clock::{FakeClock, SystemClock},
+    event_loop,
+    llm::{ImageBackend, Message, ScriptedBackend, StaticImageBackend},
+    mentions::MentionCursor,
+    metrics,
+    server::{self, SharedState, PENDING_LOGIN_TTL},
+    settings::{
+        AgentSettings, MentionSettings, ModelSettings, PersonaSettings, QuietHours,
+        ScheduleSettings,
+    },
+    status::{AgentStatus, StatusReport},
+    twitter::{
+        auth::{OAuth2Config, OAuth2Token, Pkce, TwitterTokenPair, UserAuth},
+        builder::TwitterBuilder,
+        error::TwitterError,
+        oauth2::OAuth2TokenSink,
+        rate_limit::TWEETS_ENDPOINT,
+        tweet::Tweet,
+    },
+    watchdog::{Anomaly, Watchdog},
+};
+use common::{access_tokens, builder_for, client_for};
+use mock_twitter::{Endpoint, Failure, MockTwitter, MockUser};
+use serde_json::json;
+use tokio::{net::TcpListener, sync::mpsc::UnboundedReceiver};
+
+async fn mock_attestation() -> AttestationReport {
+    let claims = AttestationClaims {
+        binary_sha256: "00".repeat(32),
+        config_sha256: None,
+        public_key: "11".repeat(32),
+    };
+    AttestationReport::generate(&MockAttestationProvider, claims)
+        .await
+        .unwrap()
+}
+
+fn action_log() -> Arc<ActionLog> {
+    Arc::new(ActionLog::new(Arc::new(EnclaveKey::generate())))
+}
+
+fn agent_config(
+    llm: Arc<ScriptedBackend>,
+    images: Option<Arc<StaticImageBackend>>,
+    action_log: Arc<ActionLog>,
+) -> AgentConfig {
+    AgentConfig {
+        llm,
+        images: images.map(|images| images as Arc<dyn ImageBackend>),
+        action_log,
+        watchdog: Arc::new(Watchdog::default()),
+        status: Arc::new(AgentStatus::default()),
+        settings: Arc::new(AgentSettings::default()),
+        clock: Arc::new(SystemClock),
+        mention_cursor: Arc::new(MentionCursor::new()),
+    }
+}
+
+/// Keeps cookies like a browser, so `/callback` sees the session `/login` set.
+fn browser() -> reqwest::Client {
+    reqwest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[tokio::test]
+async fn login_callback_event_loop() {
+    let mock = MockTwitter::start().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tee_url = format!("http://{}", listener.local_addr().unwrap());
+    let (shared_state, mut logins) = SharedState::new(
+        tee_url.clone(),
+        builder_for(&mock),
+        mock_attestation().await,
+        action_log(),
+        Arc::new(Agents::default()),
+    );
+    let app = server::router(shared_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let attestation: serde_json::Value = reqwest::get(format!("{}/attestation", tee_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(attestation["provider"], "mock");
+    assert_eq!(attestation["claims"]["public_key"], "11".repeat(32));
+    // The mock quote embeds the report data, which commits to the claims
+    let report_data = hex::decode(attestation["report_data"].as_str().unwrap()).unwrap();
+    let quote = base64::engine::general_purpose::STANDARD
+        .decode(attestation["quote"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(
+        quote,
+        [MockAttestationProvider::QUOTE_PREFIX, &report_data].concat()
+    );
+
+    // /login -> mock /oauth/authenticate -> /callback, following redirects
+    let body = browser()
+        .get(format!("{}/login", tee_url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "Succesfully logged into Encumbered Agent");
+    let account = tokio::time::timeout(Duration::from_secs(5), logins.recv())
+        .await
+        .expect("no account after callback")
+        .unwrap();
+    assert_eq!(account.user.id, mock.config().user.id);
+    assert_eq!(mock.hits(Endpoint::AccessToken), 1);
+
+    let llm = Arc::new(ScriptedBackend::new(vec![
+        ScriptedBackend::tool_calls(vec![
+            ("tweet_joke", json!({ "joke": "gm" })),
+            ("tweet_joke", json!({ "joke": "gn" })),
+        ]),
+        Message::assistant("Posted."),
+    ]));
+
+    let twitter_client = shared_state.twitter_builder.with_user_auth(account.auth);
+    let wait_for_tweet = async {
+        while mock.tweets().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, agent_config(llm.clone(), None, action_log())) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_tweet) => {}
+    }
+
+    let tweets = mock.tweets();
+    assert_eq!(tweets.len(), 2);
+    assert_eq!(tweets[0].text, "gm");
+    assert_eq!(tweets[1].text, "gn");
+    let requests = llm.requests();
+    assert_eq!(requests[0].tools[0].name, "tweet_joke");
+    // One `tool` message per call, answering it by id
+    let tool_messages: Vec<_> = requests[1]
+        .messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .map(|m| m.tool_call_id.as_deref().unwrap())
+        .collect();
+    assert_eq!(tool_messages, vec!["call_0", "call_1"]);
+}
+
+/// Serves a login server for `mock`, with pending logins kept for `ttl`.
+async fn login_server(
+    mock: &MockTwitter,
+    ttl: Duration,
+) -> (String, SharedState, UnboundedReceiver<AuthorizedAccount>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tee_url = format!("http://{}", listener.local_addr().unwrap());
+    let (shared_state, logins) = SharedState::new(
+        tee_url.clone(),
+        builder_for(mock),
+        mock_attestation().await,
+        action_log(),
+        Arc::new(Agents::default()),
+    );
+    let shared_state = shared_state.with_login_ttl(ttl);
+    let app = server::router(shared_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (tee_url, shared_state, logins)
+}
+
+/// Walks `/login` and the mock's authorize page without following the
+/// final redirect, returning the `/callback` URL and the browser's cookies.
+async fn start_login(tee_url: &str) -> (String, reqwest::Client) {
+    let client = reqwest::Client::builder()
+        .cookie_store(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let mut url = format!("{}/login", tee_url);
+    for _ in 0..2 {
+        let resp = client.get(&url).send().await.unwrap();
+        url = resp.headers()["location"].to_str().unwrap().to_string();
+    }
+    assert!(url.starts_with(&format!("{}/callback", tee_url)));
+    (url, client)
+}
+
+#[tokio::test]
+async fn callback_rejects_bad_logins() {
+    let mock = MockTwitter::start().await;
+    let (tee_url, shared_state, _logins) = login_server(&mock, PENDING_LOGIN_TTL).await;
+    let status = |resp: reqwest::Response| resp.status().as_u16();
+
+    let resp = browser()
+        .get(format!("{}/callback", tee_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status(resp), 400);
+    let resp = browser()
+        .get(format!(
+            "{}/callback?oauth_token=forged&oauth_verifier=x",
+            tee_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status(resp), 400);
+
+    // Cancelling on Twitter forgets the login
+    mock.fail_next(Endpoint::Authenticate, Failure::Denied);
+    let resp = browser()
+        .get(format!("{}/login", tee_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status(resp), 403);
+    assert!(shared_state.pending_oauth1.is_empty());
+
+    // A callback only works in the browser that started the login, and the
+    // login is spent either way
+    let (callback_url, _) = start_login(&tee_url).await;
+    let resp = browser().get(&callback_url).send().await.unwrap();
+    assert_eq!(status(resp), 403);
+    assert!(shared_state.pending_oauth1.is_empty());
+
+    // Request tokens are single use
+    let (callback_url, client) = start_login(&tee_url).await;
+    let resp = client.get(&callback_url).send().await.unwrap();
+    assert_eq!(status(resp), 200);
+    let resp = client.get(&callback_url).send().await.unwrap();
+    assert_eq!(status(resp), 400);
+    assert_eq!(mock.hits(Endpoint::AccessToken), 1);
+
+    let (tee_url, _, _logins) = login_server(&mock, Duration::ZERO).await;
+    let (callback_url, client) = start_login(&tee_url).await;
+    let resp = client.get(&callback_url).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+    assert!(resp.text().await.unwrap().contains("expired"));
+    assert_eq!(mock.hits(Endpoint::AccessToken), 1);
+}
+#[tokio::test]
+async fn logins_are_refused_once_released() {
+    let mock = MockTwitter::start().await;
+    let (tee_url, shared_state, _logins) = login_server(&mock, PENDING_LOGIN_TTL).await;
+
+    // Agents only stop for a release, after which no account would be acted as
+    let (callback_url, client) = start_login(&tee_url).await;
+    shared_state.agents.stop_all().await;
+    let resp = client.get(&callback_url).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 410);
+    assert!(resp.text().await.unwrap().contains("released"));
+    let resp = browser()
+        .get(format!("{}/login", tee_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status().as_u16(), 410);
+    assert_eq!(mock.hits(Endpoint::AccessToken), 0);
+
+    // A login nobody receives any more is turned away too
+    let (tee_url, _, logins) = login_server(&mock, PENDING_LOGIN_TTL).await;
+    let (callback_url, client) = start_login(&tee_url).await;
+    drop(logins);
+    let resp = client.get(&callback_url).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 410);
+}
+
+fn oauth2_builder_for(mock: &MockTwitter) -> TwitterBuilder {
+    let config = mock.config();
+    builder_for(mock)
+        .with_authorize_base_url(mock.base_url().to_string())
+        .with_oauth2(
+            OAuth2Config::new(config.oauth2_client_id.clone())
+                .with_client_secret(config.oauth2_client_secret.clone()),
+        )
+}
+
+#[tokio::test]
+async fn concurrent_logins_each_start_an_agent() {
+    let mock = MockTwitter::start().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tee_url = format!("http://{}", listener.local_addr().unwrap());
+    let agents = Arc::new(Agents::default());
+    let (shared_state, mut logins) = SharedState::new(
+        tee_url.clone(),
+        builder_for(&mock),
+        mock_attestation().await,
+        action_log(),
+        agents.clone(),
+    );
+    let app = server::router(shared_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // Two logins in flight at once, finished in the opposite order
+    let jar = Arc::new(reqwest::cookie::Jar::default());
+    let no_redirects = reqwest::Client::builder()
+        .cookie_provider(jar.clone())
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let browser = reqwest::Client::builder()
+        .cookie_provider(jar)
+        .build()
+        .unwrap();
+    let mut authorize_urls = Vec::new();
+    for _ in 0..2 {
+        let resp = no_redirects
+            .get(format!("{}/login", tee_url))
+            .send()
+            .await
+            .unwrap();
+        authorize_urls.push(resp.headers()["location"].to_str().unwrap().to_string());
+    }
+    assert_eq!(shared_state.pending_oauth1.len(), 2);
+    for url in authorize_urls.iter().rev() {
+        let body = browser.get(url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, "Succesfully logged into Encumbered Agent");
+    }
+    assert!(shared_state.pending_oauth1.is_empty());
+    assert_eq!(mock.hits(Endpoint::AccessToken), 2);
+
+    let llm = Arc::new(ScriptedBackend::new(vec![
+        ScriptedBackend::tool_calls(vec![("tweet_joke", json!({ "joke": "gm" }))]),
+        Message::assistant("Posted."),
+    ]));
+    for _ in 0..2 {
+        let account = logins.recv().await.unwrap();
+        let twitter_client = shared_state.twitter_builder.with_user_auth(account.auth);
+        agents
+            .spawn(
+                account.user,
+                twitter_client,
+                agent_config(llm.clone(), None, action_log()),
+            )
+            .await;
+    }
+
+    // Both logins were the same account, so the second agent replaced the first
+    let accounts: serde_json::Value = reqwest::get(format!("{}/accounts", tee_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(accounts.as_array().unwrap().len(), 1);
+    assert_eq!(accounts[0]["username"], mock.config().user.username);
+    assert_eq!(accounts[0]["running"], true);
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while mock.tweets().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // The operator API reports on the running agent
+    let status: serde_json::Value = reqwest::get(format!("{}/status", tee_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let account = &status["accounts"][0];
+    assert_eq!(account["id"], mock.config().user.id);
+    assert_eq!(account["last_action"]["tool"], "tweet_joke");
+    assert!(account["rate_limits"][TWEETS_ENDPOINT]["remaining"].is_u64());
+    let resp = reqwest::get(format!("{}/health", tee_url)).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    let metrics = reqwest::get(format!("{}/metrics", tee_url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains(&format!(
+        "agent_up{{account=\"{}\",username=\"{}\"}} 1",
+        mock.config().user.id,
+        mock.config().user.username
+    )));
+    assert!(metrics.contains("twitter_rate_limit_remaining{"));
+    assert!(metrics.contains("tool_invocations_total{outcome=\"ok\",tool=\"tweet_joke\"}"));
+    assert!(metrics.contains("twitter_requests_total{endpoint=\"POST /2/tweets\",status=\"201\"}"));
+
+    // A stopped agent makes the server unhealthy
+    agents.stop_all().await;
+    assert!(!agents.accounts()[0].running);
+    let resp = reqwest::get(format!("{}/health", tee_url)).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 503);
+}
+
+#[tokio::test]
+async fn oauth2_pkce_login() {
+    let mock = MockTwitter::start().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tee_url = format!("http://{}", listener.local_addr().unwrap());
+    let (shared_state, mut logins) = SharedState::new(
+        tee_url.clone(),
+        oauth2_builder_for(&mock),
+        mock_attestation().await,
+        action_log(),
+        Arc::new(Agents::default()),
+    );
+    let app = server::router(shared_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // /login -> mock /i/oauth2/authorize -> /callback?code=...&state=...
+    let body = browser()
+        .get(format!("{}/login", tee_url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "Succesfully logged into Encumbered Agent");
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), 1);
+    assert_eq!(mock.hits(Endpoint::RequestToken), 0);
+
+    let Some(UserAuth::OAuth2(token)) = logins.recv().await.map(|account| account.auth) else {
+        panic!("expected an OAuth 2.0 token");
+    };
+    assert!(token.expires_at.is_some());
+    let refresh_token = token.refresh_token.clone().unwrap();
+
+    // API calls carry the bearer token
+    let builder = &shared_state.twitter_builder;
+    let client = builder.with_oauth2_token(token);
+    client
+        .raw_tweet(Tweet::new("bearer".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(mock.tweets()[0].text, "bearer");
+
+    // Refresh tokens rotate: the old one is spent once used
+    let refreshed = builder.refresh_oauth2_token(&refresh_token).await.unwrap();
+    assert_ne!(
+        refreshed.refresh_token.as_deref(),
+        Some(refresh_token.as_str())
+    );
+    assert!(builder.refresh_oauth2_token(&refresh_token).await.is_err());
+    let client = builder.with_oauth2_token(refreshed);
+    client.get_user_info().await.unwrap();
+}
+
+/// Records every token a client saves.
+#[derive(Default)]
+struct RecordingSink(std::sync::Mutex<Vec<OAuth2Token>>);
+
+#[async_trait::async_trait]
+impl OAuth2TokenSink for RecordingSink {
+    async fn save(&self, token: &OAuth2Token) -> eyre::Result<()> {
+        self.0.lock().unwrap().push(token.clone());
+        Ok(())
+    }
+}
+
+/// Fails the first `failures` saves, then records like [`RecordingSink`].
+struct FlakySink {
+    failures: std::sync::atomic::AtomicUsize,
+    saved: RecordingSink,
+}
+
+impl FlakySink {
+    fn failing(failures: usize) -> Self {
+        Self {
+            failures: failures.into(),
+            saved: RecordingSink::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuth2TokenSink for FlakySink {
+    async fn save(&self, token: &OAuth2Token) -> eyre::Result<()> {
+        let failures = &self.failures;
+        if failures.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+            failures.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            eyre::bail!("disk full");
+        }
+        self.saved.save(token).await
+    }
+}
+
+fn oauth2_token(mock: &MockTwitter, expires_in: u64) -> OAuth2Token {
+    let (access_token, refresh_token) = mock.issue_oauth2_token();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    OAuth2Token {
+        access_token,
+        refresh_token: Some(refresh_token),
+        expires_at: Some(now + expires_in),
+        scope: "tweet.read tweet.write users.read offline.access".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn oauth2_refreshes_after_unauthorized() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let sink = Arc::new(RecordingSink::default());
+    let token = oauth2_token(&mock, 7200);
+    let client = builder
+        .with_oauth2_token(token.clone())
+        .with_token_sink(sink.clone());
+
+    // Revoked server-side before the client expected it to be
+    mock.expire_oauth2_tokens();
+    client
+        .raw_tweet(Tweet::new("after refresh".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(mock.tweets()[0].text, "after refresh");
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), 1);
+
+    let saved = sink.0.lock().unwrap().clone();
+    assert_eq!(saved.len(), 1);
+    assert_ne!(saved[0].refresh_token, token.refresh_token);
+    assert_eq!(
+        client.oauth2_token().unwrap().access_token,
+        saved[0].access_token
+    );
+}
+
+#[tokio::test]
+async fn oauth2_refreshes_before_expiry() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let sink = Arc::new(RecordingSink::default());
+    // Inside the refresh margin, so refreshed before the request goes out
+    let client = builder
+        .with_oauth2_token(oauth2_token(&mock, 60))
+        .with_token_sink(sink.clone());
+
+    client.get_user_info().await.unwrap();
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), 1);
+    assert_eq!(sink.0.lock().unwrap().len(), 1);
+
+    // The fresh token is good for a while; no second refresh
+    client.get_user_info().await.unwrap();
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), 1);
+}
+
+#[tokio::test]
+async fn oauth2_revoked_refresh_token_is_fatal() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let client = builder.with_oauth2_token(oauth2_token(&mock, 7200));
+
+    mock.expire_oauth2_tokens();
+    mock.revoke_oauth2_refresh_tokens();
+    let err = client.get_user_info().await.unwrap_err();
+    assert!(matches!(err, TwitterError::RefreshRevoked(_)));
+    assert!(err.is_fatal());
+
+    // Later requests fail fast without another refresh attempt
+    let hits = mock.hits(Endpoint::OAuth2Token);
+    assert!(matches!(
+        client.get_user_info().await,
+        Err(TwitterError::RefreshRevoked(_))
+    ));
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), hits);
+}
+
+#[tokio::test]
+async fn oauth2_token_save_is_retried() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let sink = Arc::new(FlakySink::failing(1));
+    let client = builder
+        .with_oauth2_token(oauth2_token(&mock, 60))
+        .with_token_sink(sink.clone());
+
+    client.get_user_info().await.unwrap();
+    assert_eq!(sink.saved.0.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn oauth2_unsaved_token_is_fatal() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let sink = Arc::new(FlakySink::failing(usize::MAX));
+    let client = builder
+        .with_oauth2_token(oauth2_token(&mock, 60))
+        .with_token_sink(sink.clone());
+
+    // The rotated token would be lost on restart, so the session stops
+    let err = client.get_user_info().await.unwrap_err();
+    assert!(matches!(err, TwitterError::RefreshRevoked(_)));
+    assert!(err.is_fatal());
+    assert_eq!(mock.hits(Endpoint::OAuth2Token), 1);
+    assert!(matches!(
+        client.get_user_info().await,
+        Err(TwitterError::RefreshRevoked(_))
+    ));
+}
+
+#[tokio::test]
+async fn oauth2_code_needs_matching_verifier() {
+    let mock = MockTwitter::start().await;
+    let builder = oauth2_builder_for(&mock);
+    let redirect_uri = "http://localhost/callback";
+    let pkce = Pkce::generate();
+    let url = builder
+        .oauth2_authorize_url(redirect_uri, "state", &pkce.challenge)
+        .unwrap();
+
+    let resp = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+    let location = resp.headers()["location"].to_str().unwrap();
+    let query = location.split_once('?').unwrap().1;
+    let params: std::collections::HashMap<String, String> =
+        serde_urlencoded::from_str(query).unwrap();
+    assert_eq!(params["state"], "state");
+
+    let other = Pkce::generate();
+    assert!(builder
+        .exchange_oauth2_code(&params["code"], redirect_uri, &other.verifier)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn agent_follows_its_settings() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let llm = Arc::new(ScriptedBackend::new(vec![Message::assistant("gm")]));
+    let settings = AgentSettings {
+        tools: Some(vec!["like".to_string(), "tweet_joke".to_string()]),
+        model: ModelSettings {
+            max_tokens: 64,
+            temperature: 1.0,
+            ..Default::default()
+        },
+        persona: PersonaSettings {
+            system_prompt: "You are a cat.".to_string(),
+            prompt: "say gm".to_string(),
+        },
+        ..Default::default()
+    };
+    let config = AgentConfig {
+        settings: Arc::new(settings),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    let wait_for_request = async {
+        while llm.requests().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_request) => {}
+    }
+
+    let request = &llm.requests()[0];
+    let tools: Vec<_> = request
+        .tools
+        .iter()
+        .map(|tool| tool.name.as_str())
+        .collect();
+    assert_eq!(tools, vec!["tweet_joke", "like"]);
+    assert_eq!(request.max_tokens, Some(64));
+    assert_eq!(request.temperature, Some(1.0));
+    let messages: Vec<_> = request
+        .messages
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_deref().unwrap()))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![("system", "You are a cat."), ("user", "say gm")]
+    );
+}
+
+#[tokio::test]
+async fn agent_answers_new_mentions() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let alice = MockUser {
+        id: "42".to_string(),
+        name: "Alice".to_string(),
+        username: "alice".to_string(),
+        profile_image_url: String::new(),
+        description: String::new(),
+    };
+    let joke = mock.post_out_of_band("gm");
+    mock.mention(&alice, "@encumbered old news", None);
+
+    let llm = Arc::new(ScriptedBackend::new(vec![]));
+    let settings = AgentSettings {
+        // Only answers mentions during the test
+        schedule: ScheduleSettings {
+            cron: Some("0 0 0 1 1 *".to_string()),
+            ..Default::default()
+        },
+        mentions: MentionSettings {
+            enabled: true,
+            poll_interval_secs: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cursor = Arc::new(MentionCursor::new());
+    let config = AgentConfig {
+        settings: Arc::new(settings),
+        mention_cursor: cursor.clone(),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    let answer = async {
+        // The mention from before the first poll is skipped
+        while mock.hits(Endpoint::Mentions) < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mention = mock.mention(&alice, "@encumbered explain", Some(&joke));
+        llm.push(ScriptedBackend::tool_calls(vec![(
+            "reply",
+            json!({ "tweet_id": mention, "text": "it was a joke" }),
+        )]));
+        llm.push(Message::assistant("Replied."));
+        while cursor.since_id().as_deref() != Some(mention.as_str()) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        mention
+    };
+    let mention = tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        mention = tokio::time::timeout(Duration::from_secs(10), answer) => mention.unwrap(),
+    };
+
+    let tweets = mock.tweets();
+    assert_eq!(tweets.len(), 1);
+    assert_eq!(tweets[0].body["reply"]["in_reply_to_tweet_id"], mention);
+    let requests = llm.requests();
+    assert_eq!(requests.len(), 2);
+    let prompt = requests[0].messages[1].content.as_deref().unwrap();
+    assert!(prompt.contains("@alice (Alice)"), "{}", prompt);
+    assert!(prompt.contains("@encumbered explain"), "{}", prompt);
+    // Conversation context: the thread being replied to
+    assert!(
+        prompt.contains(&format!(
+            "It replies to this thread, oldest first:\n[{}] @encumbered: gm\n",
+            joke
+        )),
+        "{}",
+        prompt
+    );
+}
+
+#[tokio::test]
+async fn rate_limited_mentions_are_not_answered_twice() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let alice = MockUser {
+        id: "42".to_string(),
+        name: "Alice".to_string(),
+        username: "alice".to_string(),
+        profile_image_url: String::new(),
+        description: String::new(),
+    };
+
+    let llm = Arc::new(ScriptedBackend::new(vec![]));
+    let settings = AgentSettings {
+        schedule: ScheduleSettings {
+            cron: Some("0 0 0 1 1 *".to_string()),
+            ..Default::default()
+        },
+        mentions: MentionSettings {
+            enabled: true,
+            poll_interval_secs: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cursor = Arc::new(MentionCursor::new());
+    let config = AgentConfig {
+        settings: Arc::new(settings),
+        mention_cursor: cursor.clone(),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    // The reply goes out, then the like runs out of retries
+    let answer = async {
+        while mock.hits(Endpoint::Mentions) < 1 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mention = mock.mention(&alice, "@encumbered hi", None);
+        for _ in 0..3 {
+            mock.fail_next(Endpoint::Likes, Failure::RateLimited { reset_at: now() });
+        }
+        llm.push(ScriptedBackend::tool_calls(vec![
+            ("reply", json!({ "tweet_id": mention, "text": "hello" })),
+            ("like", json!({ "tweet_id": mention })),
+        ]));
+        while cursor.since_id().as_deref() != Some(mention.as_str()) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        answered = tokio::time::timeout(Duration::from_secs(20), answer) => answered.unwrap(),
+    }
+
+    assert_eq!(mock.tweets().len(), 1);
+    assert!(mock.likes().is_empty());
+    assert_eq!(llm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn mentions_wait_for_quiet_hours() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let alice = MockUser {
+        id: "42".to_string(),
+        name: "Alice".to_string(),
+        username: "alice".to_string(),
+        profile_image_url: String::new(),
+        description: String::new(),
+    };
+
+    let llm = Arc::new(ScriptedBackend::new(vec![]));
+    let settings = AgentSettings {
+        schedule: ScheduleSettings {
+            cron: Some("0 0 0 1 1 *".to_string()),
+            quiet_hours: Some(QuietHours {
+                start: "22:00".to_string(),
+                end: "07:00".to_string(),
+            }),
+            ..Default::default()
+        },
+        mentions: MentionSettings {
+            enabled: true,
+            poll_interval_secs: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let clock = Arc::new(FakeClock::new(
+        chrono::DateTime::parse_from_rfc3339("2024-06-03T23:00:00Z")
+            .unwrap()
+            .timestamp() as u64,
+    ));
+    let cursor = Arc::new(MentionCursor::new());
+    let config = AgentConfig {
+        settings: Arc::new(settings),
+        clock: clock.clone(),
+        mention_cursor: cursor.clone(),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    let answer = async {
+        while mock.hits(Endpoint::Mentions) < 1 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mention = mock.mention(&alice, "@encumbered are you up?", None);
+        // Held, not skipped, through a few polls
+        let polls = mock.hits(Endpoint::Mentions);
+        while mock.hits(Endpoint::Mentions) < polls + 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(llm.requests().is_empty());
+        assert_ne!(cursor.since_id().as_deref(), Some(mention.as_str()));
+
+        llm.push(ScriptedBackend::tool_calls(vec![(
+            "reply",
+            json!({ "tweet_id": mention, "text": "now I am" }),
+        )]));
+        llm.push(Message::assistant("Replied."));
+        clock.advance(8 * 60 * 60);
+        while cursor.since_id().as_deref() != Some(mention.as_str()) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        mention
+    };
+    let mention = tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        mention = tokio::time::timeout(Duration::from_secs(10), answer) => mention.unwrap(),
+    };
+
+    let tweets = mock.tweets();
+    assert_eq!(tweets.len(), 1);
+    assert_eq!(tweets[0].body["reply"]["in_reply_to_tweet_id"], mention);
+}
+
+#[tokio::test]
+async fn agent_tools_reach_the_api() {
+    let mock = MockTwitter::start().await;
+    let builder = builder_for(&mock);
+    let twitter_client = builder.with_auth(access_tokens(&mock));
+    let llm = Arc::new(ScriptedBackend::new(vec![
+        ScriptedBackend::tool_calls(vec![
+            ("like", json!({ "tweet_id": "7" })),
+            ("retweet", json!({ "tweet_id": "8" })),
+            ("reply", json!({ "tweet_id": "1", "text": "replying" })),
+            ("quote_tweet", json!({ "tweet_id": "2", "text": "quoting" })),
+            (
+                "tweet_with_media",
+                json!({ "text": "look", "image_prompt": "a cat" }),
+            ),
+            (
+                "reply",
+                json!({ "tweet_id": "not-an-id", "text": "never sent" }),
+            ),
+            ("like", json!({ "id": "7" })),
+        ]),
+        Message::assistant("Done."),
+    ]));
+    let images = Arc::new(StaticImageBackend(vec![0x89, 0x50, 0x4e, 0x47]));
+    let log = action_log();
+
+    let wait_for_results = async {
+        while llm.requests().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, agent_config(llm.clone(), Some(images), log.clone())) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_results) => {}
+    }
+
+    assert_eq!(mock.likes(), vec!["7"]);
+    assert_eq!(mock.retweets(), vec!["8"]);
+    let tweets = mock.tweets();
+    assert_eq!(tweets.len(), 3);
+    assert_eq!(tweets[0].body["reply"]["in_reply_to_tweet_id"], "1");
+    assert_eq!(tweets[1].body["quote_tweet_id"], "2");
+    assert_eq!(
+        tweets[2].body["media"]["media_ids"],
+        json!([mock.media()[0].media_id])
+    );
+    assert_eq!(mock.media()[0].bytes, vec![0x89, 0x50, 0x4e, 0x47]);
+
+    // Invalid arguments are reported back to the model instead of posted
+    let requests = llm.requests();
+    let results: Vec<_> = requests[1]
+        .messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .map(|m| m.content.clone().unwrap())
+        .collect();
+    assert_eq!(results.len(), 7);
+    assert!(
+        results[5].starts_with("Rejected as invalid"),
+        "{}",
+        results[5]
+    );
+    assert!(
+        results[6].starts_with("Invalid arguments"),
+        "{}",
+        results[6]
+    );
+
+    // Rejected calls aren't counted as successes
+    let metrics = metrics::render(&StatusReport {
+        started_at: 0,
+        released: false,
+        accounts: Vec::new(),
+    })
+    .unwrap();
+    assert!(metrics.contains("tool_invocations_total{outcome=\"rejected\",tool=\"reply\"}"));
+    assert!(metrics.contains("tool_invocations_total{outcome=\"rejected\",tool=\"like\"}"));
+
+    // Every step and every ID Twitter returned is in the signed log
+    let entries = log.entries(0, usize::MAX).await;
+    action_log::verify_chain(&entries).unwrap();
+    assert!(matches!(entries[0].action, Action::Prompt { .. }));
+    assert!(matches!(entries[1].action, Action::ModelResponse { .. }));
+    assert!(entries
+        .iter()
+        .all(|entry| entry.account.as_deref() == Some(mock.config().user.id.as_str())));
+    let responses: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| match &entry.action {
+            Action::TwitterResponse { endpoint, id } => Some((endpoint.as_str(), id.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert!(responses.contains(&("POST /2/users/:id/likes", "7")));
+    assert!(responses.contains(&("POST /2/tweets", tweets[0].id.as_str())));
+    let tool_calls = entries
+        .iter()
+        .filter(|entry| matches!(entry.action, Action::ToolCall { .. }))
+        .count();
+    assert_eq!(tool_calls, 7);
+}
+
+#[tokio::test]
+async fn posts_stop_at_the_daily_limit() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let llm = Arc::new(ScriptedBackend::new(vec![
+        ScriptedBackend::tool_calls(vec![
+            ("tweet_joke", json!({ "joke": "first" })),
+            ("tweet_joke", json!({ "joke": "second" })),
+        ]),
+        Message::assistant("Done."),
+    ]));
+    let config = AgentConfig {
+        settings: Arc::new(AgentSettings {
+            schedule: ScheduleSettings {
+                max_posts_per_day: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    let wait_for_results = async {
+        while llm.requests().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_results) => {}
+    }
+
+    assert_eq!(mock.tweets().len(), 1);
+    let requests = llm.requests();
+    let results: Vec<_> = requests[1]
+        .messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .map(|m| m.content.clone().unwrap())
+        .collect();
+    assert!(results[1].starts_with("Rejected"), "{}", results[1]);
+}
+
+/// An image backend that is always down.
+struct FailingImageBackend;
+
+#[async_trait::async_trait]
+impl ImageBackend for FailingImageBackend {
+    async fn generate(&self, _prompt: &str) -> eyre::Result<Vec<u8>> {
+        eyre::bail!("image backend unavailable")
+    }
+}
+
+#[tokio::test]
+async fn failed_image_generation_is_rejected() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let llm = Arc::new(ScriptedBackend::new(vec![
+        ScriptedBackend::tool_calls(vec![(
+            "tweet_with_media",
+            json!({ "text": "look", "image_prompt": "a cat" }),
+        )]),
+        Message::assistant("Done."),
+    ]));
+    let config = AgentConfig {
+        images: Some(Arc::new(FailingImageBackend)),
+        ..agent_config(llm.clone(), None, action_log())
+    };
+
+    let wait_for_results = async {
+        while llm.requests().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, config) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_results) => {}
+    }
+
+    assert!(mock.tweets().is_empty());
+    let requests = llm.requests();
+    let result = requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .and_then(|m| m.content.clone())
+        .unwrap();
+    assert!(result.starts_with("Image generation failed"), "{}", result);
+    let metrics = metrics::render(&StatusReport {
+        started_at: 0,
+        released: false,
+        accounts: Vec::new(),
+    })
+    .unwrap();
+    assert!(
+        metrics.contains("tool_invocations_total{outcome=\"rejected\",tool=\"tweet_with_media\"}")
+    );
+}
+
+#[tokio::test]
+async fn failed_tool_calls_leave_their_batch_logged() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    mock.fail_next(
+        Endpoint::Retweets,
+        Failure::Status {
+            status: 500,
+            body: "oops".to_string(),
+        },
+    );
+    let llm = Arc::new(ScriptedBackend::new(vec![ScriptedBackend::tool_calls(
+        vec![
+            ("retweet", json!({ "tweet_id": "8" })),
+            ("like", json!({ "tweet_id": "7" })),
+        ],
+    )]));
+    let log = action_log();
+
+    let logged_calls = || async {
+        log.entries(0, usize::MAX)
+            .await
+            .into_iter()
+            .filter_map(|entry| match entry.action {
+                Action::ToolCall { name, output, .. } => Some((name, output)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let wait_for_calls = async {
+        while logged_calls().await.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::select! {
+        result = event_loop::event_loop(twitter_client, agent_config(llm.clone(), None, log.clone())) => panic!("event loop exited: {:?}", result),
+        _ = tokio::time::timeout(Duration::from_secs(10), wait_for_calls) => {}
+    }
+
+    // The like went through alongside the failed retweet, and both are logged
+    assert_eq!(mock.likes(), vec!["7"]);
+    let calls = logged_calls().await;
+    assert_eq!(calls[0].0, "retweet");
+    assert!(calls[0].1.starts_with("Failed"), "{}", calls[0].1);
+    assert_eq!(calls[1], ("like".to_string(), "Liked tweet 7".to_string()));
+    // The run still failed, so the model was not asked to carry on
+    assert_eq!(llm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn wrong_consumer_secret_is_rejected() {
+    let mock = MockTwitter::start().await;
+    let builder = TwitterBuilder::new(mock.config().consumer_key.clone(), "wrong".to_string())
+        .with_oauth_base_url(mock.base_url().to_string());
+
+    let err = builder
+        .request_oauth_token("http://localhost/callback".to_string())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TwitterError::Unauthorized(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn client_calls_are_recorded() {
+    let mock = MockTwitter::start().await;
+    let builder = builder_for(&mock);
+    let client = builder.with_auth(access_tokens(&mock));
+
+    let user = client.get_user_info().await.unwrap();
+    assert_eq!(user.id, mock.config().user.id);
+
+    let media_id = client
+        .upload_media(vec![1, 2, 3], Some(vec!["42".to_string()]))
+        .await
+        .unwrap();
+    let mut tweet = Tweet::new("with media".to_string());
+    tweet.set_media_ids(vec![media_id.clone()]);
+    tweet.set_reply_tweet_id("1".to_string());
+    let tweet_id = client.raw_tweet(tweet).await.unwrap();
+
+    client.like(user.id.clone(), "7".to_string()).await.unwrap();
+    client
+        .retweet(user.id.clone(), "8".to_string())
+        .await
+        .unwrap();
+
+    let media = mock.media();
+    assert_eq!(media[0].media_id, media_id);
+    assert_eq!(media[0].bytes, vec![1, 2, 3]);
+    assert_eq!(media[0].additional_owners.as_deref(), Some("42"));
+    let tweets = mock.tweets();
+    assert_eq!(tweets[0].id, tweet_id);
+    assert_eq!(tweets[0].body["media"]["media_ids"], json!([media_id]));
+    assert_eq!(tweets[0].body["reply"]["in_reply_to_tweet_id"], "1");
+    assert_eq!(mock.likes(), vec!["7"]);
+    assert_eq!(mock.retweets(), vec!["8"]);
+}
+
+#[tokio::test]
+async fn api_failures_are_classified() {
+    let mock = MockTwitter::start().await;
+    let builder = builder_for(&mock);
+    let client = builder.with_auth(access_tokens(&mock));
+    let tweet = |text: &str| Tweet::new(text.to_string());
+
+    client.raw_tweet(tweet("once")).await.unwrap();
+    let err = client.raw_tweet(tweet("once")).await.unwrap_err();
+    assert!(
+        matches!(err, TwitterError::DuplicateContent(_)),
+        "{:?}",
+        err
+    );
+
+    mock.fail_next(Endpoint::Tweets, Failure::Suspended);
+    let err = client.raw_tweet(tweet("suspended")).await.unwrap_err();
+    assert!(matches!(err, TwitterError::Suspended(_)), "{:?}", err);
+    assert!(err.is_fatal());
+
+    mock.fail_next(Endpoint::UsersMe, Failure::Unauthorized);
+    let err = client.get_user_info().await.unwrap_err();
+    assert!(matches!(err, TwitterError::Unauthorized(_)), "{:?}", err);
+
+    let revoked = builder.with_auth(TwitterTokenPair {
+        token: "revoked".to_string(),
+        secret: "revoked".to_string(),
+    });
+    let err = revoked.get_user_info().await.unwrap_err();
+    assert!(matches!(err, TwitterError::Unauthorized(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried() {
+    let mock = MockTwitter::start().await;
+    let builder = builder_for(&mock);
+    let client = builder.with_auth(access_tokens(&mock));
+
+    mock.fail_next(Endpoint::Tweets, Failure::RateLimited { reset_at: now() });
+    client
+        .raw_tweet(Tweet::new("after reset".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(mock.hits(Endpoint::Tweets), 2);
+
+    let budget = client.rate_limit_budget(TWEETS_ENDPOINT).unwrap();
+    assert_eq!(budget.limit, Some(mock.config().rate_limit));
+    assert_eq!(budget.remaining, mock.config().rate_limit - 1);
+}
+
+#[tokio::test]
+async fn watchdog_flags_out_of_band_activity() {
+    let mock = MockTwitter::start().await;
+    mock.post_out_of_band("posted before encumbrance");
+    let builder = builder_for(&mock);
+    let client = builder.with_auth(access_tokens(&mock));
+    let user_id = mock.config().user.id.clone();
+    let watchdog = Watchdog::new(Duration::from_secs(60));
+
+    // The first check is the baseline
+    assert!(watchdog.check(&client, &user_id).await.unwrap().is_empty());
+
+    client
+        .raw_tweet(Tweet::new("ours".to_string()))
+        .await
+        .unwrap();
+    client.like(user_id.clone(), "7".to_string()).await.unwrap();
+    client
+        .retweet(user_id.clone(), "8".to_string())
+        .await
+        .unwrap();
+    assert!(watchdog.check(&client, &user_id).await.unwrap().is_empty());
+    assert!(!watchdog.report().alert);
+
+    let id = mock.post_out_of_band("not ours");
+    mock.like_out_of_band("9");
+    mock.set_profile(MockUser {
+        name: "Someone Else".to_string(),
+        ..mock.config().user.clone()
+    });
+    let anomalies = watchdog.check(&client, &user_id).await.unwrap();
+    assert_eq!(
+        anomalies,
+        vec![
+            Anomaly::UnknownTweet {
+                id,
+                text: "not ours".to_string()
+            },
+            Anomaly::UnknownLike {
+                tweet_id: "9".to_string()
+            },
+            Anomaly::ProfileChanged {
+                field: "name",
+                before: "Encumbered Agent".to_string(),
+                after: "Someone Else".to_string(),
+            },
+        ]
+    );
+    let report = watchdog.report();
+    assert!(report.alert);
+    assert_eq!(report.anomalies.len(), 3);
+
+    // Each anomaly is reported once
+    assert!(watchdog.check(&client, &user_id).await.unwrap().is_empty());
+    assert!(watchdog.report().alert);
+}
@@ -0,0 +1,161 @@
+use std::{collections::HashMap, sync::Arc};
+
+use client::{
+    encumbrance::{self, CredentialRotator, FakeRotator, RotationState, Secret, Vault},
+    sealing::StaticKeyProvider,
+};
+
+fn initial() -> HashMap<String, Secret> {
+    HashMap::from([
+        (
+            "protonmail".to_string(),
+            Secret::new("mail-old".to_string()),
+        ),
+        (
+            "twitter".to_string(),
+            Secret::new("twitter-old".to_string()),
+        ),
+    ])
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|w| w == needle.as_bytes())
+}
+
+#[tokio::test]
+async fn encumber_rotates_and_seals_passwords() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("encumbrance.sealed");
+    let vault = Vault::new(&path, Arc::new(StaticKeyProvider(vec![7; 32])));
+
+    let mail = FakeRotator::new("protonmail", Secret::new("mail-old".to_string()));
+    let twitter = FakeRotator::new("twitter", Secret::new("twitter-old".to_string()));
+    let rotators: Vec<Box<dyn CredentialRotator>> =
+        vec![Box::new(mail.clone()), Box::new(twitter.clone())];
+    encumbrance::encumber(&rotators, &initial(), &vault)
+        .await
+        .unwrap();
+
+    let records = vault.load().await.unwrap();
+    for (service, account) in [("protonmail", &mail), ("twitter", &twitter)] {
+        let record = records.get(service).unwrap();
+        assert_eq!(record.state, RotationState::Rotated);
+        assert_eq!(record.password, account.password());
+        assert_eq!(record.password.expose().len(), encumbrance::PASSWORD_LENGTH);
+    }
+    assert_ne!(twitter.password().expose(), "twitter-old");
+    assert_ne!(mail.password(), twitter.password());
+
+    let on_disk = std::fs::read(&path).unwrap();
+    assert!(!contains(&on_disk, twitter.password().expose()));
+    assert!(!contains(&on_disk, "twitter"));
+
+    // Rotating again starts from the sealed passwords, not the initial ones
+    encumbrance::encumber(&rotators, &initial(), &vault)
+        .await
+        .unwrap();
+    let records = vault.load().await.unwrap();
+    assert_eq!(records.get("twitter").unwrap().password, twitter.password());
+}
+
+#[tokio::test]
+async fn failed_rotation_stays_pending() {
+    let dir = tempfile::tempdir().unwrap();
+    let vault = Vault::new(
+        dir.path().join("encumbrance.sealed"),
+        Arc::new(StaticKeyProvider(vec![7; 32])),
+    );
+
+    let twitter = FakeRotator::new("twitter", Secret::new("twitter-old".to_string())).failing();
+    let rotators: Vec<Box<dyn CredentialRotator>> = vec![Box::new(twitter.clone())];
+    let err = encumbrance::encumber(&rotators, &initial(), &vault)
+        .await
+        .unwrap_err();
+
+    let records = vault.load().await.unwrap();
+    let record = records.get("twitter").unwrap();
+    assert_eq!(record.state, RotationState::Pending);
+    assert!(!err.to_string().contains(record.password.expose()));
+    assert_eq!(twitter.password().expose(), "twitter-old");
+
+    // A pending record needs resolving before another attempt
+    assert!(encumbrance::encumber(&rotators, &initial(), &vault)
+        .await
+        .is_err());
+
+    // Nothing changed, so resolving drops the record and a retry starts over
+    encumbrance::resolve(&rotators, &initial(), &vault)
+        .await
+        .unwrap();
+    assert!(vault.load().await.unwrap().get("twitter").is_none());
+    let twitter = FakeRotator::new("twitter", twitter.password());
+    let rotators: Vec<Box<dyn CredentialRotator>> = vec![Box::new(twitter.clone())];
+    encumbrance::encumber(&rotators, &initial(), &vault)
+        .await
+        .unwrap();
+    let records = vault.load().await.unwrap();
+    assert_eq!(records.get("twitter").unwrap().password, twitter.password());
+}
+
+#[tokio::test]
+async fn resolve_keeps_whichever_password_logs_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let vault = Vault::new(
+        dir.path().join("encumbrance.sealed"),
+        Arc::new(StaticKeyProvider(vec![7; 32])),
+    );
+    let twitter = FakeRotator::new("twitter", Secret::new("twitter-old".to_string()));
+    let rotate = |rotator: FakeRotator| {
+        let rotators: Vec<Box<dyn CredentialRotator>> = vec![Box::new(rotator)];
+        rotators
+    };
+
+    // The change went through but was never confirmed
+    let unconfirmed = rotate(twitter.clone().losing_confirmation());
+    assert!(encumbrance::encumber(&unconfirmed, &initial(), &vault)
+        .await
+        .is_err());
+    encumbrance::resolve(&unconfirmed, &initial(), &vault)
+        .await
+        .unwrap();
+    let record = vault.load().await.unwrap().get("twitter").unwrap().clone();
+    assert_eq!(record.state, RotationState::Rotated);
+    assert_eq!(record.password, twitter.password());
+
+    // A failed second rotation falls back to the sealed password, not the
+    // initial one
+    let failing = rotate(twitter.clone().failing());
+    assert!(encumbrance::encumber(&failing, &initial(), &vault)
+        .await
+        .is_err());
+    encumbrance::resolve(&failing, &initial(), &vault)
+        .await
+        .unwrap();
+    let resolved = vault.load().await.unwrap().get("twitter").unwrap().clone();
+    assert_eq!(resolved.state, RotationState::Rotated);
+    assert_eq!(resolved.password, record.password);
+
+    // With neither password working the record stays pending
+    assert!(encumbrance::encumber(&failing, &initial(), &vault)
+        .await
+        .is_err());
+    let locked_out = rotate(FakeRotator::new(
+        "twitter",
+        Secret::new("changed-elsewhere".to_string()),
+    ));
+    assert!(encumbrance::resolve(&locked_out, &initial(), &vault)
+        .await
+        .is_err());
+    let record = vault.load().await.unwrap().get("twitter").unwrap().clone();
+    assert_eq!(record.state, RotationState::Pending);
+}
+
+#[test]
+fn secrets_are_redacted() {
+    let password = encumbrance::generate_password();
+    let debug = format!("{:?}", password);
+    assert!(!debug.contains(password.expose()));
+    assert_ne!(password, encumbrance::generate_password());
+}
@@ -0,0 +1,92 @@
+use client::twitter::error::{ApiProblem, TwitterError};
+use reqwest::{header::HeaderMap, StatusCode};
+
+fn classify(status: u16, body: &str) -> TwitterError {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-rate-limit-reset", "1700000000".parse().unwrap());
+    TwitterError::from_response(StatusCode::from_u16(status).unwrap(), &headers, body)
+}
+
+#[test]
+fn v1_errors_are_classified_by_code() {
+    let body = r#"{"errors":[{"code":64,"message":"Your account is suspended and is not permitted to access this feature."}]}"#;
+    let problem = ApiProblem::parse(body);
+    assert_eq!(problem.errors[0].code, Some(64));
+    assert_eq!(
+        problem.to_string(),
+        "Your account is suspended and is not permitted to access this feature. (code 64)"
+    );
+    assert!(matches!(classify(403, body), TwitterError::Suspended(_)));
+
+    assert!(matches!(
+        classify(
+            400,
+            r#"{"errors":[{"code":88,"message":"Rate limit exceeded"}]}"#
+        ),
+        TwitterError::RateLimited {
+            reset_at: Some(1700000000),
+            ..
+        }
+    ));
+    assert!(matches!(
+        classify(
+            403,
+            r#"{"errors":[{"code":187,"message":"Status is a duplicate."}]}"#
+        ),
+        TwitterError::DuplicateContent(_)
+    ));
+    assert!(matches!(
+        classify(
+            401,
+            r#"{"errors":[{"code":89,"message":"Invalid or expired token."}]}"#
+        ),
+        TwitterError::Unauthorized(_)
+    ));
+}
+
+#[test]
+fn v2_problems_are_classified_by_type() {
+    let body = r#"{"title":"Forbidden","type":"about:blank","status":403,"detail":"You are not allowed to create a Tweet with duplicate content."}"#;
+    let problem = ApiProblem::parse(body);
+    assert_eq!(problem.title.as_deref(), Some("Forbidden"));
+    assert_eq!(problem.kind.as_deref(), Some("about:blank"));
+    assert!(matches!(
+        classify(403, body),
+        TwitterError::DuplicateContent(_)
+    ));
+
+    let capped = r#"{"title":"UsageCapExceeded","type":"https://api.twitter.com/2/problems/usage-capped","detail":"Usage cap exceeded: Monthly product cap"}"#;
+    assert!(matches!(
+        classify(403, capped),
+        TwitterError::RateLimited { .. }
+    ));
+
+    let not_found = r#"{"errors":[{"value":"1","detail":"Could not find tweet with id: [1].","title":"Not Found Error","type":"https://api.twitter.com/2/problems/resource-not-found"}],"title":"Invalid Request","detail":"One or more parameters to your request was invalid.","type":"https://api.twitter.com/2/problems/invalid-request"}"#;
+    let problem = ApiProblem::parse(not_found);
+    assert_eq!(problem.errors.len(), 1);
+    assert!(matches!(classify(400, not_found), TwitterError::Api { .. }));
+}
+
+#[test]
+fn ordinary_messages_are_not_fatal() {
+    // Words like "locked", "suspended" or "duplicate" in a message don't
+    // change the classification
+    for body in [
+        r#"{"title":"Forbidden","type":"about:blank","detail":"This tweet is from a locked account."}"#,
+        r#"{"title":"Forbidden","type":"about:blank","detail":"The quoted user is suspended."}"#,
+        r#"{"errors":[{"message":"Remove duplicate media IDs"}]}"#,
+    ] {
+        let error = classify(403, body);
+        assert!(
+            matches!(error, TwitterError::Api { .. }),
+            "{:?} for {}",
+            error,
+            body
+        );
+        assert!(!error.is_fatal());
+    }
+    assert!(matches!(
+        classify(502, "<html>Bad Gateway</html>"),
+        TwitterError::Api { .. }
+    ));
+}
@@ -0,0 +1,162 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{http::StatusCode, routing::post, Json, Router};
+use client::{
+    llm::{AnthropicBackend, CompletionRequest, LlmBackend, Message, OpenAiBackend, ToolCall},
+    metrics,
+    status::StatusReport,
+};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+/// Serves canned OpenAI and Anthropic responses, both reporting usage.
+async fn stub_server() -> String {
+    let app = Router::new()
+        .route(
+            "/chat/completions",
+            post(|| async {
+                Json(json!({
+                    "choices": [{ "message": { "role": "assistant", "content": "hi" } }],
+                    "usage": { "prompt_tokens": 12, "completion_tokens": 3 },
+                }))
+            }),
+        )
+        .route(
+            "/v1/messages",
+            post(|| async {
+                Json(json!({
+                    "content": [{ "type": "text", "text": "hello" }],
+                    "usage": { "input_tokens": 20, "output_tokens": 5 },
+                }))
+            }),
+        )
+        .route(
+            "/rejected/chat/completions",
+            post(|| async { (StatusCode::BAD_REQUEST, "model not found") }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    url
+}
+
+#[tokio::test]
+async fn completions_are_metered() {
+    let url = stub_server().await;
+    let request = CompletionRequest {
+        messages: vec![Message::user("hi")],
+        tools: Vec::new(),
+        max_tokens: None,
+        temperature: None,
+    };
+
+    let openai = OpenAiBackend::new(None)
+        .with_base_url(url.clone())
+        .with_model("stub-gpt".to_string());
+    let message = openai.complete(&request).await.unwrap();
+    assert_eq!(message.content.as_deref(), Some("hi"));
+    let anthropic = AnthropicBackend::new("key".to_string())
+        .with_base_url(url.clone())
+        .with_model("stub-claude".to_string());
+    anthropic.complete(&request).await.unwrap();
+    let failing = OpenAiBackend::new(None)
+        .with_base_url(format!("{}/missing", url))
+        .with_model("stub-gpt".to_string());
+    assert!(failing.complete(&request).await.is_err());
+    // The provider's explanation reaches the caller
+    let rejected = OpenAiBackend::new(None)
+        .with_base_url(format!("{}/rejected", url))
+        .with_model("stub-gpt".to_string());
+    let e = rejected.complete(&request).await.unwrap_err();
+    assert!(e.to_string().contains("model not found"), "{}", e);
+
+    let text = metrics::render(&StatusReport {
+        started_at: 0,
+        released: false,
+        accounts: Vec::new(),
+    })
+    .unwrap();
+    for line in [
+        r#"llm_tokens_total{backend="openai",kind="input",model="stub-gpt"} 12"#,
+        r#"llm_tokens_total{backend="openai",kind="output",model="stub-gpt"} 3"#,
+        r#"llm_tokens_total{backend="anthropic",kind="input",model="stub-claude"} 20"#,
+        r#"llm_requests_total{backend="openai",model="stub-gpt",outcome="ok"} 1"#,
+        r#"llm_requests_total{backend="openai",model="stub-gpt",outcome="error"} 2"#,
+        r#"llm_request_duration_seconds_count{backend="anthropic",model="stub-claude"} 1"#,
+    ] {
+        assert!(text.contains(line), "missing {}", line);
+    }
+}
+
+#[tokio::test]
+async fn anthropic_tool_turns_are_translated() {
+    let bodies = Arc::new(Mutex::new(Vec::<Value>::new()));
+    let recorded = bodies.clone();
+    let app = Router::new().route(
+        "/v1/messages",
+        post(move |Json(body): Json<Value>| async move {
+            recorded.lock().unwrap().push(body);
+            Json(json!({
+                "content": [
+                    { "type": "text", "text": "On it" },
+                    { "type": "tool_use", "id": "toolu_3", "name": "like", "input": { "tweet_id": "9" } },
+                ],
+            }))
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let calls = vec![
+        ToolCall::function("toolu_1", "like", r#"{"tweet_id":"7"}"#.to_string()),
+        ToolCall::function("toolu_2", "retweet", r#"{"tweet_id":"8"}"#.to_string()),
+    ];
+    let request = CompletionRequest {
+        messages: vec![
+            Message::system("be brief"),
+            Message::user("react to 7 and 8"),
+            Message {
+                role: "assistant".to_string(),
+                tool_calls: Some(calls.clone()),
+                ..Default::default()
+            },
+            Message::tool(&calls[0], "Liked tweet 7"),
+            Message::tool(&calls[1], "Retweeted tweet 8"),
+        ],
+        tools: Vec::new(),
+        max_tokens: None,
+        temperature: None,
+    };
+    let anthropic = AnthropicBackend::new("key".to_string()).with_base_url(url);
+    let message = anthropic.complete(&request).await.unwrap();
+
+    // Parallel results are folded into one user turn after the tool_use turn
+    let body = bodies.lock().unwrap().pop().unwrap();
+    assert_eq!(body["system"], "be brief");
+    assert_eq!(
+        body["messages"],
+        json!([
+            { "role": "user", "content": [{ "type": "text", "text": "react to 7 and 8" }] },
+            { "role": "assistant", "content": [
+                { "type": "tool_use", "id": "toolu_1", "name": "like", "input": { "tweet_id": "7" } },
+                { "type": "tool_use", "id": "toolu_2", "name": "retweet", "input": { "tweet_id": "8" } },
+            ] },
+            { "role": "user", "content": [
+                { "type": "tool_result", "tool_use_id": "toolu_1", "content": "Liked tweet 7" },
+                { "type": "tool_result", "tool_use_id": "toolu_2", "content": "Retweeted tweet 8" },
+            ] },
+        ])
+    );
+
+    // tool_use blocks come back as OpenAI-shaped tool calls
+    assert_eq!(message.content.as_deref(), Some("On it"));
+    let tool_calls = message.tool_calls.unwrap();
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(tool_calls[0].id, "toolu_3");
+    assert_eq!(tool_calls[0].function.name, "like");
+    assert_eq!(
+        serde_json::from_str::<Value>(&tool_calls[0].function.arguments).unwrap(),
+        json!({ "tweet_id": "9" })
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use client::mentions::MentionCursor;
+use common::client_for;
+use mock_twitter::{Endpoint, MockTwitter, MockUser};
+
+fn user(id: &str, username: &str) -> MockUser {
+    MockUser {
+        id: id.to_string(),
+        name: username.to_uppercase(),
+        username: username.to_string(),
+        profile_image_url: String::new(),
+        description: String::new(),
+    }
+}
+
+#[tokio::test]
+async fn mentions_page_from_the_cursor() {
+    let mock = MockTwitter::start().await;
+    let config = mock.config();
+    let twitter_client = client_for(&mock);
+    let user_id = &config.user.id;
+
+    let (alice, bob) = (user("42", "alice"), user("43", "bob"));
+    let first = mock.mention(&alice, "first", None);
+    let ids: Vec<String> = (0..150)
+        .map(|i| mock.mention(&bob, &format!("reply {}", i), Some(&first)))
+        .collect();
+
+    // Two pages of 100, returned oldest first
+    let mentions = twitter_client
+        .get_mentions(user_id, Some(&first))
+        .await
+        .unwrap();
+    assert_eq!(mock.hits(Endpoint::Mentions), 2);
+    let returned: Vec<&str> = mentions.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(returned, ids);
+    let last = mentions.last().unwrap();
+    assert_eq!(last.author.as_ref().unwrap().username, "bob");
+    assert_eq!(last.in_reply_to.as_ref().unwrap().text, "first");
+    assert_eq!(last.conversation_id.as_deref(), Some(first.as_str()));
+
+    let newest = twitter_client
+        .get_mentions(user_id, Some(&ids[149]))
+        .await
+        .unwrap();
+    assert!(newest.is_empty());
+}
+
+#[tokio::test]
+async fn threads_walk_up_to_the_root() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let (alice, bob) = (user("42", "alice"), user("43", "bob"));
+
+    let root = mock.tweet_as(&alice, "hot take:\nrollups are banks", None);
+    let reply = mock.tweet_as(&bob, "no", Some(&root));
+    let last = mock.tweet_as(&alice, "yes", Some(&reply));
+
+    let thread = twitter_client.get_conversation(&last, 10).await.unwrap();
+    assert!(thread.complete);
+    assert_eq!(
+        thread.tweets[0].conversation_id.as_deref(),
+        Some(root.as_str())
+    );
+    assert_eq!(
+        thread.transcript(),
+        format!(
+            "[{}] @alice: hot take: rollups are banks\n[{}] @bob: no\n[{}] @alice: yes\n",
+            root, reply, last
+        )
+    );
+    assert_eq!(mock.hits(Endpoint::TweetLookup), 3);
+
+    // Cached tweets aren't fetched again, and the walk stops at the limit
+    let branch = mock.tweet_as(&bob, "still no", Some(&last));
+    let thread = twitter_client.get_conversation(&branch, 2).await.unwrap();
+    assert!(!thread.complete);
+    assert_eq!(thread.tweets.len(), 2);
+    assert!(thread
+        .transcript()
+        .starts_with("(earlier tweets not shown)\n"));
+    assert_eq!(mock.hits(Endpoint::TweetLookup), 4);
+
+    // Missing tweets end the walk early
+    let orphan = mock.tweet_as(&bob, "what did they say", Some("1"));
+    let thread = twitter_client.get_conversation(&orphan, 10).await.unwrap();
+    assert!(!thread.complete);
+    assert_eq!(thread.tweets.len(), 1);
+    assert!(twitter_client.get_tweet("1").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn cursor_survives_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let cursor = MentionCursor::open(dir.path(), "1234").await.unwrap();
+    assert_eq!(cursor.since_id(), None);
+    cursor.advance("1800000000000000001").await.unwrap();
+
+    let reopened = MentionCursor::open(dir.path(), "1234").await.unwrap();
+    assert_eq!(reopened.since_id().as_deref(), Some("1800000000000000001"));
+    let other = MentionCursor::open(dir.path(), "5678").await.unwrap();
+    assert_eq!(other.since_id(), None);
+    assert!(MentionCursor::open(dir.path(), "../1234").await.is_err());
+}
@@ -0,0 +1,94 @@
+use std::time::{Duration, SystemTime};
+
+use client::{
+    event_loop::next_run_delay,
+    twitter::rate_limit::{RateLimitBudget, RateLimiter, TWEETS_ENDPOINT},
+};
+use reqwest::header::HeaderMap;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn headers(limit: &str, remaining: &str, reset_at: u64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-rate-limit-limit", limit.parse().unwrap());
+    headers.insert("x-rate-limit-remaining", remaining.parse().unwrap());
+    headers.insert("x-rate-limit-reset", reset_at.into());
+    headers
+}
+
+#[test]
+fn budgets_come_from_headers() {
+    let limiter = RateLimiter::default();
+    let reset_at = now() + 900;
+    limiter.record(TWEETS_ENDPOINT, &headers("100", "42", reset_at));
+    assert_eq!(
+        limiter.budget(TWEETS_ENDPOINT),
+        Some(RateLimitBudget {
+            limit: Some(100),
+            remaining: 42,
+            reset_at,
+        })
+    );
+
+    // Out of range counts are dropped rather than truncated
+    limiter.record(TWEETS_ENDPOINT, &headers("4294967296", "7", reset_at));
+    assert_eq!(limiter.budget(TWEETS_ENDPOINT).unwrap().limit, None);
+    limiter.record(TWEETS_ENDPOINT, &headers("100", "4294967297", reset_at));
+    assert_eq!(limiter.budget(TWEETS_ENDPOINT).unwrap().remaining, 7);
+
+    // Responses without the headers leave the budget alone
+    limiter.record(TWEETS_ENDPOINT, &HeaderMap::new());
+    assert_eq!(limiter.budget(TWEETS_ENDPOINT).unwrap().remaining, 7);
+    assert_eq!(limiter.budgets().len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn exhausted_budgets_wait_for_the_reset() {
+    let limiter = RateLimiter::default();
+    let started = tokio::time::Instant::now();
+    limiter.wait_for_budget(TWEETS_ENDPOINT).await;
+    assert_eq!(started.elapsed(), Duration::ZERO);
+
+    limiter.record(TWEETS_ENDPOINT, &headers("100", "1", now() + 600));
+    limiter.wait_for_budget(TWEETS_ENDPOINT).await;
+    assert_eq!(started.elapsed(), Duration::ZERO);
+
+    limiter.record(TWEETS_ENDPOINT, &headers("100", "0", now() + 600));
+    limiter.wait_for_budget(TWEETS_ENDPOINT).await;
+    let waited = started.elapsed();
+    assert!(
+        (Duration::from_secs(599)..=Duration::from_secs(601)).contains(&waited),
+        "{:?}",
+        waited
+    );
+}
+
+#[test]
+fn runs_spread_over_the_window() {
+    let min_interval = Duration::from_secs(30);
+    let budget = |remaining, reset_in| {
+        Some(RateLimitBudget {
+            limit: Some(100),
+            remaining,
+            reset_at: now() + reset_in,
+        })
+    };
+    assert_eq!(next_run_delay(None, min_interval), min_interval);
+    // 10 tweets over 900s: one every 90s, give or take the clock ticking
+    let delay = next_run_delay(budget(10, 900), min_interval);
+    assert!((89..=90).contains(&delay.as_secs()), "{:?}", delay);
+    // A large budget never runs more often than the minimum
+    assert_eq!(
+        next_run_delay(budget(1000, 900), min_interval),
+        min_interval
+    );
+    // Nothing left: wait for the reset
+    let delay = next_run_delay(budget(0, 600), min_interval);
+    assert!((599..=600).contains(&delay.as_secs()), "{:?}", delay);
+    assert_eq!(next_run_delay(budget(0, 0), min_interval), min_interval);
+}
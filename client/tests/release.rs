@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use client::{
+    clock::FakeClock,
+    credentials::StoredCredentials,
+    encumbrance::Secret,
+    release::{self, Release, ReleasePayload, ReleasePolicy, ReleaseStatus},
+    sealing::StaticKeyProvider,
+    twitter::auth::{TwitterTokenPair, UserAuth},
+};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const UNLOCK_AT: u64 = 1_800_000_000;
+
+fn payload() -> ReleasePayload {
+    let mut payload = ReleasePayload::default();
+    payload.credentials.insert(
+        "1234567890".to_string(),
+        StoredCredentials {
+            consumer_key: "consumer-key".to_string(),
+            consumer_secret: "consumer-secret".to_string(),
+            auth: UserAuth::OAuth1(TwitterTokenPair {
+                token: "access-token".to_string(),
+                secret: "access-secret".to_string(),
+            }),
+        },
+    );
+    payload.passwords.insert(
+        "twitter".to_string(),
+        Secret::new("twitter-password".to_string()),
+    );
+    payload
+}
+
+async fn open(clock: Arc<FakeClock>, path: &std::path::Path, recipient: PublicKey) -> Release {
+    let policy = ReleasePolicy {
+        unlock_at: UNLOCK_AT,
+        recipient,
+    };
+    Release::open(
+        policy,
+        clock,
+        path,
+        Arc::new(StaticKeyProvider(vec![7; 32])),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn releases_to_recipient_after_unlock_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("release.sealed");
+    let owner = StaticSecret::from([3; 32]);
+    let clock = Arc::new(FakeClock::new(UNLOCK_AT - 60));
+    let release = open(clock.clone(), &path, PublicKey::from(&owner)).await;
+
+    assert!(release.evaluate(&payload()).await.unwrap().is_none());
+    assert!(matches!(release.status(), ReleaseStatus::Locked { .. }));
+    assert!(!path.exists());
+
+    clock.advance(60);
+    let decision = release.evaluate(&payload()).await.unwrap().unwrap();
+    assert_eq!(decision.released_at, UNLOCK_AT);
+    assert!(release.is_released());
+
+    let plaintext = release::decrypt(&owner, &decision.release).unwrap();
+    let revealed: ReleasePayload = serde_json::from_slice(&plaintext).unwrap();
+    assert!(matches!(
+        &revealed.credentials["1234567890"].auth,
+        UserAuth::OAuth1(pair) if pair.secret == "access-secret"
+    ));
+    assert_eq!(revealed.passwords["twitter"].expose(), "twitter-password");
+
+    // Nobody else can read it, and it isn't readable on disk
+    assert!(release::decrypt(&StaticSecret::from([4; 32]), &decision.release).is_err());
+    let on_disk = std::fs::read(&path).unwrap();
+    let needle = b"twitter-password";
+    assert!(!on_disk.windows(needle.len()).any(|w| w == needle));
+}
+
+#[tokio::test]
+async fn release_decision_is_persisted_and_final() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("release.sealed");
+    let owner = PublicKey::from(&StaticSecret::from([3; 32]));
+    let clock = Arc::new(FakeClock::new(UNLOCK_AT));
+    let decision = open(clock.clone(), &path, owner)
+        .await
+        .evaluate(&payload())
+        .await
+        .unwrap()
+        .unwrap();
+
+    // After a restart, even with the clock set back and a different recipient
+    // configured, the original release stands
+    clock.set(UNLOCK_AT - 3600);
+    let other = PublicKey::from(&StaticSecret::from([4; 32]));
+    let release = open(clock, &path, other).await;
+    assert!(release.is_released());
+    assert_eq!(
+        release.evaluate(&payload()).await.unwrap().unwrap(),
+        decision
+    );
+}
+
+#[test]
+fn low_order_recipients_are_rejected() {
+    let zero = hex::encode([0u8; 32]);
+    assert!(release::parse_public_key(&zero).is_err());
+    assert!(release::encrypt_to(&PublicKey::from([0; 32]), b"secret").is_err());
+
+    std::env::set_var("RELEASE_AT", UNLOCK_AT.to_string());
+    std::env::set_var("RELEASE_RECIPIENT", &zero);
+    let policy = ReleasePolicy::from_env();
+    std::env::remove_var("RELEASE_AT");
+    std::env::remove_var("RELEASE_RECIPIENT");
+    assert!(policy.is_err());
+
+    let owner = PublicKey::from(&StaticSecret::from([3; 32]));
+    assert!(release::parse_public_key(&hex::encode(owner.as_bytes())).is_ok());
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use chrono::DateTime;
+use client::{
+    clock::FakeClock,
+    schedule::Scheduler,
+    settings::{AgentSettings, CatchUp, QuietHours, ScheduleSettings},
+};
+
+fn at(time: &str) -> u64 {
+    DateTime::parse_from_rfc3339(time).unwrap().timestamp() as u64
+}
+
+fn start(settings: ScheduleSettings, now: &str) -> (Scheduler, Arc<FakeClock>) {
+    let clock = Arc::new(FakeClock::new(at(now)));
+    (Scheduler::new(&settings, clock.clone()).unwrap(), clock)
+}
+
+/// Advances the clock to the next planned run, posts once and returns it.
+fn run(scheduler: &Scheduler, clock: &FakeClock) -> u64 {
+    let next = scheduler.next_run(0);
+    clock.set(next);
+    scheduler.record_post();
+    next
+}
+
+fn hourly(catch_up: CatchUp) -> ScheduleSettings {
+    ScheduleSettings {
+        cron: Some("0 0 * * * *".to_string()),
+        catch_up,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn runs_at_cron_slots() {
+    let settings = ScheduleSettings {
+        cron: Some("0 30 9,13,18 * * *".to_string()),
+        timezone: "Europe/Berlin".to_string(),
+        ..Default::default()
+    };
+    let (scheduler, clock) = start(settings, "2024-06-03T06:00:00Z");
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T09:30:00+02:00"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T13:30:00+02:00"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T18:30:00+02:00"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-04T09:30:00+02:00"));
+
+    // Never before `earliest`, e.g. while rate limited
+    assert_eq!(
+        scheduler.next_run(at("2024-06-04T14:00:00+02:00")),
+        at("2024-06-04T14:00:00+02:00")
+    );
+}
+
+#[test]
+fn without_cron_runs_as_soon_as_allowed() {
+    let (scheduler, _clock) = start(ScheduleSettings::default(), "2024-06-03T06:00:00Z");
+    assert_eq!(scheduler.next_run(0), at("2024-06-03T06:00:00Z"));
+    assert_eq!(
+        scheduler.next_run(at("2024-06-03T06:00:30Z")),
+        at("2024-06-03T06:00:30Z")
+    );
+}
+
+#[test]
+fn quiet_hours_hold_runs() {
+    let settings = ScheduleSettings {
+        timezone: "America/New_York".to_string(),
+        quiet_hours: Some(QuietHours {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+        }),
+        ..Default::default()
+    };
+    let (scheduler, _clock) = start(settings, "2024-06-03T23:30:00-04:00");
+    assert_eq!(scheduler.next_run(0), at("2024-06-04T07:00:00-04:00"));
+
+    // Cron slots inside quiet hours are skipped, not piled up at the end
+    let settings = ScheduleSettings {
+        cron: Some("0 0 */4 * * *".to_string()),
+        quiet_hours: Some(QuietHours {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+        }),
+        ..Default::default()
+    };
+    let (scheduler, clock) = start(settings, "2024-06-03T20:30:00Z");
+    assert_eq!(run(&scheduler, &clock), at("2024-06-04T08:00:00Z"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-04T12:00:00Z"));
+}
+
+#[test]
+fn daily_cap_waits_for_the_next_day() {
+    let settings = ScheduleSettings {
+        max_posts_per_day: 2,
+        ..Default::default()
+    };
+    let (scheduler, clock) = start(settings, "2024-06-03T10:00:00Z");
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T10:00:00Z"));
+    assert_eq!(scheduler.posts_left(), 1);
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T10:00:00Z"));
+    assert_eq!(scheduler.posts_left(), 0);
+    assert_eq!(run(&scheduler, &clock), at("2024-06-04T00:00:00Z"));
+    assert_eq!(scheduler.posts_left(), 1);
+}
+
+#[test]
+fn runs_that_post_nothing_leave_the_cap_alone() {
+    let settings = ScheduleSettings {
+        max_posts_per_day: 1,
+        ..Default::default()
+    };
+    let (scheduler, _clock) = start(settings, "2024-06-03T10:00:00Z");
+    for _ in 0..3 {
+        assert_eq!(scheduler.next_run(0), at("2024-06-03T10:00:00Z"));
+    }
+    // Posts count however they came about, e.g. answering a mention
+    scheduler.record_post();
+    assert_eq!(scheduler.next_run(0), at("2024-06-04T00:00:00Z"));
+}
+
+#[test]
+fn the_cap_defaults_to_a_safe_daily_count() {
+    let (scheduler, _clock) = start(ScheduleSettings::default(), "2024-06-03T10:00:00Z");
+    assert_eq!(scheduler.posts_left(), 12);
+}
+
+#[test]
+fn daily_minimum_adds_runs() {
+    let settings = ScheduleSettings {
+        cron: Some("0 0 12 * * *".to_string()),
+        min_posts_per_day: 3,
+        ..Default::default()
+    };
+    let (scheduler, clock) = start(settings, "2024-06-03T00:00:00Z");
+    // Two runs short with the noon slot, so one a third of the way through
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T08:00:00Z"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T12:00:00Z"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-03T18:00:00Z"));
+    assert_eq!(run(&scheduler, &clock), at("2024-06-04T08:00:00Z"));
+}
+
+#[test]
+fn posts_before_a_restart_count_towards_their_day() {
+    let settings = ScheduleSettings {
+        max_posts_per_day: 2,
+        ..Default::default()
+    };
+    let posts = [at("2024-06-02T23:00:00Z"), at("2024-06-03T08:00:00Z")];
+    let (scheduler, _clock) = start(settings, "2024-06-03T10:00:00Z");
+    let scheduler = scheduler.with_history(None, posts);
+    assert_eq!(scheduler.posts_left(), 1);
+}
+
+#[test]
+fn missed_slots_are_caught_up_on() {
+    let last_run = Some(at("2024-06-03T09:00:00Z"));
+    let now = "2024-06-03T12:30:00Z";
+
+    let (skip, clock) = start(hourly(CatchUp::Skip), now);
+    let skip = skip.with_history(last_run, []);
+    assert_eq!(run(&skip, &clock), at("2024-06-03T13:00:00Z"));
+
+    let (once, clock) = start(hourly(CatchUp::Once), now);
+    let once = once.with_history(last_run, []);
+    assert_eq!(run(&once, &clock), at(now));
+    assert_eq!(run(&once, &clock), at("2024-06-03T13:00:00Z"));
+
+    // One run each for 10:00, 11:00 and 12:00
+    let (all, clock) = start(hourly(CatchUp::All), now);
+    let all = all.with_history(last_run, []);
+    for _ in 0..3 {
+        assert_eq!(run(&all, &clock), at(now));
+    }
+    assert_eq!(run(&all, &clock), at("2024-06-03T13:00:00Z"));
+}
+
+#[test]
+fn jitter_delays_within_bounds() {
+    let settings = ScheduleSettings {
+        jitter_secs: 600,
+        ..hourly(CatchUp::Skip)
+    };
+    let (scheduler, clock) = start(settings, "2024-06-03T09:30:00Z");
+    for hour in 10..20 {
+        let slot = at(&format!("2024-06-03T{}:00:00Z", hour));
+        let next = run(&scheduler, &clock);
+        assert!((slot..=slot + 600).contains(&next), "{} for {}", next, slot);
+    }
+}
+
+#[test]
+fn invalid_schedules_are_rejected() {
+    let quiet = |start: &str, end: &str| {
+        Some(QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+        })
+    };
+    for schedule in [
+        ScheduleSettings {
+            cron: Some("every hour".to_string()),
+            ..Default::default()
+        },
+        ScheduleSettings {
+            timezone: "Mars/Olympus_Mons".to_string(),
+            ..Default::default()
+        },
+        ScheduleSettings {
+            quiet_hours: quiet("10pm", "07:00"),
+            ..Default::default()
+        },
+        ScheduleSettings {
+            quiet_hours: quiet("07:00", "07:00"),
+            ..Default::default()
+        },
+        ScheduleSettings {
+            min_posts_per_day: 5,
+            max_posts_per_day: 3,
+            ..Default::default()
+        },
+        ScheduleSettings {
+            max_posts_per_day: 0,
+            ..Default::default()
+        },
+    ] {
+        let settings = AgentSettings {
+            schedule: schedule.clone(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err(), "accepted {:?}", schedule);
+    }
+}
@@ -0,0 +1,110 @@
+use client::{
+    settings::{AgentSettings, LoadedSettings, SettingsFormat},
+    tools::ToolRegistry,
+};
+
+const TOML: &str = r#"
+tools = ["tweet_joke", "reply"]
+
+[model]
+name = "gpt-4o-mini"
+temperature = 0.9
+
+[persona]
+system_prompt = "You are a cat."
+prompt = "meow"
+
+[schedule]
+min_interval_secs = 60
+"#;
+
+const YAML: &str = "
+tools: [tweet_joke, reply]
+model:
+  name: gpt-4o-mini
+  temperature: 0.9
+persona:
+  system_prompt: You are a cat.
+  prompt: meow
+schedule:
+  min_interval_secs: 60
+";
+
+#[test]
+fn toml_and_yaml_configs_agree() {
+    let toml = LoadedSettings::parse(TOML.to_string(), SettingsFormat::Toml).unwrap();
+    let yaml = LoadedSettings::parse(YAML.to_string(), SettingsFormat::Yaml).unwrap();
+    assert_eq!(toml.settings, yaml.settings);
+
+    let settings = toml.settings;
+    assert_eq!(settings.model.name.as_deref(), Some("gpt-4o-mini"));
+    // Unset fields keep their defaults
+    assert_eq!(settings.model.max_tokens, 500);
+    assert_eq!(settings.limits.max_turns, 10);
+    assert!(settings.enables("reply"));
+    assert!(!settings.enables("like"));
+    assert!(AgentSettings::default().enables("like"));
+}
+
+#[test]
+fn hash_covers_the_exact_source() {
+    let parse = |source: &str| LoadedSettings::parse(source.to_string(), SettingsFormat::Toml);
+    let loaded = parse(TOML).unwrap();
+    assert_eq!(loaded.sha256, parse(TOML).unwrap().sha256);
+    assert_ne!(loaded.sha256, parse(&format!("{}\n", TOML)).unwrap().sha256);
+
+    let defaults = LoadedSettings::defaults().unwrap();
+    assert_eq!(defaults.settings, AgentSettings::default());
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    for source in [
+        "tools = [\"tweet_joke\", \"delete_account\"]",
+        "tools = [\"like\", \"like\"]",
+        "tools = []",
+        "[model]\ntemperature = 3.0",
+        "[model]\nmax_tokens = 0",
+        "[persona]\nprompt = \" \"",
+        "[schedule]\nmin_interval_secs = 0",
+        "[limits]\nmax_turns = 0",
+        "[model]\ntemprature = 0.7",
+        "tools = [\"tweet_joke\"]\n[mentions]\nenabled = true",
+    ] {
+        assert!(
+            LoadedSettings::parse(source.to_string(), SettingsFormat::Toml).is_err(),
+            "accepted {:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn every_registered_tool_can_be_enabled() {
+    let names = ToolRegistry::names();
+    assert!(names.contains(&"tweet_with_media"));
+    let source = format!("tools = {:?}", names);
+    let loaded = LoadedSettings::parse(source, SettingsFormat::Toml).unwrap();
+    assert!(names.iter().all(|name| loaded.settings.enables(name)));
+}
+
+#[tokio::test]
+async fn format_follows_the_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("agent.yml");
+    tokio::fs::write(&path, YAML).await.unwrap();
+    let loaded = LoadedSettings::load(&path).await.unwrap();
+    assert_eq!(loaded.format, SettingsFormat::Yaml);
+    assert_eq!(loaded.source, YAML);
+
+    assert!(LoadedSettings::load(dir.path().join("agent.json"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn example_config_matches_the_defaults() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/agent.example.toml");
+    let loaded = LoadedSettings::load(path).await.unwrap();
+    assert_eq!(loaded.settings, AgentSettings::default());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use client::twitter::{
+    error::TwitterError,
+    tweet::{weighted_length, Thread, Tweet, MAX_TWEET_LENGTH},
+};
+use common::client_for;
+use mock_twitter::{Endpoint, MockTwitter};
+
+/// `count` numbered sentences of about 50 characters each.
+fn sentences(count: usize) -> String {
+    (0..count)
+        .map(|i| format!("Sentence {:02} is about as long as all the others.", i))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn length_is_weighted() {
+    assert_eq!(weighted_length("gm"), 2);
+    assert_eq!(weighted_length("日本"), 4);
+    assert_eq!(weighted_length("🦀"), 2);
+    assert_eq!(
+        weighted_length("see https://example.com/a/very/long/path/indeed ok"),
+        4 + 23 + 3
+    );
+
+    assert!(Tweet::new("a".repeat(MAX_TWEET_LENGTH)).validate().is_ok());
+    let cjk = Tweet::new("日".repeat(MAX_TWEET_LENGTH / 2 + 1));
+    assert!(matches!(cjk.validate(), Err(TwitterError::InvalidTweet(_))));
+}
+
+#[test]
+fn threads_split_between_sentences() {
+    let text = sentences(12);
+    let thread = Thread::split(&text).unwrap();
+    assert_eq!(thread.parts().len(), 3);
+    for part in thread.parts() {
+        assert!(weighted_length(part) <= MAX_TWEET_LENGTH, "{}", part);
+        assert!(part.starts_with("Sentence") && part.ends_with("others."));
+    }
+    assert_eq!(thread.parts().join(" "), text);
+
+    // Short text stays a single tweet, line breaks and all
+    let short = Thread::split("  gm.\ngn.  ").unwrap();
+    assert_eq!(short.parts(), ["gm.\ngn."]);
+
+    // Sentences and words too long for a tweet are cut further
+    let rant = format!("{} {}", "word ".repeat(70).trim(), "x".repeat(300));
+    let thread = Thread::split(&rant).unwrap();
+    assert!(thread
+        .parts()
+        .iter()
+        .all(|part| weighted_length(part) <= MAX_TWEET_LENGTH));
+    assert_eq!(thread.parts().concat().replace(' ', "").len(), 70 * 4 + 300);
+
+    assert!(Thread::split(" \n ").is_err());
+}
+
+#[tokio::test]
+async fn threads_post_as_a_reply_chain() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let root = twitter_client
+        .raw_tweet(Tweet::new("root".to_string()))
+        .await
+        .unwrap();
+
+    let mut thread = Thread::split(&sentences(12)).unwrap();
+    thread.set_reply_tweet_id(root.clone());
+    let ids = twitter_client.post_thread(thread.clone()).await.unwrap();
+    assert_eq!(ids.len(), 3);
+
+    let tweets = mock.tweets();
+    let posted = &tweets[1..];
+    let replied_to: Vec<&str> = posted
+        .iter()
+        .map(|tweet| {
+            tweet.body["reply"]["in_reply_to_tweet_id"]
+                .as_str()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(replied_to, [root.as_str(), &ids[0], &ids[1]]);
+    let texts: Vec<&str> = posted.iter().map(|tweet| tweet.text.as_str()).collect();
+    assert_eq!(texts, thread.parts());
+}
+
+#[tokio::test]
+async fn failed_threads_are_deleted() {
+    let mock = MockTwitter::start().await;
+    let twitter_client = client_for(&mock);
+    let thread = Thread::split(&sentences(12)).unwrap();
+
+    // The last part duplicates an earlier tweet
+    let earlier = twitter_client
+        .raw_tweet(Tweet::new(thread.parts()[2].clone()))
+        .await
+        .unwrap();
+    let result = twitter_client.post_thread(thread).await;
+    assert!(matches!(result, Err(TwitterError::DuplicateContent(_))));
+
+    assert_eq!(mock.hits(Endpoint::DeleteTweet), 2);
+    assert_eq!(mock.deleted().len(), 2);
+    let left: Vec<String> = mock.tweets().into_iter().map(|tweet| tweet.id).collect();
+    assert_eq!(left, [earlier]);
+    // Still known as the agent's own, in case the timeline lags
+    let activity = twitter_client.activity();
+    assert_eq!(activity.tweets.len(), 1);
+    assert_eq!(activity.deleted_tweets.len(), 2);
+}
@@ -0,0 +1,364 @@
+//! An in-process stand-in for the parts of the Twitter API used by `client`.
+//!
+//! It speaks OAuth 1.0a (including signature verification) and OAuth 2.0
+//! with PKCE, records every tweet, like, retweet and media upload, and lets
+//! tests queue failures for specific endpoints or simulate activity by
+//! someone other than the client.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tokio::task::JoinHandle;
+
+mod oauth;
+mod oauth2;
+mod routes;
+
+/// Length of a Twitter rate-limit window.
+const WINDOW_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    RequestToken,
+    Authenticate,
+    AccessToken,
+    UsersMe,
+    Tweets,
+    DeleteTweet,
+    Likes,
+    Retweets,
+    MediaUpload,
+    UserTweets,
+    LikedTweets,
+    TweetLookup,
+    Mentions,
+    OAuth2Authorize,
+    OAuth2Token,
+}
+
+/// A failure to return instead of the normal response.
+#[derive(Debug, Clone)]
+pub enum Failure {
+    /// 429 with `x-rate-limit-reset` set to `reset_at`.
+    RateLimited { reset_at: u64 },
+    /// 401 problem JSON, as for a revoked token.
+    Unauthorized,
+    /// 403 with the v1.1 "account suspended" error code.
+    Suspended,
+    /// 403 duplicate-content problem JSON.
+    DuplicateContent,
+    /// On `Authenticate` or `OAuth2Authorize`: the user clicked "Cancel".
+    Denied,
+    /// Any other status and raw body.
+    Status { status: u16, body: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct MockUser {
+    pub id: String,
+    pub name: String,
+    pub username: String,
+    pub profile_image_url: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    /// OAuth 2.0 confidential client credentials.
+    pub oauth2_client_id: String,
+    pub oauth2_client_secret: String,
+    /// Lifetime of OAuth 2.0 access tokens, in seconds.
+    pub oauth2_token_lifetime: u64,
+    pub user: MockUser,
+    /// Requests allowed per endpoint per 15-minute window.
+    pub rate_limit: u32,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            consumer_key: "mock-consumer-key".to_string(),
+            consumer_secret: "mock-consumer-secret".to_string(),
+            oauth2_client_id: "mock-client-id".to_string(),
+            oauth2_client_secret: "mock-client-secret".to_string(),
+            oauth2_token_lifetime: 7200,
+            user: MockUser {
+                id: "1234567890".to_string(),
+                name: "Encumbered Agent".to_string(),
+                username: "encumbered".to_string(),
+                profile_image_url: "https://pbs.twimg.com/profile_images/mock.jpg".to_string(),
+                description: "Posting from an enclave".to_string(),
+            },
+            rate_limit: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedTweet {
+    pub id: String,
+    pub text: String,
+    /// The full JSON body as posted.
+    pub body: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedMedia {
+    pub media_id: String,
+    pub bytes: Vec<u8>,
+    pub additional_owners: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingToken {
+    secret: String,
+    callback: String,
+    verifier: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingCode {
+    code_challenge: String,
+    redirect_uri: String,
+    scope: String,
+}
+
+/// A tweet by someone other than the account.
+#[derive(Debug, Clone)]
+struct ForeignTweet {
+    tweet: serde_json::Value,
+    author: MockUser,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    remaining: u32,
+    reset_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    request_tokens: HashMap<String, PendingToken>,
+    /// Access token -> secret.
+    access_tokens: HashMap<String, String>,
+    nonces: HashSet<String>,
+    /// Authorization code -> the request it was issued for.
+    oauth2_codes: HashMap<String, PendingCode>,
+    /// OAuth 2.0 access token -> expiry.
+    bearer_tokens: HashMap<String, u64>,
+    /// Refresh token -> scope. Each is single use.
+    refresh_tokens: HashMap<String, String>,
+    tweets: Vec<RecordedTweet>,
+    /// IDs of the account's tweets deleted through the API, in order.
+    deleted: Vec<String>,
+    /// The account's timeline as `GET /2/users/:id/tweets` returns it, oldest
+    /// first: posted tweets, retweets and out-of-band posts.
+    timeline: Vec<serde_json::Value>,
+    /// Replaces `MockConfig::user` once the profile is edited.
+    profile: Option<MockUser>,
+    likes: Vec<String>,
+    retweets: Vec<String>,
+    /// Tweets mentioning the account, oldest first.
+    mentions: Vec<ForeignTweet>,
+    /// Other people's tweets that don't mention the account.
+    others: Vec<ForeignTweet>,
+    media: Vec<RecordedMedia>,
+    failures: HashMap<Endpoint, VecDeque<Failure>>,
+    windows: HashMap<Endpoint, Window>,
+    hits: HashMap<Endpoint, usize>,
+    next_id: u64,
+}
+
+impl State {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        (1_800_000_000_000_000_000 + self.next_id).to_string()
+    }
+}
+
+#[derive(Clone)]
+struct Shared {
+    config: Arc<MockConfig>,
+    state: Arc<Mutex<State>>,
+}
+
+/// A running mock server; stops when dropped.
+pub struct MockTwitter {
+    base_url: String,
+    shared: Shared,
+    handle: JoinHandle<()>,
+}
+
+impl MockTwitter {
+    pub async fn start() -> Self {
+        Self::start_with(MockConfig::default()).await
+    }
+
+    pub async fn start_with(config: MockConfig) -> Self {
+        let shared = Shared {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(State::default())),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock twitter");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = routes::router(shared.clone());
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        Self {
+            base_url,
+            shared,
+            handle,
+        }
+    }
+
+    /// Serves the API, upload and OAuth endpoints alike.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn config(&self) -> &MockConfig {
+        &self.shared.config
+    }
+
+    /// Returns `failure` for the next request to `endpoint`. Failures queue up
+    /// in order.
+    pub fn fail_next(&self, endpoint: Endpoint, failure: Failure) {
+        self.state()
+            .failures
+            .entry(endpoint)
+            .or_default()
+            .push_back(failure);
+    }
+
+    /// Sets the budget reported for `endpoint` in the current window.
+    pub fn set_rate_limit(&self, endpoint: Endpoint, remaining: u32, reset_at: u64) {
+        self.state().windows.insert(
+            endpoint,
+            Window {
+                remaining,
+                reset_at,
+            },
+        );
+    }
+
+    /// Issues an access token directly, skipping the three-legged flow.
+    pub fn issue_access_token(&self) -> (String, String) {
+        routes::issue_access_token(&mut self.state())
+    }
+
+    /// Issues an OAuth 2.0 access and refresh token directly, skipping the
+    /// authorization code flow.
+    pub fn issue_oauth2_token(&self) -> (String, String) {
+        let lifetime = self.shared.config.oauth2_token_lifetime;
+        oauth2::issue_token(
+            &mut self.state(),
+            lifetime,
+            "tweet.read tweet.write users.read",
+        )
+    }
+
+    /// Expires every OAuth 2.0 access token issued so far.
+    pub fn expire_oauth2_tokens(&self) {
+        for expires_at in self.state().bearer_tokens.values_mut() {
+            *expires_at = 0;
+        }
+    }
+
+    /// Revokes every outstanding OAuth 2.0 refresh token.
+    pub fn revoke_oauth2_refresh_tokens(&self) {
+        self.state().refresh_tokens.clear();
+    }
+
+    pub fn tweets(&self) -> Vec<RecordedTweet> {
+        self.state().tweets.clone()
+    }
+
+    /// Tweets deleted through the API; they are gone from `tweets` and the
+    /// timeline.
+    pub fn deleted(&self) -> Vec<String> {
+        self.state().deleted.clone()
+    }
+
+    pub fn likes(&self) -> Vec<String> {
+        self.state().likes.clone()
+    }
+
+    pub fn retweets(&self) -> Vec<String> {
+        self.state().retweets.clone()
+    }
+
+    /// Posts a tweet as if from another client holding the account's
+    /// password. It shows up on the timeline but not in `tweets`.
+    pub fn post_out_of_band(&self, text: &str) -> String {
+        let mut state = self.state();
+        let id = state.next_id();
+        state
+            .timeline
+            .push(serde_json::json!({ "id": id, "text": text }));
+        id
+    }
+
+    /// Mentions the account as `author`, optionally replying to
+    /// `in_reply_to`, and returns the new tweet's ID.
+    pub fn mention(&self, author: &MockUser, text: &str, in_reply_to: Option<&str>) -> String {
+        let mut state = self.state();
+        let tweet = routes::foreign_tweet(&mut state, author, text, in_reply_to);
+        let id = tweet.tweet["id"].as_str().unwrap_or_default().to_string();
+        state.mentions.push(tweet);
+        id
+    }
+
+    /// Posts a tweet as `author` that doesn't mention the account, e.g. the
+    /// start of a thread it is later pulled into.
+    pub fn tweet_as(&self, author: &MockUser, text: &str, in_reply_to: Option<&str>) -> String {
+        let mut state = self.state();
+        let tweet = routes::foreign_tweet(&mut state, author, text, in_reply_to);
+        let id = tweet.tweet["id"].as_str().unwrap_or_default().to_string();
+        state.others.push(tweet);
+        id
+    }
+
+    /// Likes a tweet as if from another client.
+    pub fn like_out_of_band(&self, tweet_id: &str) {
+        self.state().likes.push(tweet_id.to_string());
+    }
+
+    /// Edits the profile returned by `/2/users/me`.
+    pub fn set_profile(&self, user: MockUser) {
+        self.state().profile = Some(user);
+    }
+
+    pub fn media(&self) -> Vec<RecordedMedia> {
+        self.state().media.clone()
+    }
+
+    /// How many requests reached `endpoint`, including rejected ones.
+    pub fn hits(&self, endpoint: Endpoint) -> usize {
+        self.state().hits.get(&endpoint).copied().unwrap_or(0)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+impl Drop for MockTwitter {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
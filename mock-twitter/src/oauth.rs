@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha1::Sha1;
+
+/// RFC 5849 section 3.6: everything but unreserved characters is encoded.
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn encode(s: &str) -> String {
+    utf8_percent_encode(s, RFC3986).to_string()
+}
+
+/// The `oauth_*` parameters of an `Authorization: OAuth ...` header.
+#[derive(Debug, Clone, Default)]
+pub struct OAuthParams(BTreeMap<String, String>);
+
+impl OAuthParams {
+    pub fn parse(header: &str) -> Option<Self> {
+        let params = header.trim().strip_prefix("OAuth ")?;
+        let mut map = BTreeMap::new();
+        for pair in params.split(',') {
+            let (key, value) = pair.trim().split_once('=')?;
+            let value = value.trim_matches('"');
+            let value = percent_decode_str(value).decode_utf8().ok()?;
+            map.insert(key.to_string(), value.into_owned());
+        }
+        Some(Self(map))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.get("oauth_token")
+    }
+}
+
+/// A request as seen by the signature check.
+pub struct SignedRequest<'a> {
+    pub method: &'a str,
+    /// Scheme, host and path, without the query string.
+    pub base_url: &'a str,
+    /// Decoded query and form-body parameters.
+    pub params: Vec<(String, String)>,
+    pub oauth: &'a OAuthParams,
+}
+
+impl SignedRequest<'_> {
+    /// Recomputes the HMAC-SHA1 signature and compares it with the one sent.
+    pub fn verify(&self, consumer_key: &str, consumer_secret: &str, token_secret: &str) -> bool {
+        if self.oauth.get("oauth_consumer_key") != Some(consumer_key)
+            || self.oauth.get("oauth_signature_method") != Some("HMAC-SHA1")
+        {
+            return false;
+        }
+        let Some(signature) = self.oauth.get("oauth_signature") else {
+            return false;
+        };
+        signature == self.signature(consumer_secret, token_secret)
+    }
+
+    fn signature(&self, consumer_secret: &str, token_secret: &str) -> String {
+        let oauth_params = self
+            .oauth
+            .0
+            .iter()
+            .filter(|(k, _)| *k != "oauth_signature" && *k != "realm");
+        let mut params: Vec<(String, String)> = self
+            .params
+            .iter()
+            .map(|(k, v)| (k, v))
+            .chain(oauth_params)
+            .map(|(k, v)| (encode(k), encode(v)))
+            .collect();
+        params.sort();
+        let normalized = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let base_string = format!(
+            "{}&{}&{}",
+            self.method.to_uppercase(),
+            encode(self.base_url),
+            encode(&normalized)
+        );
+        let key = format!("{}&{}", encode(consumer_secret), encode(token_secret));
+
+        let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("any key length");
+        mac.update(base_string.as_bytes());
+        base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    }
+}
@@ -0,0 +1,222 @@
+//! OAuth 2.0 authorization code flow with PKCE, as served at
+//! `twitter.com/i/oauth2/authorize` and `api.twitter.com/2/oauth2/token`.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{
+    now,
+    routes::{failure_response, gate},
+    Endpoint, Failure, PendingCode, Shared, State as MockState,
+};
+
+fn oauth2_error(status: StatusCode, error: &str, description: &str) -> Response {
+    (
+        status,
+        Json(json!({ "error": error, "error_description": description })),
+    )
+        .into_response()
+}
+
+fn invalid_grant() -> Response {
+    oauth2_error(
+        StatusCode::BAD_REQUEST,
+        "invalid_request",
+        "Value passed for the token was invalid.",
+    )
+}
+
+/// `url` with `params` appended to its query string.
+fn with_query(url: &str, params: &[(&str, &str)]) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}{}",
+        url,
+        separator,
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    )
+}
+
+pub(crate) fn issue_token(state: &mut MockState, lifetime: u64, scope: &str) -> (String, String) {
+    let id = state.next_id();
+    let access_token = format!("bearer-token-{}", id);
+    let refresh_token = format!("refresh-token-{}", id);
+    state
+        .bearer_tokens
+        .insert(access_token.clone(), now() + lifetime);
+    state
+        .refresh_tokens
+        .insert(refresh_token.clone(), scope.to_string());
+    (access_token, refresh_token)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AuthorizeQuery {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    scope: String,
+    state: String,
+    code_challenge: String,
+    code_challenge_method: String,
+}
+
+/// Stands in for the user approving (or cancelling) the app in a browser.
+pub(crate) async fn authorize(
+    State(shared): State<Shared>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Response {
+    let mut state = shared.state.lock().unwrap();
+    *state.hits.entry(Endpoint::OAuth2Authorize).or_default() += 1;
+
+    if query.response_type != "code" || query.client_id != shared.config.oauth2_client_id {
+        return oauth2_error(StatusCode::BAD_REQUEST, "invalid_request", "Invalid client");
+    }
+    if query.code_challenge_method != "S256" {
+        return oauth2_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "Only S256 code challenges are supported",
+        );
+    }
+
+    match state
+        .failures
+        .get_mut(&Endpoint::OAuth2Authorize)
+        .and_then(|q| q.pop_front())
+    {
+        Some(Failure::Denied) => {
+            let url = with_query(
+                &query.redirect_uri,
+                &[("error", "access_denied"), ("state", &query.state)],
+            );
+            return Redirect::to(&url).into_response();
+        }
+        Some(failure) => return failure_response(failure),
+        None => {}
+    }
+
+    let code = format!("code-{}", state.next_id());
+    state.oauth2_codes.insert(
+        code.clone(),
+        PendingCode {
+            code_challenge: query.code_challenge,
+            redirect_uri: query.redirect_uri.clone(),
+            scope: query.scope,
+        },
+    );
+    let url = with_query(
+        &query.redirect_uri,
+        &[("state", &query.state), ("code", &code)],
+    );
+    Redirect::to(&url).into_response()
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TokenForm {
+    grant_type: String,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    code_verifier: Option<String>,
+    refresh_token: Option<String>,
+}
+
+/// Whether `headers` carry HTTP Basic auth for the configured client.
+fn client_authenticated(shared: &Shared, headers: &HeaderMap) -> bool {
+    let expected = STANDARD.encode(format!(
+        "{}:{}",
+        shared.config.oauth2_client_id, shared.config.oauth2_client_secret
+    ));
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+        .is_some_and(|credentials| credentials == expected)
+}
+
+/// Exchanges an authorization code, or rotates a refresh token: the old one
+/// stops working as soon as it has been used.
+pub(crate) async fn token(
+    State(shared): State<Shared>,
+    headers: HeaderMap,
+    Form(form): Form<TokenForm>,
+) -> Response {
+    let mut state = shared.state.lock().unwrap();
+    *state.hits.entry(Endpoint::OAuth2Token).or_default() += 1;
+    if let Err(resp) = gate(
+        &mut state,
+        shared.config.rate_limit,
+        Endpoint::OAuth2Token,
+        false,
+    ) {
+        return resp;
+    }
+    if !client_authenticated(&shared, &headers) {
+        return oauth2_error(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized_client",
+            "Missing valid authorization header",
+        );
+    }
+
+    let scope = match form.grant_type.as_str() {
+        "authorization_code" => {
+            let Some(pending) = form
+                .code
+                .as_ref()
+                .and_then(|code| state.oauth2_codes.remove(code))
+            else {
+                return invalid_grant();
+            };
+            let challenge = form
+                .code_verifier
+                .as_ref()
+                .map(|verifier| URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())));
+            if challenge.as_ref() != Some(&pending.code_challenge)
+                || form.redirect_uri.as_ref() != Some(&pending.redirect_uri)
+            {
+                return invalid_grant();
+            }
+            pending.scope
+        }
+        "refresh_token" => {
+            match form
+                .refresh_token
+                .as_ref()
+                .and_then(|token| state.refresh_tokens.remove(token))
+            {
+                Some(scope) => scope,
+                None => return invalid_grant(),
+            }
+        }
+        _ => {
+            return oauth2_error(
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+                "Unsupported grant type",
+            )
+        }
+    };
+
+    let lifetime = shared.config.oauth2_token_lifetime;
+    let (access_token, refresh_token) = issue_token(&mut state, lifetime, &scope);
+    Json(json!({
+        "token_type": "bearer",
+        "expires_in": lifetime,
+        "access_token": access_token,
+        "scope": scope,
+        "refresh_token": refresh_token,
+    }))
+    .into_response()
+}
@@ -0,0 +1,991 @@
+// Helpers short-circuit with a ready-made `Response` as their error.
+#![allow(clippy::result_large_err)]
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    now,
+    oauth::{OAuthParams, SignedRequest},
+    oauth2, Endpoint, Failure, ForeignTweet, MockUser, PendingToken, RecordedMedia, RecordedTweet,
+    Shared, State as MockState, Window, WINDOW_SECS,
+};
+
+pub(crate) fn router(shared: Shared) -> Router {
+    Router::new()
+        .route("/oauth/request_token", post(request_token))
+        .route("/oauth/authenticate", get(authenticate))
+        .route("/oauth/access_token", post(access_token))
+        .route("/2/users/me", get(users_me))
+        .route("/2/tweets", post(create_tweet))
+        .route("/2/tweets/:id", get(lookup_tweet).delete(delete_tweet))
+        .route("/2/users/:id/likes", post(like))
+        .route("/2/users/:id/retweets", post(retweet))
+        .route("/2/users/:id/tweets", get(user_tweets))
+        .route("/2/users/:id/liked_tweets", get(liked_tweets))
+        .route("/2/users/:id/mentions", get(mentions))
+        .route("/1.1/media/upload.json", post(media_upload))
+        .route("/i/oauth2/authorize", get(oauth2::authorize))
+        .route("/2/oauth2/token", post(oauth2::token))
+        .with_state(shared)
+}
+
+/// Which token secret a request must be signed with.
+#[derive(Clone, Copy)]
+enum Credential {
+    /// Consumer secret only, as for `oauth/request_token`.
+    Consumer,
+    RequestToken,
+    AccessToken,
+}
+
+/// The parts of a request that enter the OAuth signature.
+struct Incoming<'a> {
+    method: &'a Method,
+    uri: &'a Uri,
+    headers: &'a HeaderMap,
+}
+
+fn v1_error(status: StatusCode, code: u32, message: &str) -> Response {
+    (
+        status,
+        Json(json!({ "errors": [{ "code": code, "message": message }] })),
+    )
+        .into_response()
+}
+
+fn problem(status: StatusCode, title: &str, detail: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "title": title,
+            "detail": detail,
+            "type": "about:blank",
+            "status": status.as_u16(),
+        })),
+    )
+        .into_response()
+}
+
+fn could_not_authenticate() -> Response {
+    v1_error(StatusCode::UNAUTHORIZED, 32, "Could not authenticate you.")
+}
+
+fn rate_limited(reset_at: u64) -> Response {
+    let mut resp = problem(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too Many Requests",
+        "Too Many Requests",
+    );
+    let headers = resp.headers_mut();
+    headers.insert("x-rate-limit-remaining", HeaderValue::from(0));
+    headers.insert("x-rate-limit-reset", HeaderValue::from(reset_at));
+    resp
+}
+
+fn duplicate_content() -> Response {
+    problem(
+        StatusCode::FORBIDDEN,
+        "Forbidden",
+        "You are not allowed to create a Tweet with duplicate content.",
+    )
+}
+
+pub(crate) fn failure_response(failure: Failure) -> Response {
+    match failure {
+        Failure::RateLimited { reset_at } => rate_limited(reset_at),
+        Failure::Unauthorized => problem(StatusCode::UNAUTHORIZED, "Unauthorized", "Unauthorized"),
+        Failure::Suspended => v1_error(
+            StatusCode::FORBIDDEN,
+            64,
+            "Your account is suspended and is not permitted to access this feature.",
+        ),
+        Failure::DuplicateContent => duplicate_content(),
+        Failure::Denied => problem(StatusCode::FORBIDDEN, "Forbidden", "Access denied"),
+        Failure::Status { status, body } => (
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            body,
+        )
+            .into_response(),
+    }
+}
+
+/// Counts the hit, checks the OAuth 1.0a signature or OAuth 2.0 bearer token,
+/// then applies any queued failure and the endpoint's rate limit. Returns the
+/// verified OAuth parameters (empty for bearer tokens) and the rate-limit
+/// headers for the success response.
+fn authorize(
+    shared: &Shared,
+    endpoint: Endpoint,
+    incoming: Incoming<'_>,
+    credential: Credential,
+) -> Result<(OAuthParams, HeaderMap), Response> {
+    let mut state = shared.state.lock().unwrap();
+    *state.hits.entry(endpoint).or_default() += 1;
+
+    let authorization = incoming
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if let Some(bearer) = authorization.strip_prefix("Bearer ") {
+        let valid = matches!(credential, Credential::AccessToken)
+            && state
+                .bearer_tokens
+                .get(bearer)
+                .is_some_and(|&expires_at| expires_at > now());
+        if !valid {
+            return Err(problem(
+                StatusCode::UNAUTHORIZED,
+                "Unauthorized",
+                "Unauthorized",
+            ));
+        }
+        return gate(&mut state, shared.config.rate_limit, endpoint, true)
+            .map(|headers| (OAuthParams::default(), headers));
+    }
+    let oauth = OAuthParams::parse(authorization).ok_or_else(could_not_authenticate)?;
+
+    let token_secret = match (credential, oauth.token()) {
+        (Credential::Consumer, None) => String::new(),
+        (Credential::RequestToken, Some(token)) => state
+            .request_tokens
+            .get(token)
+            .map(|pending| pending.secret.clone())
+            .ok_or_else(|| v1_error(StatusCode::UNAUTHORIZED, 89, "Invalid or expired token."))?,
+        (Credential::AccessToken, Some(token)) => {
+            state.access_tokens.get(token).cloned().ok_or_else(|| {
+                v1_error(StatusCode::UNAUTHORIZED, 89, "Invalid or expired token.")
+            })?
+        }
+        _ => return Err(could_not_authenticate()),
+    };
+
+    let host = incoming
+        .headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let base_url = format!("http://{}{}", host, incoming.uri.path());
+    let params: Vec<(String, String)> = incoming
+        .uri
+        .query()
+        .and_then(|q| serde_urlencoded::from_str(q).ok())
+        .unwrap_or_default();
+    let request = SignedRequest {
+        method: incoming.method.as_str(),
+        base_url: &base_url,
+        params,
+        oauth: &oauth,
+    };
+    if !request.verify(
+        &shared.config.consumer_key,
+        &shared.config.consumer_secret,
+        &token_secret,
+    ) {
+        return Err(could_not_authenticate());
+    }
+    let nonce = format!(
+        "{}:{}",
+        oauth.get("oauth_timestamp").unwrap_or_default(),
+        oauth.get("oauth_nonce").unwrap_or_default()
+    );
+    if !state.nonces.insert(nonce) {
+        return Err(could_not_authenticate());
+    }
+
+    let windowed = !matches!(
+        endpoint,
+        Endpoint::RequestToken | Endpoint::AccessToken | Endpoint::Authenticate
+    );
+    gate(&mut state, shared.config.rate_limit, endpoint, windowed).map(|headers| (oauth, headers))
+}
+
+/// Applies queued failures and, if `windowed`, the endpoint's rate limit.
+pub(crate) fn gate(
+    state: &mut MockState,
+    limit: u32,
+    endpoint: Endpoint,
+    windowed: bool,
+) -> Result<HeaderMap, Response> {
+    if let Some(failure) = state
+        .failures
+        .get_mut(&endpoint)
+        .and_then(|q| q.pop_front())
+    {
+        return Err(failure_response(failure));
+    }
+    let mut headers = HeaderMap::new();
+    if !windowed {
+        return Ok(headers);
+    }
+
+    let now = now();
+    let window = state.windows.entry(endpoint).or_insert(Window {
+        remaining: limit,
+        reset_at: now + WINDOW_SECS,
+    });
+    if window.reset_at <= now {
+        *window = Window {
+            remaining: limit,
+            reset_at: now + WINDOW_SECS,
+        };
+    }
+    if window.remaining == 0 {
+        return Err(rate_limited(window.reset_at));
+    }
+    window.remaining -= 1;
+
+    headers.insert("x-rate-limit-limit", HeaderValue::from(limit));
+    headers.insert(
+        "x-rate-limit-remaining",
+        HeaderValue::from(window.remaining),
+    );
+    headers.insert("x-rate-limit-reset", HeaderValue::from(window.reset_at));
+    Ok(headers)
+}
+
+pub(crate) fn issue_access_token(state: &mut MockState) -> (String, String) {
+    let id = state.next_id();
+    let token = format!("access-token-{}", id);
+    let secret = format!("access-secret-{}", id);
+    state.access_tokens.insert(token.clone(), secret.clone());
+    (token, secret)
+}
+
+fn urlencoded(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/x-www-form-urlencoded")],
+        body,
+    )
+        .into_response()
+}
+
+async fn request_token(
+    State(shared): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let oauth = match authorize(
+        &shared,
+        Endpoint::RequestToken,
+        incoming,
+        Credential::Consumer,
+    ) {
+        Ok((oauth, _)) => oauth,
+        Err(resp) => return resp,
+    };
+    let Some(callback) = oauth.get("oauth_callback") else {
+        return v1_error(StatusCode::BAD_REQUEST, 417, "Callback URL not approved.");
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    let id = state.next_id();
+    let token = format!("request-token-{}", id);
+    let secret = format!("request-secret-{}", id);
+    state.request_tokens.insert(
+        token.clone(),
+        PendingToken {
+            secret: secret.clone(),
+            callback: callback.to_string(),
+            verifier: None,
+        },
+    );
+    urlencoded(format!(
+        "oauth_token={}&oauth_token_secret={}&oauth_callback_confirmed=true",
+        token, secret
+    ))
+}
+
+#[derive(Deserialize)]
+struct AuthenticateQuery {
+    oauth_token: String,
+}
+
+/// Stands in for the user approving (or cancelling) the app in a browser.
+async fn authenticate(
+    State(shared): State<Shared>,
+    Query(query): Query<AuthenticateQuery>,
+) -> Response {
+    let mut state = shared.state.lock().unwrap();
+    *state.hits.entry(Endpoint::Authenticate).or_default() += 1;
+
+    let Some(pending) = state.request_tokens.get(&query.oauth_token).cloned() else {
+        return v1_error(StatusCode::UNAUTHORIZED, 89, "Invalid or expired token.");
+    };
+    let separator = if pending.callback.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+
+    match state
+        .failures
+        .get_mut(&Endpoint::Authenticate)
+        .and_then(|q| q.pop_front())
+    {
+        Some(Failure::Denied) => {
+            state.request_tokens.remove(&query.oauth_token);
+            return Redirect::to(&format!(
+                "{}{}denied={}",
+                pending.callback, separator, query.oauth_token
+            ))
+            .into_response();
+        }
+        Some(failure) => return failure_response(failure),
+        None => {}
+    }
+
+    let verifier = format!("verifier-{}", state.next_id());
+    if let Some(pending) = state.request_tokens.get_mut(&query.oauth_token) {
+        pending.verifier = Some(verifier.clone());
+    }
+    Redirect::to(&format!(
+        "{}{}oauth_token={}&oauth_verifier={}",
+        pending.callback, separator, query.oauth_token, verifier
+    ))
+    .into_response()
+}
+
+async fn access_token(
+    State(shared): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let oauth = match authorize(
+        &shared,
+        Endpoint::AccessToken,
+        incoming,
+        Credential::RequestToken,
+    ) {
+        Ok((oauth, _)) => oauth,
+        Err(resp) => return resp,
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    let request_token = oauth.token().unwrap_or_default().to_string();
+    let verified = state
+        .request_tokens
+        .get(&request_token)
+        .and_then(|pending| pending.verifier.as_deref())
+        .is_some_and(|verifier| Some(verifier) == oauth.get("oauth_verifier"));
+    if !verified {
+        return v1_error(StatusCode::UNAUTHORIZED, 89, "Invalid or expired token.");
+    }
+    // Request tokens are single use
+    state.request_tokens.remove(&request_token);
+
+    let (token, secret) = issue_access_token(&mut state);
+    let user = &shared.config.user;
+    urlencoded(format!(
+        "oauth_token={}&oauth_token_secret={}&user_id={}&screen_name={}",
+        token, secret, user.id, user.username
+    ))
+}
+
+async fn users_me(
+    State(shared): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::UsersMe,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    let user = shared
+        .state
+        .lock()
+        .unwrap()
+        .profile
+        .clone()
+        .unwrap_or_else(|| shared.config.user.clone());
+    (
+        rate_headers,
+        Json(json!({
+            "data": {
+                "id": user.id,
+                "name": user.name,
+                "username": user.username,
+                "profile_image_url": user.profile_image_url,
+                "description": user.description,
+            }
+        })),
+    )
+        .into_response()
+}
+
+async fn create_tweet(
+    State(shared): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(&shared, Endpoint::Tweets, incoming, Credential::AccessToken)
+    {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(&body) else {
+        return problem(
+            StatusCode::BAD_REQUEST,
+            "Invalid Request",
+            "Body is not JSON",
+        );
+    };
+    let Some(text) = body["text"].as_str().map(str::to_string) else {
+        return problem(StatusCode::BAD_REQUEST, "Invalid Request", "Missing text");
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    if state.tweets.iter().any(|tweet| tweet.text == text) {
+        return duplicate_content();
+    }
+    let id = state.next_id();
+    let mut timeline_entry = json!({ "id": id, "text": text, "conversation_id": id });
+    if let Some(quoted) = body["quote_tweet_id"].as_str() {
+        timeline_entry["referenced_tweets"] = json!([{ "type": "quoted", "id": quoted }]);
+    }
+    if let Some(replied_to) = body["reply"]["in_reply_to_tweet_id"].as_str() {
+        timeline_entry["conversation_id"] = conversation_id(&state, replied_to).into();
+        timeline_entry["referenced_tweets"] = json!([{ "type": "replied_to", "id": replied_to }]);
+    }
+    state.timeline.push(timeline_entry);
+    state.tweets.push(RecordedTweet {
+        id: id.clone(),
+        text: text.clone(),
+        body,
+    });
+    (
+        StatusCode::CREATED,
+        rate_headers,
+        Json(json!({
+            "data": { "id": id, "text": text, "edit_history_tweet_ids": [id] }
+        })),
+    )
+        .into_response()
+}
+
+/// Deletes one of the account's own tweets.
+async fn delete_tweet(
+    State(shared): State<Shared>,
+    Path(id): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::DeleteTweet,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    let Some(index) = state
+        .timeline
+        .iter()
+        .position(|tweet| tweet["id"] == id.as_str())
+    else {
+        return problem(
+            StatusCode::NOT_FOUND,
+            "Not Found Error",
+            &format!("Could not find tweet with id: [{}].", id),
+        );
+    };
+    state.timeline.remove(index);
+    state.tweets.retain(|tweet| tweet.id != id);
+    state.deleted.push(id);
+    (rate_headers, Json(json!({ "data": { "deleted": true } }))).into_response()
+}
+
+#[derive(Deserialize)]
+struct TweetIdBody {
+    tweet_id: String,
+}
+
+async fn react(
+    shared: Shared,
+    endpoint: Endpoint,
+    user_id: String,
+    incoming: Incoming<'_>,
+    body: String,
+) -> Response {
+    let rate_headers = match authorize(&shared, endpoint, incoming, Credential::AccessToken) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    if user_id != shared.config.user.id {
+        return problem(
+            StatusCode::FORBIDDEN,
+            "Forbidden",
+            "You are not permitted to perform this action.",
+        );
+    }
+    let Ok(TweetIdBody { tweet_id }) = serde_json::from_str(&body) else {
+        return problem(
+            StatusCode::BAD_REQUEST,
+            "Invalid Request",
+            "Missing tweet_id",
+        );
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    let data = if endpoint == Endpoint::Likes {
+        state.likes.push(tweet_id);
+        json!({ "liked": true })
+    } else {
+        let id = state.next_id();
+        state.timeline.push(json!({
+            "id": id,
+            "text": format!("RT {}", tweet_id),
+            "referenced_tweets": [{ "type": "retweeted", "id": tweet_id }],
+        }));
+        state.retweets.push(tweet_id);
+        json!({ "retweeted": true })
+    };
+    (rate_headers, Json(json!({ "data": data }))).into_response()
+}
+
+async fn like(
+    State(shared): State<Shared>,
+    Path(user_id): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    react(shared, Endpoint::Likes, user_id, incoming, body).await
+}
+
+async fn retweet(
+    State(shared): State<Shared>,
+    Path(user_id): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    react(shared, Endpoint::Retweets, user_id, incoming, body).await
+}
+
+#[derive(Deserialize)]
+struct TimelineQuery {
+    since_id: Option<u64>,
+}
+
+/// `{"data": [...], "meta": {...}}` newest first, omitting `data` when empty
+/// like the real API.
+fn tweet_list(rate_headers: HeaderMap, tweets: Vec<serde_json::Value>) -> Response {
+    let mut body = json!({ "meta": { "result_count": tweets.len() } });
+    if let (Some(newest), Some(oldest)) = (tweets.first(), tweets.last()) {
+        body["meta"]["newest_id"] = newest["id"].clone();
+        body["meta"]["oldest_id"] = oldest["id"].clone();
+        body["data"] = json!(tweets);
+    }
+    (rate_headers, Json(body)).into_response()
+}
+
+async fn user_tweets(
+    State(shared): State<Shared>,
+    Path(user_id): Path<String>,
+    Query(query): Query<TimelineQuery>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::UserTweets,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    if user_id != shared.config.user.id {
+        return tweet_list(rate_headers, Vec::new());
+    }
+    let since_id = query.since_id.unwrap_or(0);
+    let tweets = shared
+        .state
+        .lock()
+        .unwrap()
+        .timeline
+        .iter()
+        .rev()
+        .filter(|tweet| {
+            tweet["id"]
+                .as_str()
+                .and_then(|id| id.parse::<u64>().ok())
+                .is_some_and(|id| id > since_id)
+        })
+        .take(100)
+        .cloned()
+        .collect();
+    tweet_list(rate_headers, tweets)
+}
+
+async fn liked_tweets(
+    State(shared): State<Shared>,
+    Path(user_id): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::LikedTweets,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    if user_id != shared.config.user.id {
+        return tweet_list(rate_headers, Vec::new());
+    }
+    let tweets = shared
+        .state
+        .lock()
+        .unwrap()
+        .likes
+        .iter()
+        .rev()
+        .take(100)
+        .map(|id| json!({ "id": id, "text": "" }))
+        .collect();
+    tweet_list(rate_headers, tweets)
+}
+
+async fn media_upload(
+    State(shared): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::MediaUpload,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+
+    let mut bytes = None;
+    let mut additional_owners = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("media") => bytes = field.bytes().await.ok().map(|b| b.to_vec()),
+            Some("additional_owners") => additional_owners = field.text().await.ok(),
+            _ => {}
+        }
+    }
+    let Some(bytes) = bytes else {
+        return v1_error(StatusCode::BAD_REQUEST, 38, "media parameter is missing.");
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    let media_id = state.next_id();
+    let size = bytes.len();
+    state.media.push(RecordedMedia {
+        media_id: media_id.clone(),
+        bytes,
+        additional_owners,
+    });
+    (
+        rate_headers,
+        Json(json!({
+            "media_id": media_id.parse::<u64>().unwrap_or_default(),
+            "media_id_string": media_id,
+            "size": size,
+        })),
+    )
+        .into_response()
+}
+
+/// A tweet by ID from the account's timeline, its mentions or other
+/// people's tweets.
+pub(crate) fn find_tweet(state: &MockState, id: &str) -> Option<serde_json::Value> {
+    state
+        .timeline
+        .iter()
+        .chain(
+            state
+                .mentions
+                .iter()
+                .chain(&state.others)
+                .map(|foreign| &foreign.tweet),
+        )
+        .find(|tweet| tweet["id"] == id)
+        .cloned()
+}
+
+/// Builds a tweet by `author`, in the conversation of `in_reply_to` if set.
+pub(crate) fn foreign_tweet(
+    state: &mut MockState,
+    author: &MockUser,
+    text: &str,
+    in_reply_to: Option<&str>,
+) -> ForeignTweet {
+    let id = state.next_id();
+    let mut tweet = json!({
+        "id": id,
+        "text": text,
+        "author_id": author.id,
+        "conversation_id": id,
+    });
+    if let Some(replied_to) = in_reply_to {
+        tweet["conversation_id"] = conversation_id(state, replied_to).into();
+        tweet["referenced_tweets"] = json!([{ "type": "replied_to", "id": replied_to }]);
+    }
+    ForeignTweet {
+        tweet,
+        author: author.clone(),
+    }
+}
+
+/// The conversation a reply to `replied_to` joins.
+fn conversation_id(state: &MockState, replied_to: &str) -> String {
+    find_tweet(state, replied_to)
+        .and_then(|tweet| tweet["conversation_id"].as_str().map(str::to_string))
+        .unwrap_or_else(|| replied_to.to_string())
+}
+
+#[derive(Deserialize)]
+struct LookupQuery {
+    expansions: Option<String>,
+}
+
+/// A single tweet, with its author under `includes` when expanded. Unknown
+/// IDs get a 200 with only `errors`, as from Twitter.
+async fn lookup_tweet(
+    State(shared): State<Shared>,
+    Path(id): Path<String>,
+    Query(query): Query<LookupQuery>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::TweetLookup,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+
+    let state = shared.state.lock().unwrap();
+    let Some(mut tweet) = find_tweet(&state, &id) else {
+        return (
+            rate_headers,
+            Json(json!({
+                "errors": [{
+                    "value": id,
+                    "detail": format!("Could not find tweet with id: [{}].", id),
+                    "title": "Not Found Error",
+                    "resource_type": "tweet",
+                    "parameter": "id",
+                    "resource_id": id,
+                    "type": "https://api.twitter.com/2/problems/resource-not-found",
+                }]
+            })),
+        )
+            .into_response();
+    };
+    let author = state
+        .mentions
+        .iter()
+        .chain(&state.others)
+        .find(|foreign| foreign.tweet["id"] == id.as_str())
+        .map(|foreign| foreign.author.clone())
+        .unwrap_or_else(|| {
+            state
+                .profile
+                .clone()
+                .unwrap_or_else(|| shared.config.user.clone())
+        });
+    tweet["author_id"] = author.id.clone().into();
+
+    let mut body = json!({ "data": tweet });
+    if query
+        .expansions
+        .is_some_and(|expansions| expansions.split(',').any(|e| e == "author_id"))
+    {
+        body["includes"] = json!({
+            "users": [{
+                "id": author.id,
+                "username": author.username,
+                "name": author.name,
+            }]
+        });
+    }
+    (rate_headers, Json(body)).into_response()
+}
+
+#[derive(Deserialize)]
+struct MentionsQuery {
+    since_id: Option<u64>,
+    max_results: Option<usize>,
+    pagination_token: Option<String>,
+}
+
+/// Newest first, paged by `max_results`, with authors and replied-to tweets
+/// under `includes`.
+async fn mentions(
+    State(shared): State<Shared>,
+    Path(user_id): Path<String>,
+    Query(query): Query<MentionsQuery>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let incoming = Incoming {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+    };
+    let rate_headers = match authorize(
+        &shared,
+        Endpoint::Mentions,
+        incoming,
+        Credential::AccessToken,
+    ) {
+        Ok((_, rate_headers)) => rate_headers,
+        Err(resp) => return resp,
+    };
+    if user_id != shared.config.user.id {
+        return tweet_list(rate_headers, Vec::new());
+    }
+    let since_id = query.since_id.unwrap_or(0);
+    let page_size = query.max_results.unwrap_or(10);
+    let offset: usize = query
+        .pagination_token
+        .and_then(|token| token.parse().ok())
+        .unwrap_or(0);
+
+    let state = shared.state.lock().unwrap();
+    let matching: Vec<&ForeignTweet> = state
+        .mentions
+        .iter()
+        .rev()
+        .filter(|mention| {
+            mention.tweet["id"]
+                .as_str()
+                .and_then(|id| id.parse::<u64>().ok())
+                .is_some_and(|id| id > since_id)
+        })
+        .collect();
+    let page: Vec<&ForeignTweet> = matching
+        .iter()
+        .skip(offset)
+        .take(page_size)
+        .copied()
+        .collect();
+
+    let mut body = json!({ "meta": { "result_count": page.len() } });
+    if let (Some(newest), Some(oldest)) = (page.first(), page.last()) {
+        body["meta"]["newest_id"] = newest.tweet["id"].clone();
+        body["meta"]["oldest_id"] = oldest.tweet["id"].clone();
+        let tweets: Vec<_> = page.iter().map(|mention| mention.tweet.clone()).collect();
+        let users: Vec<_> = page
+            .iter()
+            .map(|mention| {
+                json!({
+                    "id": mention.author.id,
+                    "username": mention.author.username,
+                    "name": mention.author.name,
+                })
+            })
+            .collect();
+        let replied_to: Vec<_> = page
+            .iter()
+            .filter_map(|mention| mention.tweet["referenced_tweets"][0]["id"].as_str())
+            .filter_map(|id| find_tweet(&state, id))
+            .collect();
+        body["data"] = json!(tweets);
+        body["includes"] = json!({ "users": users, "tweets": replied_to });
+    }
+    if offset + page_size < matching.len() {
+        body["meta"]["next_token"] = (offset + page_size).to_string().into();
+    }
+    (rate_headers, Json(body)).into_response()
+}